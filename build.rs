@@ -0,0 +1,34 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "capi")]
+    capi_header();
+}
+
+/// C header of the `ipr_` functions, so C/.NET/Go callers get the signatures
+/// and status codes from the Rust source. Written to OUT_DIR; a capi test
+/// checks that the committed include/inventory_processor.h matches it.
+#[cfg(feature = "capi")]
+fn capi_header() {
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    let config = cbindgen::Config {
+        enumeration: cbindgen::EnumConfig {
+            prefix_with_name: true,
+            rename_variants: cbindgen::RenameRule::ScreamingSnakeCase,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src("src/capi.rs")
+        .with_language(cbindgen::Language::C)
+        .with_cpp_compat(true)
+        .with_include_guard("INVENTORY_PROCESSOR_H")
+        .with_header("/* auto-generated from rust-inventory-processor, do not edit */")
+        .generate()
+        .expect("Failed to generate C header")
+        .write_to_file(
+            std::path::Path::new(&std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo"))
+                .join("inventory_processor.h"),
+        );
+}
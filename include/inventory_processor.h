@@ -0,0 +1,76 @@
+/* auto-generated from rust-inventory-processor, do not edit */
+
+#ifndef INVENTORY_PROCESSOR_H
+#define INVENTORY_PROCESSOR_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of the `ipr_` calls; anything but `IPR_STATUS_OK` sets the
+ * last-error message.
+ */
+typedef enum IprStatus {
+  IPR_STATUS_OK = 0,
+  /**
+   * A required pointer argument was NULL.
+   */
+  IPR_STATUS_NULL_POINTER = 1,
+  /**
+   * The input is not valid UTF-8.
+   */
+  IPR_STATUS_INVALID_UTF8 = 2,
+  /**
+   * The input does not match the JSON contract; the message is the error
+   * payload (`{ error, issues }`) as JSON.
+   */
+  IPR_STATUS_INVALID_INPUT = 3,
+  /**
+   * Processing panicked.
+   */
+  IPR_STATUS_PROCESSING_FAILED = 4,
+} IprStatus;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Processes the NUL-terminated UTF-8 `input_json` like
+ * process_inventory_history_wasm. On `IPR_STATUS_OK`, `*output_json` is the
+ * result JSON, to be released with ipr_free_string; otherwise it is NULL.
+ *
+ * # Safety
+ *
+ * `input_json` must be NULL or point to a NUL-terminated string, and
+ * `output_json` must be NULL or valid for writing a pointer.
+ */
+enum IprStatus ipr_process_json(const char *input_json, char **output_json);
+
+/**
+ * Releases a string returned by ipr_process_json; NULL is ignored.
+ *
+ * # Safety
+ *
+ * `s` must be NULL or a string from ipr_process_json not freed before.
+ */
+void ipr_free_string(char *s);
+
+/**
+ * Message of the last failed `ipr_` call on this thread, or NULL. Owned by
+ * the library and valid until the next ipr_process_json call on the thread.
+ */
+const char *ipr_last_error_message(void);
+
+/**
+ * Library version, e.g. "0.1.0" (static string).
+ */
+const char *ipr_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* INVENTORY_PROCESSOR_H */
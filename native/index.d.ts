@@ -4,3 +4,13 @@
 /* auto-generated by NAPI-RS */
 
 export declare function processInventoryHistory(inputJson: string): string
+export declare function processInventoryHistoryCsv(inputJson: string): string
+export declare function processInventoryHistoryXlsx(inputJson: string): Buffer
+export declare function processInventoryHistoryNdjson(input: string): string
+export declare function processInventoryHistoryColumnar(inputJson: string, createdAt: Float64Array, stockQty: Int32Array, oldStatus: Int32Array, newStatus: Int32Array, quantity: Int32Array, goodsIssueIndex: Int32Array, goodsIssueIds: Array<string>): string
+export declare function costingReport(inputJson: string): string
+export declare class NdjsonInventoryProcessor {
+  constructor()
+  push(chunk: string): string
+  finish(): string
+}
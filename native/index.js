@@ -310,6 +310,12 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { processInventoryHistory } = nativeBinding
+const { processInventoryHistory, processInventoryHistoryCsv, processInventoryHistoryXlsx, processInventoryHistoryNdjson, processInventoryHistoryColumnar, costingReport, NdjsonInventoryProcessor } = nativeBinding
 
 module.exports.processInventoryHistory = processInventoryHistory
+module.exports.processInventoryHistoryCsv = processInventoryHistoryCsv
+module.exports.processInventoryHistoryXlsx = processInventoryHistoryXlsx
+module.exports.processInventoryHistoryNdjson = processInventoryHistoryNdjson
+module.exports.processInventoryHistoryColumnar = processInventoryHistoryColumnar
+module.exports.costingReport = costingReport
+module.exports.NdjsonInventoryProcessor = NdjsonInventoryProcessor
@@ -0,0 +1,17 @@
+//! Writes the TypeScript declarations of the JSON input and output, generated
+//! from the Rust structs, to the given file (stdout when omitted).
+
+use std::{env, fs, io::Write};
+
+fn main() {
+    let types = format!(
+        "/* auto-generated from rust-inventory-processor, do not edit */\n\n{}",
+        rust_inventory_processor::typescript_declarations()
+    );
+    match env::args_os().nth(1) {
+        Some(path) => fs::write(&path, types).expect("Failed to write declarations"),
+        None => std::io::stdout()
+            .write_all(types.as_bytes())
+            .expect("Failed to write declarations"),
+    }
+}
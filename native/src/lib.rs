@@ -3,15 +3,14 @@
 
 #![deny(clippy::all)]
 
-use std::panic::catch_unwind;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
-use rust_inventory_processor::process_inventory_history_native;
+use rust_inventory_processor as processor;
 
-#[napi]
-pub fn process_inventory_history(input_json: String) -> Result<String> {
-    catch_unwind(|| process_inventory_history_native(&input_json)).map_err(|e| {
+fn run<T>(f: impl FnOnce() -> T) -> Result<T> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|e| {
         let msg = if let Some(s) = e.downcast_ref::<&str>() {
             s.to_string()
         } else if let Some(s) = e.downcast_ref::<String>() {
@@ -22,3 +21,82 @@ pub fn process_inventory_history(input_json: String) -> Result<String> {
         Error::from_reason(msg)
     })
 }
+
+#[napi]
+pub fn process_inventory_history(input_json: String) -> Result<String> {
+    run(|| processor::process_inventory_history_native(&input_json))
+}
+
+#[napi]
+pub fn process_inventory_history_csv(input_json: String) -> Result<String> {
+    run(|| processor::process_inventory_history_csv_native(&input_json))
+}
+
+#[napi]
+pub fn process_inventory_history_xlsx(input_json: String) -> Result<Buffer> {
+    run(|| processor::process_inventory_history_xlsx_native(&input_json).into())
+}
+
+#[napi]
+pub fn process_inventory_history_ndjson(input: String) -> Result<String> {
+    processor::process_inventory_history_ndjson(&input).map_err(Error::from_reason)
+}
+
+#[napi]
+#[allow(clippy::too_many_arguments)]
+pub fn process_inventory_history_columnar(
+    input_json: String,
+    created_at: Float64Array,
+    stock_qty: Int32Array,
+    old_status: Int32Array,
+    new_status: Int32Array,
+    quantity: Int32Array,
+    goods_issue_index: Int32Array,
+    goods_issue_ids: Vec<String>,
+) -> Result<String> {
+    processor::process_inventory_history_columnar(
+        &input_json,
+        &created_at,
+        &stock_qty,
+        &old_status,
+        &new_status,
+        &quantity,
+        &goods_issue_index,
+        goods_issue_ids,
+    )
+    .map_err(Error::from_reason)
+}
+
+#[napi]
+pub fn costing_report(input_json: String) -> Result<String> {
+    run(|| processor::costing_report_native(&input_json))
+}
+
+#[napi]
+pub struct NdjsonInventoryProcessor {
+    inner: processor::NdjsonInventoryProcessor,
+}
+
+#[napi]
+impl NdjsonInventoryProcessor {
+    #[napi(constructor)]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            inner: processor::NdjsonInventoryProcessor::new(),
+        }
+    }
+
+    // Invalid input comes back as an error with the state untouched; the
+    // stateful object is not run under catch_unwind, which could leave it
+    // half-updated.
+    #[napi]
+    pub fn push(&mut self, chunk: String) -> Result<String> {
+        self.inner.push(&chunk).map_err(Error::from_reason)
+    }
+
+    #[napi]
+    pub fn finish(&mut self) -> Result<String> {
+        self.inner.finish().map_err(Error::from_reason)
+    }
+}
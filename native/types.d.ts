@@ -0,0 +1,310 @@
+/* auto-generated from rust-inventory-processor, do not edit */
+
+export interface HandleInventoryListInputSerializable {
+    inventory: InventoryItem;
+    inventoryList: unknown[];
+    asinOutboundList: string[];
+    goods: unknown;
+    goodsReceipt: GoodsReceiptItem;
+    supplier: unknown;
+    customer: unknown;
+    inventoryHistoryList?: InventoryHistoryItem[];
+    receiptOrders: unknown[];
+    inventoryId: string;
+    inventoryIdsMap: Record<string, number>;
+    fromDate?: number | string | null;
+    toDate?: number | string | null;
+    volumeTolerance?: number;
+    numericMode?: string;
+    precision?: number;
+    pricePrecision?: number;
+    roundingMode?: string;
+    currency?: string;
+    fxRates?: FxRateItem[];
+    valuePrecision?: number;
+}
+
+export interface InventoryItem {
+    id?: string;
+    creatorId?: number;
+    createdAt?: number | string | null;
+    updatedAt?: number | string | null;
+    status?: number;
+    shelfCode?: string;
+    customerId?: number;
+    stockQty?: number;
+    stockCbm?: number;
+    goodsReceiptId?: string;
+    goodsIssueId?: string;
+    goodsId?: string;
+    duration?: number;
+    exportAt?: number | string | null;
+    asin?: string;
+    supplierId?: string;
+    asinOutbound?: string;
+    indexCustomsDeclaration?: string;
+    unitPrice?: number;
+    innerQtyOnMas?: number;
+    poNo?: string;
+    masterDimension?: DimensionItem;
+    dimension?: DimensionItem;
+    volume?: number;
+    masterVolume?: number;
+    masterQty?: number;
+    doNo?: string;
+    dimensionUnit?: string;
+    cartonsPerPallet?: number;
+    palletFootprint?: number;
+    currency?: string;
+}
+
+export interface DimensionItem {
+    length?: number;
+    width?: number;
+    height?: number;
+}
+
+export interface GoodsReceiptItem {
+    importedAt?: number | string | null;
+}
+
+export interface FxRateItem {
+    currency: string;
+    rate: number;
+    effectiveAt?: number | string;
+}
+
+export interface InventoryHistoryItem {
+    id: string;
+    createdAt: number | string;
+    stockQty: number;
+    oldStatus: number;
+    newStatus: number;
+    quantity: number;
+    goodsIssueId?: string;
+}
+
+export interface ProcessedInventoryHistoryResultSerializable {
+    entries: Record<string, ProcessedEntry>;
+    totalDuration: number;
+    warnings: string[];
+    goodsIssueLedger: Record<string, GoodsIssueLedgerSerializable>;
+}
+
+export interface GoodsIssueLedgerSerializable {
+    allocatedQty: number;
+    releasedQty: number;
+    disposedQty: number;
+    shippedQty: number;
+    openQty: number;
+    allocatedAt: number | null;
+    releasedAt: number | null;
+    disposedAt: number | null;
+    shippedAt: number | null;
+}
+
+export interface ProcessedEntry {
+    data: InventoryDataSerializable;
+    goodsIssueIds: string[];
+}
+
+export interface InventoryDataSerializable {
+    openingStock: number;
+    openingCbm: number;
+    openingMasterQty: number;
+    openingPalletQty: number;
+    asin: string;
+    asinOutbound: string[];
+    unitPrice: number;
+    receivedDate: number;
+    innerQtyOnMas: number;
+    date: number;
+    lineInCd: string;
+    poNo: string;
+    masterDimension: DimensionSerializable;
+    dimension: DimensionSerializable;
+    inboundQty: number;
+    inboundCbm: number;
+    inboundMasterQty: number;
+    inboundPalletQty: number;
+    closingStock: number;
+    closingCbm: number;
+    closingMasterQty: number;
+    closingPalletQty: number;
+    allocatedQty: number;
+    allocatedCbm: number;
+    allocatedMasterQty: number;
+    disposalStock: number;
+    disposalCbm: number;
+    disposalMasterQty: number;
+    restoreStockQty: number;
+    restoreStockCbm: number;
+    restoreMasterQty: number;
+    outboundQty: number;
+    outboundCbm: number;
+    outboundMasterQty: number;
+    outboundPalletQty: number;
+    palletFootprint: number;
+    storageTimeDays: number;
+    currency: string;
+    openingValue: number;
+    inboundValue: number;
+    outboundValue: number;
+    disposalValue: number;
+    closingValue: number;
+    closingBalances: StatusBalancesSerializable;
+}
+
+export interface DimensionSerializable {
+    length: number;
+    width: number;
+    height: number;
+}
+
+export interface StatusBalancesSerializable {
+    availableQty: number;
+    onHandQty: number;
+    damagedQty: number;
+    returnQty: number;
+    liquidationQty: number;
+    exportedQty: number;
+}
+
+export interface AgingReportInputSerializable {
+    inventories: HandleInventoryListInputSerializable[];
+    asOf?: number | string | null;
+    fromDate?: number | string | null;
+    toDate?: number | string | null;
+    agingBuckets?: AgingBucketItem[];
+}
+
+export interface AgingBucketItem {
+    label: string;
+    minDays: number;
+    maxDays?: number;
+}
+
+export interface AgingReportSerializable {
+    asOf: number;
+    date: string;
+    buckets: AgingBucketTotalSerializable[];
+    totalQty: number;
+    totalCbm: number;
+}
+
+export interface AgingBucketTotalSerializable {
+    label: string;
+    quantity: number;
+    cbm: number;
+}
+
+export interface CostingInputSerializable {
+    inventories: HandleInventoryListInputSerializable[];
+    method?: 'fifo' | 'lifo' | 'weightedAverage';
+}
+
+export interface CostingReportSerializable {
+    issues: CostedIssueSerializable[];
+    restores: CostedIssueSerializable[];
+    goodsIssueCosts: Record<string, GoodsIssueCostSerializable>;
+    remainingLayers: CostLayerSerializable[];
+    costOfGoodsIssued: number;
+    disposalCost: number;
+    remainingValue: number;
+}
+
+export interface CostedIssueSerializable {
+    inventoryId: string;
+    goodsIssueId: string;
+    createdAt: number;
+    oldStatus: number;
+    newStatus: number;
+    quantity: number;
+    cost: number;
+    uncostedQty: number;
+    layers: CostLayerConsumptionSerializable[];
+}
+
+export interface CostLayerConsumptionSerializable {
+    inventoryId: string;
+    receivedAt: number;
+    quantity: number;
+    unitCost: number;
+    cost: number;
+}
+
+export interface CostLayerSerializable {
+    inventoryId: string;
+    receivedAt: number;
+    unitCost: number;
+    remainingQty: number;
+}
+
+export interface GoodsIssueCostSerializable {
+    quantity: number;
+    cost: number;
+}
+
+export interface SnapshotInputSerializable extends HandleInventoryListInputSerializable {
+    at: number | string;
+}
+
+export interface InventorySnapshotSerializable extends StatusBalancesSerializable {
+    at: number;
+    date: string;
+    stockQty: number;
+    stockCbm: number;
+    allocatedByGoodsIssue: Record<string, number>;
+}
+
+export interface IncrementalInputSerializable extends HandleInventoryListInputSerializable {
+    checkpoint?: ProcessorCheckpoint;
+}
+
+export interface IncrementalResultSerializable extends ProcessedInventoryHistoryResultSerializable {
+    checkpoint: ProcessorCheckpoint;
+}
+
+/**
+ * Processor state returned by the incremental API; store it unchanged and
+ * pass it back with the next call.
+ */
+export type ProcessorCheckpoint = Record<string, unknown>;
+
+export interface CsvInputSerializable extends HandleInventoryListInputSerializable {
+    csvOptions?: CsvOptionsItem;
+}
+
+export interface CsvOptionsItem {
+    columns?: string[];
+    language?: 'en' | 'vi';
+    delimiter?: string;
+    decimalSeparator?: string;
+    precision?: number;
+    includeHeader?: boolean;
+    byteOrderMark?: boolean;
+}
+
+export interface XlsxInputSerializable {
+    inventories: HandleInventoryListInputSerializable[];
+    xlsxOptions?: XlsxOptionsItem;
+}
+
+export interface XlsxOptionsItem {
+    columns?: string[];
+    language?: 'en' | 'vi';
+}
+
+export type NdjsonInputLine = ({ type: "header" } & HandleInventoryListInputSerializable) | ({ type: "event" } & InventoryHistoryItem);
+
+export type NdjsonOutputLine = ({ type: "entry" } & { inventoryId: string; key: string } & ProcessedEntry) | { type: "summary"; inventoryId: string; totalDuration: number; warnings: string[]; goodsIssueLedger: Record<string, GoodsIssueLedgerSerializable> };
+
+export interface ErrorPayloadSerializable {
+    error: string;
+    issues: ValidationIssueSerializable[];
+}
+
+export interface ValidationIssueSerializable {
+    path: string;
+    message: string;
+}
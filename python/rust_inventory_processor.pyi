@@ -0,0 +1,72 @@
+from typing import Any, Literal, Optional, Union, overload
+
+__version__: str
+
+# Input: dict of the process_inventory_history_wasm JSON contract, or its JSON text.
+Input = Union[dict, str]
+# History events: pandas DataFrame, dict of columns or list of row dicts
+# (createdAt, stockQty, oldStatus, newStatus, quantity, goodsIssueId, optional inventoryId).
+History = Any
+# Inventories, one per row, in the same forms (id, stockQty, unitPrice, dimensionLength, ...).
+Inventories = Any
+AsOf = Union[int, str, Any]
+
+@overload
+def process_inventory_history(
+    input: Input,
+    history: Optional[History] = None,
+    *,
+    inventories: Optional[Inventories] = None,
+    output: Literal["dict"] = "dict",
+    utc_offset_seconds: Optional[int] = None,
+    as_of: Optional[AsOf] = None,
+) -> dict: ...
+@overload
+def process_inventory_history(
+    input: Input,
+    history: Optional[History] = None,
+    *,
+    inventories: Optional[Inventories] = None,
+    output: Literal["rows"],
+    utc_offset_seconds: Optional[int] = None,
+    as_of: Optional[AsOf] = None,
+) -> list[dict]: ...
+@overload
+def process_inventory_history(
+    input: Input,
+    history: Optional[History] = None,
+    *,
+    inventories: Optional[Inventories] = None,
+    output: Literal["arrow"],
+    utc_offset_seconds: Optional[int] = None,
+    as_of: Optional[AsOf] = None,
+) -> Any: ...  # pyarrow.Table
+@overload
+def process_inventory_history_batch(
+    inputs: Union[list, str],
+    history: Optional[History] = None,
+    *,
+    inventories: Optional[Inventories] = None,
+    output: Literal["dict"] = "dict",
+    utc_offset_seconds: Optional[int] = None,
+    as_of: Optional[AsOf] = None,
+) -> list[dict]: ...
+@overload
+def process_inventory_history_batch(
+    inputs: Union[list, str],
+    history: Optional[History] = None,
+    *,
+    inventories: Optional[Inventories] = None,
+    output: Literal["rows", "arrow"],
+    utc_offset_seconds: Optional[int] = None,
+    as_of: Optional[AsOf] = None,
+) -> Any: ...
+def costing_report(
+    inputs: Union[list, str],
+    history: Optional[History] = None,
+    *,
+    inventories: Optional[Inventories] = None,
+    method: Literal["fifo", "lifo", "weighted_average"] = "fifo",
+    utc_offset_seconds: Optional[int] = None,
+    as_of: Optional[AsOf] = None,
+) -> dict: ...
@@ -0,0 +1,482 @@
+//! Python bindings for rust-inventory-processor.
+//! Build: cd python && maturin develop --release
+
+#![deny(clippy::all)]
+
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use arrow::pyarrow::ToPyArrow;
+use processor::{
+    apply_inventories, costing_report_json, history_by_inventory_from_table, history_from_table,
+    inventories_from_table, inventory_record_batch, is_valid_timestamp, is_valid_utc_offset,
+    parse_timestamp_with_offset, table_has_inventory_ids, ArrowOptions, BatchResult, CostingMethod,
+    ErrorPayloadSerializable, ImportOptions, JsonInput, JsonOutput, ProcessInventoryHistoryInput,
+    ProshipInventory, ProshipInventoryHistory, Table, GMT7_OFFSET_SECONDS,
+};
+use pyo3::exceptions::{PyRuntimeError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyFloat, PyInt, PyString};
+
+const SECONDS_PER_DAY: i64 = 24 * 3600;
+
+fn run<T>(f: impl FnOnce() -> T) -> PyResult<T> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|e| {
+        let msg = if let Some(s) = e.downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = e.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "Processing failed".to_string()
+        };
+        PyRuntimeError::new_err(msg)
+    })
+}
+
+fn input_error(error: ErrorPayloadSerializable) -> PyErr {
+    PyValueError::new_err(
+        serde_json::to_string(&error).unwrap_or_else(|_| "Invalid input JSON".to_string()),
+    )
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Output {
+    /// The process_inventory_history_wasm result object.
+    Dict,
+    /// Daily rows sorted by key.
+    Rows,
+    /// pyarrow.Table of inventory_record_batch.
+    Arrow,
+}
+
+impl Output {
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name {
+            "dict" => Ok(Output::Dict),
+            "rows" => Ok(Output::Rows),
+            "arrow" => Ok(Output::Arrow),
+            _ => Err(PyValueError::new_err(format!(
+                "unknown output {:?}, expected \"dict\", \"rows\" or \"arrow\"",
+                name
+            ))),
+        }
+    }
+}
+
+/// `default` of json.dumps: dates (datetime, date, pandas Timestamp) as
+/// ISO-8601, numpy scalars and arrays as Python values.
+#[pyfunction]
+fn json_default(value: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    for method in ["isoformat", "item", "tolist"] {
+        if value.hasattr(method)? {
+            return Ok(value.call_method0(method)?.unbind());
+        }
+    }
+    Err(PyTypeError::new_err(format!(
+        "Object of type {} is not JSON serializable",
+        value.get_type().name()?
+    )))
+}
+
+/// JSON text of a dict/list input; a str is taken as JSON already.
+fn to_json_text(value: &Bound<'_, PyAny>) -> PyResult<String> {
+    if let Ok(text) = value.downcast::<PyString>() {
+        return text.extract();
+    }
+    let py = value.py();
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("default", wrap_pyfunction!(json_default, py)?)?;
+    py.import("json")?
+        .call_method("dumps", (value,), Some(&kwargs))?
+        .extract()
+}
+
+fn from_json(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
+    let text = serde_json::to_string(value).expect("Failed to serialize result");
+    Ok(py.import("json")?.call_method1("loads", (text,))?.unbind())
+}
+
+/// Text of a history cell as the spreadsheet import reads it: None, NaN and
+/// NaT empty, whole floats without the fraction, dates as ISO-8601.
+fn cell_text(value: &Bound<'_, PyAny>) -> PyResult<String> {
+    if value.is_none() || value.ne(value).unwrap_or(true) {
+        return Ok(String::new());
+    }
+    if let Ok(v) = value.downcast::<PyFloat>() {
+        let v = v.value();
+        return Ok(if v.fract() == 0.0 {
+            (v as i64).to_string()
+        } else {
+            v.to_string()
+        });
+    }
+    if value.downcast::<PyInt>().is_err() && !value.is_instance_of::<PyString>() {
+        for method in ["isoformat", "item"] {
+            if value.hasattr(method)? {
+                return cell_text(&value.call_method0(method)?);
+            }
+        }
+    }
+    Ok(value.str()?.to_string())
+}
+
+/// Sheet (`name` in errors) given as a pandas DataFrame, a dict of columns or
+/// a list of row dicts.
+fn py_table(value: &Bound<'_, PyAny>, name: &str) -> PyResult<Table> {
+    let value = if value.hasattr("to_dict")? && !value.is_instance_of::<PyDict>() {
+        value.call_method1("to_dict", ("list",))?
+    } else {
+        value.clone()
+    };
+
+    if let Ok(columns) = value.downcast::<PyDict>() {
+        let mut headers = Vec::new();
+        let mut cells = Vec::new();
+        for (header, values) in columns.iter() {
+            headers.push(header.str()?.to_string());
+            cells.push(
+                values
+                    .try_iter()?
+                    .map(|v| cell_text(&v?))
+                    .collect::<PyResult<Vec<_>>>()?,
+            );
+        }
+        let len = cells.first().map_or(0, Vec::len);
+        if cells.iter().any(|column| column.len() != len) {
+            return Err(PyValueError::new_err(format!(
+                "{} columns must have the same length",
+                name
+            )));
+        }
+        let rows = (0..len)
+            .map(|i| cells.iter().map(|column| column[i].clone()).collect())
+            .collect();
+        return Ok(Table {
+            headers,
+            rows,
+            header_line: 1,
+        });
+    }
+
+    let mut headers: Vec<String> = Vec::new();
+    let mut records = Vec::new();
+    for record in value.try_iter()? {
+        let record = record?;
+        let record = record.downcast::<PyDict>().map_err(|_| {
+            PyTypeError::new_err(format!(
+                "{} must be a DataFrame, a dict of columns or a list of dicts",
+                name
+            ))
+        })?;
+        let mut row = HashMap::new();
+        for (header, value) in record.iter() {
+            let header = header.str()?.to_string();
+            if !headers.contains(&header) {
+                headers.push(header.clone());
+            }
+            row.insert(header, cell_text(&value)?);
+        }
+        records.push(row);
+    }
+    let rows = records
+        .into_iter()
+        .map(|mut row| {
+            headers
+                .iter()
+                .map(|header| row.remove(header).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+    Ok(Table {
+        headers,
+        rows,
+        header_line: 1,
+    })
+}
+
+/// History given to the call: per inventory id, or the same events for every input.
+enum ImportedHistory {
+    ByInventory(HashMap<String, Vec<ProshipInventoryHistory>>),
+    All(Vec<ProshipInventoryHistory>),
+}
+
+fn import_history(
+    history: &Bound<'_, PyAny>,
+    utc_offset_seconds: i32,
+) -> PyResult<ImportedHistory> {
+    let table = py_table(history, "history")?;
+    let options = ImportOptions {
+        utc_offset_seconds,
+        ..ImportOptions::default()
+    };
+    let error = |e: String| PyValueError::new_err(format!("history: {}", e));
+    Ok(if table_has_inventory_ids(&table, &options) {
+        ImportedHistory::ByInventory(
+            history_by_inventory_from_table(&table, &options).map_err(error)?,
+        )
+    } else {
+        ImportedHistory::All(history_from_table(&table, &options).map_err(error)?)
+    })
+}
+
+/// Epoch seconds of `as_of`; a date alone means the end of that day.
+fn as_of_timestamp(value: &Bound<'_, PyAny>, utc_offset_seconds: i32) -> PyResult<i64> {
+    if let Ok(ts) = value.extract::<i64>() {
+        return if is_valid_timestamp(ts) {
+            Ok(ts)
+        } else {
+            Err(PyValueError::new_err(format!("as_of out of range: {}", ts)))
+        };
+    }
+    let text = cell_text(value)?;
+    let ts = parse_timestamp_with_offset(&text, utc_offset_seconds)
+        .ok_or_else(|| PyValueError::new_err(format!("invalid as_of: {}", text)))?;
+    let date_only = text.trim().len() == 10 && text.contains('-');
+    Ok(if date_only {
+        ts + SECONDS_PER_DAY - 1
+    } else {
+        ts
+    })
+}
+
+fn import_inventories(
+    inventories: &Bound<'_, PyAny>,
+    utc_offset_seconds: i32,
+) -> PyResult<Vec<ProshipInventory>> {
+    let table = py_table(inventories, "inventories")?;
+    let options = ImportOptions {
+        utc_offset_seconds,
+        ..ImportOptions::default()
+    };
+    inventories_from_table(&table, &options)
+        .map_err(|e| PyValueError::new_err(format!("inventories: {}", e)))
+}
+
+/// History, inventories, timezone and `as_of` given to the call, applied to
+/// each input.
+struct CallOptions {
+    history: Option<ImportedHistory>,
+    inventories: Option<Vec<ProshipInventory>>,
+    utc_offset_seconds: i32,
+    as_of: Option<i64>,
+}
+
+impl CallOptions {
+    fn new(
+        history: Option<&Bound<'_, PyAny>>,
+        inventories: Option<&Bound<'_, PyAny>>,
+        utc_offset_seconds: Option<i32>,
+        as_of: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<Self> {
+        let tz = utc_offset_seconds.unwrap_or(GMT7_OFFSET_SECONDS);
+        if !is_valid_utc_offset(tz) {
+            return Err(PyValueError::new_err(format!(
+                "utc_offset_seconds out of range: {}",
+                tz
+            )));
+        }
+        Ok(Self {
+            history: history.map(|h| import_history(h, tz)).transpose()?,
+            inventories: inventories.map(|v| import_inventories(v, tz)).transpose()?,
+            utc_offset_seconds: tz,
+            as_of: as_of.map(|v| as_of_timestamp(v, tz)).transpose()?,
+        })
+    }
+
+    /// Replaces the inventory of the inputs named in `inventories`.
+    fn set_inventories(&mut self, inputs: &mut [JsonInput]) -> PyResult<()> {
+        match self.inventories.take() {
+            Some(inventories) => apply_inventories(inputs, inventories)
+                .map_err(|e| PyValueError::new_err(format!("inventories: {}", e))),
+            None => Ok(()),
+        }
+    }
+
+    fn configure(&self, input: &mut ProcessInventoryHistoryInput<'_>) {
+        match &self.history {
+            Some(ImportedHistory::ByInventory(history)) => {
+                input.inventory_history_list =
+                    history.get(input.inventory_id).cloned().unwrap_or_default();
+            }
+            Some(ImportedHistory::All(history)) => {
+                input.inventory_history_list = history.clone();
+            }
+            None => {}
+        }
+        input.options.utc_offset_seconds = self.utc_offset_seconds;
+        input.options.as_of = self.as_of;
+    }
+}
+
+fn process(
+    py: Python<'_>,
+    mut inputs: Vec<JsonInput>,
+    history: Option<&Bound<'_, PyAny>>,
+    inventories: Option<&Bound<'_, PyAny>>,
+    utc_offset_seconds: Option<i32>,
+    as_of: Option<&Bound<'_, PyAny>>,
+) -> PyResult<Vec<(String, JsonOutput)>> {
+    let mut options = CallOptions::new(history, inventories, utc_offset_seconds, as_of)?;
+    options.set_inventories(&mut inputs)?;
+
+    py.allow_threads(|| {
+        run(|| {
+            inputs
+                .into_iter()
+                .map(|input| {
+                    let inventory_id = input.inventory_id().to_string();
+                    let output = input.process(|input| options.configure(input));
+                    (inventory_id, output)
+                })
+                .collect()
+        })
+    })
+}
+
+fn to_python(
+    py: Python<'_>,
+    results: Vec<(String, JsonOutput)>,
+    output: Output,
+    batch: bool,
+) -> PyResult<PyObject> {
+    match output {
+        Output::Arrow => {
+            let items: Vec<BatchResult<'_>> = results
+                .iter()
+                .map(|(inventory_id, output)| BatchResult {
+                    inventory_id,
+                    output: &output.output,
+                })
+                .collect();
+            // Scales of the first input, as for costing.
+            let options = results
+                .first()
+                .map_or_else(ArrowOptions::default, |(_, first)| {
+                    ArrowOptions::new(&first.numeric, first.value_precision)
+                });
+            let record_batch = inventory_record_batch(&items, &options)
+                .map_err(|e| PyValueError::new_err(e.to_string()))?
+                .to_pyarrow(py)?;
+            Ok(py
+                .import("pyarrow")?
+                .getattr("Table")?
+                .call_method1("from_batches", (vec![record_batch],))?
+                .unbind())
+        }
+        Output::Dict if batch => {
+            let results = results
+                .into_iter()
+                .map(|(_, output)| output.into_json())
+                .collect();
+            from_json(py, &serde_json::Value::Array(results))
+        }
+        Output::Dict => {
+            let (_, output) = results.into_iter().next().expect("No result");
+            from_json(py, &output.into_json())
+        }
+        Output::Rows if batch => {
+            let mut rows = Vec::new();
+            for (inventory_id, output) in results {
+                for row in output.into_rows() {
+                    let mut with_id = serde_json::Map::new();
+                    with_id.insert("inventoryId".to_string(), inventory_id.clone().into());
+                    if let serde_json::Value::Object(row) = row {
+                        with_id.extend(row);
+                    }
+                    rows.push(serde_json::Value::Object(with_id));
+                }
+            }
+            from_json(py, &serde_json::Value::Array(rows))
+        }
+        Output::Rows => {
+            let (_, output) = results.into_iter().next().expect("No result");
+            from_json(py, &serde_json::Value::Array(output.into_rows()))
+        }
+    }
+}
+
+/// Processes one input (dict or JSON string) like process_inventory_history_wasm.
+/// `history` (DataFrame, dict of columns or list of dicts) replaces
+/// `inventoryHistoryList`; a row of `inventories` (same kinds) with the input's
+/// `inventoryId` as `id` replaces its `inventory`.
+#[pyfunction]
+#[pyo3(signature = (input, history=None, *, inventories=None, output="dict", utc_offset_seconds=None, as_of=None))]
+fn process_inventory_history(
+    py: Python<'_>,
+    input: &Bound<'_, PyAny>,
+    history: Option<&Bound<'_, PyAny>>,
+    inventories: Option<&Bound<'_, PyAny>>,
+    output: &str,
+    utc_offset_seconds: Option<i32>,
+    as_of: Option<&Bound<'_, PyAny>>,
+) -> PyResult<PyObject> {
+    let output = Output::from_name(output)?;
+    let input = JsonInput::parse(&to_json_text(input)?).map_err(input_error)?;
+    let results = process(
+        py,
+        vec![input],
+        history,
+        inventories,
+        utc_offset_seconds,
+        as_of,
+    )?;
+    to_python(py, results, output, false)
+}
+
+/// Processes a list of inputs (or a JSON array string); with an `inventoryId`
+/// column, `history` rows go to the input of that inventory, and each row of
+/// `inventories` replaces the `inventory` of the input with its `id`.
+#[pyfunction]
+#[pyo3(signature = (inputs, history=None, *, inventories=None, output="dict", utc_offset_seconds=None, as_of=None))]
+fn process_inventory_history_batch(
+    py: Python<'_>,
+    inputs: &Bound<'_, PyAny>,
+    history: Option<&Bound<'_, PyAny>>,
+    inventories: Option<&Bound<'_, PyAny>>,
+    output: &str,
+    utc_offset_seconds: Option<i32>,
+    as_of: Option<&Bound<'_, PyAny>>,
+) -> PyResult<PyObject> {
+    let output = Output::from_name(output)?;
+    let inputs = JsonInput::parse_batch(&to_json_text(inputs)?).map_err(input_error)?;
+    let results = process(py, inputs, history, inventories, utc_offset_seconds, as_of)?;
+    to_python(py, results, output, true)
+}
+
+/// Cost of goods issued across a list of inputs (or a JSON array string),
+/// lots of the same goods, as the costing_report_wasm dict. `method` is
+/// "fifo", "lifo" or "weighted_average".
+#[pyfunction]
+#[pyo3(signature = (inputs, history=None, *, inventories=None, method="fifo", utc_offset_seconds=None, as_of=None))]
+fn costing_report(
+    py: Python<'_>,
+    inputs: &Bound<'_, PyAny>,
+    history: Option<&Bound<'_, PyAny>>,
+    inventories: Option<&Bound<'_, PyAny>>,
+    method: &str,
+    utc_offset_seconds: Option<i32>,
+    as_of: Option<&Bound<'_, PyAny>>,
+) -> PyResult<PyObject> {
+    let method = CostingMethod::from_name(method).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown method {:?}, expected \"fifo\", \"lifo\" or \"weighted_average\"",
+            method
+        ))
+    })?;
+    let mut inputs = JsonInput::parse_batch(&to_json_text(inputs)?).map_err(input_error)?;
+    let mut options = CallOptions::new(history, inventories, utc_offset_seconds, as_of)?;
+    options.set_inventories(&mut inputs)?;
+    let result = py.allow_threads(|| {
+        run(|| costing_report_json(inputs, method, |input| options.configure(input)))
+    })?;
+    from_json(py, &result)
+}
+
+#[pymodule]
+fn rust_inventory_processor(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_function(wrap_pyfunction!(process_inventory_history, m)?)?;
+    m.add_function(wrap_pyfunction!(process_inventory_history_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(costing_report, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,369 @@
+//! Inventory aging: stock held per age bucket, aged from the goods receipt date.
+
+use crate::process_inventory_list::{
+    calculate_cbm_decimal, days_between, epoch_to_human, start_of_day, Decimal,
+    EnumProshipType_InventoryStatus, ProcessOptions, ProshipGoodsReceipt, ProshipInventory,
+    ProshipInventoryHistory,
+};
+
+const SECONDS_PER_DAY: i64 = 24 * 3600;
+
+/// Age range in days, inclusive on both ends; `max_days: None` is open-ended.
+#[derive(Clone, PartialEq, Default)]
+pub struct AgingBucket {
+    pub label: String,
+    pub min_days: i32,
+    pub max_days: Option<i32>,
+}
+
+impl AgingBucket {
+    pub fn new(label: &str, min_days: i32, max_days: Option<i32>) -> Self {
+        Self {
+            label: label.to_string(),
+            min_days,
+            max_days,
+        }
+    }
+
+    pub fn contains(&self, age_days: i32) -> bool {
+        age_days >= self.min_days && self.max_days.map_or(true, |max| age_days <= max)
+    }
+}
+
+/// 0-30, 31-60, 61-90 and 90+ days.
+pub fn default_aging_buckets() -> Vec<AgingBucket> {
+    vec![
+        AgingBucket::new("0-30", 0, Some(30)),
+        AgingBucket::new("31-60", 31, Some(60)),
+        AgingBucket::new("61-90", 61, Some(90)),
+        AgingBucket::new("90+", 91, None),
+    ]
+}
+
+/// One lot: its receipt date ages all of its stock.
+pub struct AgingLot<'a> {
+    pub inventory: &'a ProshipInventory,
+    pub goods_receipt: &'a ProshipGoodsReceipt,
+    pub inventory_history_list: &'a [ProshipInventoryHistory],
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct AgingBucketTotal {
+    pub label: String,
+    pub quantity: i32,
+    pub cbm: Decimal,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct AgingReport {
+    pub as_of: i64,
+    /// `as_of` as yyyymmdd in the report timezone, matching the daily row keys.
+    pub date: String,
+    pub buckets: Vec<AgingBucketTotal>,
+    pub total_qty: i32,
+    pub total_cbm: Decimal,
+}
+
+/// Most days `daily_aging_report` produces reports for.
+pub const MAX_AGING_REPORT_DAYS: i64 = 3660;
+
+/// A `daily_aging_report` range longer than MAX_AGING_REPORT_DAYS.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AgingRangeError {
+    /// Days the requested range covers.
+    pub days: i64,
+}
+
+impl std::fmt::Display for AgingRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "range covers {} days, more than the {} allowed",
+            self.days, MAX_AGING_REPORT_DAYS
+        )
+    }
+}
+
+impl std::error::Error for AgingRangeError {}
+
+/// Whether an event changes the lot's closing stock.
+fn counts_toward_stock(h: &ProshipInventoryHistory) -> bool {
+    h.get_new_status() != EnumProshipType_InventoryStatus::PENDING_FOR_IMPORT_INVENTORY_STATUS
+}
+
+/// Closing stock of a lot at `as_of`: the `stock_qty` of its last event at or before it.
+fn stock_qty_at(history: &[ProshipInventoryHistory], as_of: i64) -> i32 {
+    history
+        .iter()
+        .filter(|h| counts_toward_stock(h) && h.get_created_at() <= as_of)
+        .max_by_key(|h| h.get_created_at())
+        .map_or(0, |h| h.get_stock_qty())
+}
+
+/// Stock per age bucket at `as_of`. Lots received after `as_of` are ignored and
+/// ages outside every bucket are counted in the totals only.
+pub fn aging_report(
+    lots: &[AgingLot<'_>],
+    buckets: &[AgingBucket],
+    as_of: i64,
+    options: &ProcessOptions,
+) -> AgingReport {
+    let volumes = lot_volumes(lots, options);
+    report_at(lots, &volumes, buckets, as_of, options, |index| {
+        stock_qty_at(lots[index].inventory_history_list, as_of)
+    })
+}
+
+/// `(volume, master_volume)` of each lot.
+fn lot_volumes(lots: &[AgingLot<'_>], options: &ProcessOptions) -> Vec<(f32, f32)> {
+    lots.iter()
+        .map(|lot| {
+            let (volume, master_volume, _) =
+                lot.inventory.resolve_volumes(options.volume_tolerance);
+            (volume, master_volume)
+        })
+        .collect()
+}
+
+/// `aging_report` with the stock of the lot at each index from `stock_qty`.
+fn report_at(
+    lots: &[AgingLot<'_>],
+    volumes: &[(f32, f32)],
+    buckets: &[AgingBucket],
+    as_of: i64,
+    options: &ProcessOptions,
+    stock_qty: impl Fn(usize) -> i32,
+) -> AgingReport {
+    let mut report = AgingReport {
+        as_of,
+        date: epoch_to_human(as_of, options.utc_offset_seconds),
+        buckets: buckets
+            .iter()
+            .map(|b| AgingBucketTotal {
+                label: b.label.clone(),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    for (index, lot) in lots.iter().enumerate() {
+        let received_at = lot.goods_receipt.get_imported_at();
+        if received_at > as_of {
+            continue;
+        }
+        let quantity = stock_qty(index);
+        if quantity <= 0 {
+            continue;
+        }
+        let (volume, master_volume) = volumes[index];
+        let cbm = calculate_cbm_decimal(
+            quantity,
+            lot.inventory.get_inner_qty_on_mas(),
+            volume,
+            master_volume,
+            &options.numeric,
+        );
+        let age_days = days_between(received_at, Some(as_of), options.utc_offset_seconds);
+
+        if let Some(index) = buckets.iter().position(|b| b.contains(age_days)) {
+            let total = &mut report.buckets[index];
+            total.quantity += quantity;
+            total.cbm = options
+                .numeric
+                .round_decimal(total.cbm + cbm, options.numeric.cbm_precision);
+        }
+        report.total_qty += quantity;
+        report.total_cbm = options
+            .numeric
+            .round_decimal(report.total_cbm + cbm, options.numeric.cbm_precision);
+    }
+
+    report
+}
+
+/// `aging_report` at the end of every day (in the report timezone) from
+/// `from_date` to `to_date`, one report per day. Ranges of more than
+/// MAX_AGING_REPORT_DAYS days are rejected.
+pub fn daily_aging_report(
+    lots: &[AgingLot<'_>],
+    buckets: &[AgingBucket],
+    from_date: i64,
+    to_date: i64,
+    options: &ProcessOptions,
+) -> Result<Vec<AgingReport>, AgingRangeError> {
+    let first_day = start_of_day(from_date, options.utc_offset_seconds);
+    let days = if to_date < first_day {
+        0
+    } else {
+        (to_date - first_day) / SECONDS_PER_DAY + 1
+    };
+    if days > MAX_AGING_REPORT_DAYS {
+        return Err(AgingRangeError { days });
+    }
+
+    // Each lot's events in time order (ties keep their order, as in
+    // stock_qty_at), read once as the days advance.
+    let histories: Vec<Vec<&ProshipInventoryHistory>> = lots
+        .iter()
+        .map(|lot| {
+            let mut history: Vec<_> = lot
+                .inventory_history_list
+                .iter()
+                .filter(|h| counts_toward_stock(h))
+                .collect();
+            history.sort_by_key(|h| h.get_created_at());
+            history
+        })
+        .collect();
+    let volumes = lot_volumes(lots, options);
+    let mut next_event = vec![0; lots.len()];
+    let mut stock_qty = vec![0; lots.len()];
+
+    let mut reports = Vec::with_capacity(days as usize);
+    let mut day_start = first_day;
+    while day_start <= to_date {
+        let as_of = (day_start + SECONDS_PER_DAY - 1).min(to_date);
+        for (index, history) in histories.iter().enumerate() {
+            while let Some(h) = history
+                .get(next_event[index])
+                .filter(|h| h.get_created_at() <= as_of)
+            {
+                stock_qty[index] = h.get_stock_qty();
+                next_event[index] += 1;
+            }
+        }
+        reports.push(report_at(
+            lots,
+            &volumes,
+            buckets,
+            as_of,
+            options,
+            |index| stock_qty[index],
+        ));
+        day_start += SECONDS_PER_DAY;
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use EnumProshipType_InventoryStatus::*;
+
+    // 2024-03-01 00:00 at GMT+7.
+    const START: i64 = 1_709_226_000;
+
+    fn event(created_at: i64, stock_qty: i32) -> ProshipInventoryHistory {
+        ProshipInventoryHistory {
+            created_at,
+            stock_qty,
+            old_status: ON_HAND_INVENTORY_STATUS,
+            new_status: AVALABLE_INVENTORY_STATUS,
+            ..Default::default()
+        }
+    }
+
+    fn inventory() -> ProshipInventory {
+        ProshipInventory {
+            id: "A".to_string(),
+            inner_qty_on_mas: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Quantity per bucket label with a non-empty bucket.
+    fn filled(report: &AgingReport) -> Vec<(String, i32)> {
+        report
+            .buckets
+            .iter()
+            .filter(|b| b.quantity != 0)
+            .map(|b| (b.label.clone(), b.quantity))
+            .collect()
+    }
+
+    #[test]
+    fn ages_fall_into_the_bucket_containing_them() {
+        let inventory = inventory();
+        let goods_receipt = ProshipGoodsReceipt { imported_at: START };
+        let history = [event(START, 10)];
+        let lots = [AgingLot {
+            inventory: &inventory,
+            goods_receipt: &goods_receipt,
+            inventory_history_list: &history,
+        }];
+        let bucket_at = |day: i64| {
+            let report = aging_report(
+                &lots,
+                &default_aging_buckets(),
+                START + day * SECONDS_PER_DAY + 3600,
+                &ProcessOptions::default(),
+            );
+            filled(&report)
+        };
+
+        assert_eq!(bucket_at(30), [("0-30".to_string(), 10)]);
+        assert_eq!(bucket_at(31), [("31-60".to_string(), 10)]);
+        assert_eq!(bucket_at(60), [("31-60".to_string(), 10)]);
+        assert_eq!(bucket_at(61), [("61-90".to_string(), 10)]);
+        assert_eq!(bucket_at(90), [("61-90".to_string(), 10)]);
+        assert_eq!(bucket_at(91), [("90+".to_string(), 10)]);
+    }
+
+    #[test]
+    fn daily_reports_follow_the_stock_of_each_day() {
+        let inventory = inventory();
+        let goods_receipt = ProshipGoodsReceipt { imported_at: START };
+        let history = [
+            event(START + 2 * SECONDS_PER_DAY, 4),
+            event(START, 10),
+            event(START + 2 * SECONDS_PER_DAY, 3),
+        ];
+        let lots = [AgingLot {
+            inventory: &inventory,
+            goods_receipt: &goods_receipt,
+            inventory_history_list: &history,
+        }];
+        let options = ProcessOptions::default();
+
+        let reports = daily_aging_report(
+            &lots,
+            &default_aging_buckets(),
+            START,
+            START + 3 * SECONDS_PER_DAY,
+            &options,
+        )
+        .unwrap();
+
+        let totals: Vec<i32> = reports.iter().map(|r| r.total_qty).collect();
+        assert_eq!(totals, [10, 10, 3, 3]);
+        for report in &reports {
+            let single = aging_report(&lots, &default_aging_buckets(), report.as_of, &options);
+            assert!(single == *report);
+        }
+    }
+
+    #[test]
+    fn daily_reports_reject_ranges_over_the_limit() {
+        let options = ProcessOptions::default();
+        let to_date = START + (MAX_AGING_REPORT_DAYS - 1) * SECONDS_PER_DAY;
+
+        let reports = daily_aging_report(&[], &default_aging_buckets(), START, to_date, &options);
+        assert_eq!(reports.map(|r| r.len()), Ok(MAX_AGING_REPORT_DAYS as usize));
+
+        let too_long = daily_aging_report(
+            &[],
+            &default_aging_buckets(),
+            START,
+            to_date + SECONDS_PER_DAY,
+            &options,
+        );
+        assert_eq!(
+            too_long.map(|r| r.len()),
+            Err(AgingRangeError {
+                days: MAX_AGING_REPORT_DAYS + 1
+            })
+        );
+    }
+}
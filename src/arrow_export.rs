@@ -0,0 +1,409 @@
+//! Arrow RecordBatch and Parquet output of the daily rows of batch results,
+//! for loading into a columnar warehouse.
+
+use crate::process_inventory_list::{
+    BatchResult, Decimal, InventoryData, NumericConfig, RoundingMode, ValuationOptions,
+};
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{
+    ArrayRef, Date32Array, Decimal128Array, Float32Array, Float64Array, Int32Array, RecordBatch,
+    StringArray, TimestampSecondArray,
+};
+use arrow_schema::{ArrowError, Field, Schema, DECIMAL128_MAX_PRECISION};
+use chrono::NaiveDate;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use std::sync::Arc;
+
+/// Decimal places of the `Decimal128` columns.
+#[derive(Clone, PartialEq, Copy)]
+pub struct ArrowOptions {
+    /// CBM columns (`opening_cbm`, `closing_cbm`, ...).
+    pub cbm_scale: u32,
+    /// `unit_price`.
+    pub price_scale: u32,
+    /// Value columns (`opening_value`, `closing_value`, ...).
+    pub value_scale: u32,
+    pub rounding: RoundingMode,
+}
+
+/// Places of `unit_price` when prices are not rounded (no `price_precision`).
+const UNROUNDED_PRICE_SCALE: u32 = 6;
+
+impl Default for ArrowOptions {
+    fn default() -> Self {
+        Self::new(
+            &NumericConfig::default(),
+            ValuationOptions::default().value_precision,
+        )
+    }
+}
+
+impl ArrowOptions {
+    /// Scales matching the precision the rows were computed with; at most
+    /// `Decimal::MAX_SCALE`.
+    pub fn new(numeric: &NumericConfig, value_precision: u32) -> Self {
+        Self {
+            cbm_scale: numeric.cbm_precision.min(Decimal::MAX_SCALE),
+            price_scale: numeric
+                .price_precision
+                .unwrap_or(UNROUNDED_PRICE_SCALE)
+                .min(Decimal::MAX_SCALE),
+            value_scale: value_precision.min(Decimal::MAX_SCALE),
+            rounding: numeric.rounding,
+        }
+    }
+}
+
+/// One daily row of one inventory.
+struct Row<'a> {
+    inventory_id: &'a str,
+    key: &'a str,
+    data: &'a InventoryData,
+    goods_issue_ids: Vec<&'a String>,
+}
+
+/// Days since 1970-01-01 of the yyyymmdd date starting the row key.
+fn key_day(key: &str) -> Option<i32> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    let date = NaiveDate::parse_from_str(key.get(0..8)?, "%Y%m%d").ok()?;
+    Some((date - epoch).num_days() as i32)
+}
+
+fn string_list<'a>(values: impl Iterator<Item = Vec<&'a str>>) -> ArrayRef {
+    let mut builder = ListBuilder::new(StringBuilder::new());
+    for list in values {
+        for value in list {
+            builder.values().append_value(value);
+        }
+        builder.append(true);
+    }
+    Arc::new(builder.finish())
+}
+
+/// Daily rows of all `results` (sorted by inventory order, then key), one
+/// column per `InventoryData` field in snake_case. Dimensions and closing
+/// balances are flattened (`dimension_length`, `available_qty`), `date` and
+/// `received_date` are UTC timestamps and `day` is the row key's date. CBM,
+/// price and value columns are `Decimal128` with the scales of `options`;
+/// fails when a value has more than 38 digits at its scale.
+pub fn inventory_record_batch(
+    results: &[BatchResult<'_>],
+    options: &ArrowOptions,
+) -> Result<RecordBatch, ArrowError> {
+    let mut rows = Vec::new();
+    for result in results {
+        let mut keys: Vec<&String> = result.output.merged_inventory_history.keys().collect();
+        keys.sort();
+        for key in keys {
+            let (data, goods_issue_ids) = &result.output.merged_inventory_history[key];
+            let mut goods_issue_ids: Vec<&String> = goods_issue_ids.iter().collect();
+            goods_issue_ids.sort();
+            rows.push(Row {
+                inventory_id: result.inventory_id,
+                key,
+                data,
+                goods_issue_ids,
+            });
+        }
+    }
+
+    let text = |f: for<'r> fn(&'r Row<'r>) -> &'r str| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(rows.iter().map(f)))
+    };
+    let int32 = |f: fn(&InventoryData) -> i32| -> ArrayRef {
+        Arc::new(Int32Array::from_iter_values(rows.iter().map(|r| f(r.data))))
+    };
+    let decimal = |scale: u32, f: fn(&InventoryData) -> Decimal| -> Result<ArrayRef, ArrowError> {
+        let units = rows
+            .iter()
+            .map(|r| {
+                let value = f(r.data);
+                value.units_at(scale, options.rounding).ok_or_else(|| {
+                    ArrowError::InvalidArgumentError(format!("{} is out of range", value))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let array = Decimal128Array::from_iter_values(units)
+            .with_precision_and_scale(DECIMAL128_MAX_PRECISION, scale as i8)?;
+        array.validate_decimal_precision(DECIMAL128_MAX_PRECISION)?;
+        Ok(Arc::new(array))
+    };
+    let cbm = |f: fn(&InventoryData) -> Decimal| decimal(options.cbm_scale, f);
+    let value = |f: fn(&InventoryData) -> Decimal| decimal(options.value_scale, f);
+    let float32 = |f: fn(&InventoryData) -> f32| -> ArrayRef {
+        Arc::new(Float32Array::from_iter_values(
+            rows.iter().map(|r| f(r.data)),
+        ))
+    };
+    let float64 = |f: fn(&InventoryData) -> f64| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(
+            rows.iter().map(|r| f(r.data)),
+        ))
+    };
+    let timestamp = |f: fn(&InventoryData) -> i64| -> ArrayRef {
+        Arc::new(
+            TimestampSecondArray::from_iter_values(rows.iter().map(|r| f(r.data)))
+                .with_timezone("UTC"),
+        )
+    };
+
+    let columns: Vec<(&str, ArrayRef)> = vec![
+        ("inventory_id", text(|r| r.inventory_id)),
+        ("key", text(|r| r.key)),
+        (
+            "day",
+            Arc::new(Date32Array::from_iter(rows.iter().map(|r| key_day(r.key)))),
+        ),
+        ("date", timestamp(|d| d.date)),
+        ("opening_stock", int32(|d| d.opening_stock)),
+        ("opening_cbm", cbm(|d| d.get_opening_cbm_decimal())?),
+        ("opening_master_qty", int32(|d| d.opening_master_qty)),
+        ("opening_pallet_qty", int32(|d| d.opening_pallet_qty)),
+        ("asin", text(|r| &r.data.asin)),
+        (
+            "asin_outbound",
+            string_list(
+                rows.iter()
+                    .map(|r| r.data.asin_outbound.iter().map(|s| s.as_str()).collect()),
+            ),
+        ),
+        (
+            "unit_price",
+            decimal(options.price_scale, |d| d.get_unit_price_decimal())?,
+        ),
+        ("received_date", timestamp(|d| d.received_date)),
+        ("inner_qty_on_mas", int32(|d| d.inner_qty_on_mas)),
+        ("line_in_cd", text(|r| &r.data.line_in_cd)),
+        ("po_no", text(|r| &r.data.po_no)),
+        (
+            "master_dimension_length",
+            float64(|d| d.master_dimension.length),
+        ),
+        (
+            "master_dimension_width",
+            float64(|d| d.master_dimension.width),
+        ),
+        (
+            "master_dimension_height",
+            float64(|d| d.master_dimension.height),
+        ),
+        ("dimension_length", float64(|d| d.dimension.length)),
+        ("dimension_width", float64(|d| d.dimension.width)),
+        ("dimension_height", float64(|d| d.dimension.height)),
+        ("inbound_qty", int32(|d| d.inbound_qty)),
+        ("inbound_cbm", cbm(|d| d.get_inbound_cbm_decimal())?),
+        ("inbound_master_qty", int32(|d| d.inbound_master_qty)),
+        ("inbound_pallet_qty", int32(|d| d.inbound_pallet_qty)),
+        ("closing_stock", int32(|d| d.closing_stock)),
+        ("closing_cbm", cbm(|d| d.get_closing_cbm_decimal())?),
+        ("closing_master_qty", int32(|d| d.closing_master_qty)),
+        ("closing_pallet_qty", int32(|d| d.closing_pallet_qty)),
+        ("allocated_qty", int32(|d| d.allocated_qty)),
+        ("allocated_cbm", cbm(|d| d.get_allocated_cbm_decimal())?),
+        ("allocated_master_qty", int32(|d| d.allocated_master_qty)),
+        ("disposal_stock", int32(|d| d.disposal_stock)),
+        ("disposal_cbm", cbm(|d| d.get_disposal_cbm_decimal())?),
+        ("disposal_master_qty", int32(|d| d.disposal_master_qty)),
+        ("restore_stock_qty", int32(|d| d.restore_stock_qty)),
+        (
+            "restore_stock_cbm",
+            cbm(|d| d.get_restore_stock_cbm_decimal())?,
+        ),
+        ("restore_master_qty", int32(|d| d.restore_master_qty)),
+        ("outbound_qty", int32(|d| d.outbound_qty)),
+        ("outbound_cbm", cbm(|d| d.get_outbound_cbm_decimal())?),
+        ("outbound_master_qty", int32(|d| d.outbound_master_qty)),
+        ("outbound_pallet_qty", int32(|d| d.outbound_pallet_qty)),
+        ("pallet_footprint", float32(|d| d.pallet_footprint)),
+        ("storage_time_days", int32(|d| d.storage_time_days)),
+        ("currency", text(|r| &r.data.currency)),
+        ("opening_value", value(|d| d.opening_value)?),
+        ("inbound_value", value(|d| d.inbound_value)?),
+        ("outbound_value", value(|d| d.outbound_value)?),
+        ("disposal_value", value(|d| d.disposal_value)?),
+        ("closing_value", value(|d| d.closing_value)?),
+        ("available_qty", int32(|d| d.closing_balances.available_qty)),
+        ("on_hand_qty", int32(|d| d.closing_balances.on_hand_qty)),
+        ("damaged_qty", int32(|d| d.closing_balances.damaged_qty)),
+        ("return_qty", int32(|d| d.closing_balances.return_qty)),
+        (
+            "liquidation_qty",
+            int32(|d| d.closing_balances.liquidation_qty),
+        ),
+        ("exported_qty", int32(|d| d.closing_balances.exported_qty)),
+        (
+            "goods_issue_ids",
+            string_list(
+                rows.iter()
+                    .map(|r| r.goods_issue_ids.iter().map(|s| s.as_str()).collect()),
+            ),
+        ),
+    ];
+
+    let schema = Schema::new(
+        columns
+            .iter()
+            .map(|(name, array)| Field::new(*name, array.data_type().clone(), *name == "day"))
+            .collect::<Vec<_>>(),
+    );
+    RecordBatch::try_new(
+        Arc::new(schema),
+        columns.into_iter().map(|(_, array)| array).collect(),
+    )
+}
+
+/// inventory_record_batch as a Snappy-compressed Parquet file (bytes).
+pub fn write_inventory_parquet(
+    results: &[BatchResult<'_>],
+    options: &ArrowOptions,
+) -> Result<Vec<u8>, ParquetError> {
+    let batch = inventory_record_batch(results, options)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut parquet = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut parquet, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(parquet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_inventory_list::{
+        process_inventory_history_data, EnumProshipType_InventoryStatus::*, NumericMode,
+        ProcessInventoryHistoryInput, ProcessInventoryHistoryOutput, ProcessOptions,
+        ProshipGoodsReceipt, ProshipInventory, ProshipInventoryHistory,
+    };
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Decimal128Type;
+    use arrow_schema::DataType;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::collections::HashMap;
+
+    // 2024-03-01 00:00 at GMT+7.
+    const START: i64 = 1_709_226_000;
+
+    fn output(numeric: NumericConfig) -> ProcessInventoryHistoryOutput {
+        let inventory = ProshipInventory {
+            id: "i1".to_string(),
+            inner_qty_on_mas: 3,
+            volume: 0.01,
+            unit_price: 1.125,
+            currency: "USD".to_string(),
+            ..Default::default()
+        };
+        process_inventory_history_data(ProcessInventoryHistoryInput {
+            inventory: &inventory,
+            inventory_id: "i1",
+            asin_outbound_list: &[],
+            goods_receipt: &ProshipGoodsReceipt {
+                imported_at: START + 3600,
+            },
+            inventory_history_list: vec![
+                ProshipInventoryHistory {
+                    created_at: START + 3600,
+                    stock_qty: 100,
+                    old_status: PENDING_FOR_IMPORT_INVENTORY_STATUS,
+                    new_status: AVALABLE_INVENTORY_STATUS,
+                    quantity: 100,
+                    goods_issue_id: String::new(),
+                },
+                ProshipInventoryHistory {
+                    created_at: START + 86400 + 3600,
+                    stock_qty: 100,
+                    old_status: AVALABLE_INVENTORY_STATUS,
+                    new_status: EXPORTED_INVENTORY_STATUS,
+                    quantity: 7,
+                    goods_issue_id: "gi1".to_string(),
+                },
+            ],
+            inventory_ids: HashMap::from([("i1".to_string(), 100)]),
+            from_date: None,
+            to_date: Some(START + 2 * 86400),
+            options: ProcessOptions {
+                numeric,
+                as_of: Some(START + 10 * 86400),
+                ..Default::default()
+            },
+        })
+    }
+
+    #[test]
+    fn decimal_columns_use_the_configured_scales() {
+        let numeric = NumericConfig {
+            mode: NumericMode::Decimal,
+            cbm_precision: 4,
+            price_precision: Some(2),
+            ..Default::default()
+        };
+        let output = output(numeric);
+        let items = [BatchResult {
+            inventory_id: "i1",
+            output: &output,
+        }];
+        let options = ArrowOptions::new(&numeric, 3);
+        let batch = inventory_record_batch(&items, &options).unwrap();
+
+        let column = |name: &str| batch.column_by_name(name).unwrap();
+        assert_eq!(
+            column("closing_cbm").data_type(),
+            &DataType::Decimal128(38, 4)
+        );
+        assert_eq!(
+            column("unit_price").data_type(),
+            &DataType::Decimal128(38, 2)
+        );
+        assert_eq!(
+            column("closing_value").data_type(),
+            &DataType::Decimal128(38, 3)
+        );
+
+        let mut keys: Vec<_> = output.merged_inventory_history.keys().collect();
+        keys.sort();
+        let data = &output.merged_inventory_history[keys[0]].0;
+        let units = |name: &str| column(name).as_primitive::<Decimal128Type>().value(0);
+        assert_eq!(
+            Some(units("closing_cbm")),
+            data.get_closing_cbm_decimal()
+                .units_at(4, RoundingMode::HalfUp)
+        );
+        // 1.125 rounded half up to the price precision.
+        assert_eq!(units("unit_price"), 113);
+        assert_eq!(
+            Some(units("closing_value")),
+            data.closing_value.units_at(3, RoundingMode::HalfUp)
+        );
+    }
+
+    #[test]
+    fn parquet_reads_back_as_the_record_batch() {
+        let output = output(NumericConfig::default());
+        let items = [
+            BatchResult {
+                inventory_id: "i1",
+                output: &output,
+            },
+            BatchResult {
+                inventory_id: "i2",
+                output: &output,
+            },
+        ];
+        let options = ArrowOptions::default();
+        let batch = inventory_record_batch(&items, &options).unwrap();
+        let parquet = write_inventory_parquet(&items, &options).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(parquet))
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0], batch);
+        assert_eq!(batch.num_rows(), 2 * output.merged_inventory_history.len());
+    }
+}
@@ -0,0 +1,592 @@
+//! Command-line tool: runs the processor offline on a process_inventory_history_wasm
+//! input JSON, e.g. to reproduce a customer report.
+//! Build: cargo build --release --features cli
+
+use clap::{Parser, ValueEnum};
+use rust_inventory_processor::{
+    apply_inventories, costing_report_json, history_by_inventory_from_table, history_from_table,
+    inventories_from_table, table_has_inventory_ids, CostingMethod,
+    EnumProshipType_InventoryStatus, ImportOptions, ProcessInventoryHistoryInput, ProshipInventory,
+    ProshipInventoryHistory, StatusMapping, Table,
+};
+use rust_inventory_processor::{
+    parse_timestamp_with_offset, write_inventory_csv, write_inventory_xlsx, BatchResult, CsvColumn,
+    CsvLanguage, CsvOptions, JsonInput, JsonOutput, XlsxOptions,
+};
+#[cfg(feature = "arrow")]
+use rust_inventory_processor::{write_inventory_parquet, ArrowOptions};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const SECONDS_PER_DAY: i64 = 24 * 3600;
+
+#[derive(Parser)]
+#[command(name = "inventory-processor", version, about)]
+struct Args {
+    /// Input JSON file (process_inventory_history_wasm format, or an array of
+    /// them for `--format xlsx`); stdin when omitted or `-`.
+    input: Option<PathBuf>,
+    /// Output file; stdout when omitted.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+    /// Shape of the JSON output; CSV is always one line per daily row.
+    #[arg(long, value_enum, default_value_t = Shape::Full)]
+    shape: Shape,
+    /// Timezone of the daily rows and of date arguments: `+07:00`, `-0530`, `UTC`.
+    #[arg(
+        long,
+        default_value = "+07:00",
+        value_parser = parse_utc_offset,
+        allow_hyphen_values = true
+    )]
+    timezone: i32,
+    /// Produce the report as it was at this time; later events are ignored.
+    /// A date alone means the end of that day.
+    #[arg(long)]
+    as_of: Option<String>,
+    /// Start of the report window, overriding `fromDate`.
+    #[arg(long)]
+    from: Option<String>,
+    /// End of the report window, overriding `toDate`. A date alone means the end of that day.
+    #[arg(long)]
+    to: Option<String>,
+    /// Indent the JSON output.
+    #[arg(long)]
+    pretty: bool,
+    /// Layer order of `--format costing`: `fifo`, `lifo` or `weighted-average`.
+    #[arg(long, default_value = "fifo", value_parser = parse_costing_method)]
+    costing_method: CostingMethod,
+    /// CSV / XLSX daily sheet columns in order, as JSON row field names, e.g. `key,closingStock,availableQty`.
+    #[arg(long, value_delimiter = ',', value_parser = parse_column)]
+    columns: Vec<CsvColumn>,
+    /// Language of the CSV / XLSX headers: `en` or `vi`.
+    #[arg(long, default_value = "en", value_parser = parse_language)]
+    lang: CsvLanguage,
+    #[arg(long, default_value_t = ',', value_parser = parse_delimiter)]
+    delimiter: char,
+    #[arg(long, default_value_t = '.')]
+    decimal_separator: char,
+    /// Fixed number of decimals for CBM, price, dimension and value CSV columns.
+    #[arg(long)]
+    precision: Option<usize>,
+    /// Leave out the CSV header line.
+    #[arg(long)]
+    no_header: bool,
+    /// Start the CSV with a UTF-8 byte order mark, for Excel.
+    #[arg(long)]
+    bom: bool,
+    /// CSV or XLSX/XLS/ODS sheet of history events replacing `inventoryHistoryList`.
+    /// When it has an `inventoryId` column each input takes only its own rows.
+    #[arg(long)]
+    history: Option<PathBuf>,
+    /// Sheet of `--history` to read; the first one when omitted.
+    #[arg(long)]
+    history_sheet: Option<String>,
+    /// Delimiter of a CSV `--history` file.
+    #[arg(long, default_value_t = ',', value_parser = parse_delimiter)]
+    history_delimiter: char,
+    /// CSV or XLSX/XLS/ODS sheet of inventories, one per row, replacing the
+    /// `inventory` of the input whose `inventoryId` is in its `id` column.
+    #[arg(long)]
+    inventories: Option<PathBuf>,
+    /// Sheet of `--inventories` to read; the first one when omitted.
+    #[arg(long)]
+    inventories_sheet: Option<String>,
+    /// Delimiter of a CSV `--inventories` file.
+    #[arg(long, default_value_t = ',', value_parser = parse_delimiter)]
+    inventories_delimiter: char,
+    /// Column of `--history` or `--inventories` to read a field from, e.g.
+    /// `createdAt=Ngày tạo`. Repeatable.
+    #[arg(long = "map", value_name = "FIELD=HEADER", value_parser = parse_pair)]
+    column_mapping: Vec<(String, String)>,
+    /// Status name used in `--history` or `--inventories`, e.g. `Hàng tốt=1` or
+    /// `OK=available`. Repeatable.
+    #[arg(long = "status", value_name = "NAME=STATUS", value_parser = parse_pair)]
+    status_mapping: Vec<(String, String)>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+    /// Workbook with summary, daily, goods issue and totals sheets; the input
+    /// may be a JSON array of inputs.
+    Xlsx,
+    /// Daily rows of every inventory as a Parquet file (requires the `arrow`
+    /// feature); the input may be a JSON array of inputs.
+    #[cfg(feature = "arrow")]
+    Parquet,
+    /// Cost of goods issued across the inputs, lots of the same goods, as JSON
+    /// (see `--costing-method`); the input may be a JSON array of inputs.
+    Costing,
+}
+
+impl Format {
+    fn accepts_batch(self) -> bool {
+        match self {
+            Format::Json | Format::Csv => false,
+            Format::Xlsx | Format::Costing => true,
+            #[cfg(feature = "arrow")]
+            Format::Parquet => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Shape {
+    /// Same object as process_inventory_history_wasm.
+    Full,
+    /// Array of daily rows sorted by date.
+    Rows,
+    /// totalDuration, warnings and goodsIssueLedger only.
+    Summary,
+}
+
+fn parse_column(value: &str) -> Result<CsvColumn, String> {
+    CsvColumn::from_name(value).ok_or_else(|| format!("unknown column: {}", value))
+}
+
+fn parse_pair(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE: {}", value))
+}
+
+fn parse_language(value: &str) -> Result<CsvLanguage, String> {
+    CsvLanguage::from_name(value).ok_or_else(|| format!("unknown language: {}", value))
+}
+
+/// One CSV field delimiter character; quotes and line breaks would split or
+/// join fields.
+fn parse_delimiter(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some('"' | '\n' | '\r'), None) => {
+            Err(format!("{:?} cannot be used as a CSV delimiter", value))
+        }
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("expected a single character: {:?}", value)),
+    }
+}
+
+fn parse_costing_method(value: &str) -> Result<CostingMethod, String> {
+    CostingMethod::from_name(value).ok_or_else(|| format!("unknown costing method: {}", value))
+}
+
+fn parse_utc_offset(value: &str) -> Result<i32, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("utc") || value.eq_ignore_ascii_case("z") {
+        return Ok(0);
+    }
+    let (sign, rest) = match value.as_bytes().first() {
+        Some(b'+') => (1, &value[1..]),
+        Some(b'-') => (-1, &value[1..]),
+        _ => return Err(format!("invalid timezone offset: {}", value)),
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.as_str(), "0"),
+        4 => digits.split_at(2),
+        _ => return Err(format!("invalid timezone offset: {}", value)),
+    };
+    let hours: i32 = hours
+        .parse()
+        .map_err(|_| format!("invalid timezone offset: {}", value))?;
+    let minutes: i32 = minutes
+        .parse()
+        .map_err(|_| format!("invalid timezone offset: {}", value))?;
+    if hours > 23 || minutes > 59 {
+        return Err(format!("invalid timezone offset: {}", value));
+    }
+    Ok(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Epoch seconds from a date argument; a date alone is the start of that day,
+/// or its last second when `end_of_day` is set.
+fn parse_time(value: &str, utc_offset: i32, end_of_day: bool) -> Result<i64, String> {
+    let ts = parse_timestamp_with_offset(value, utc_offset)
+        .ok_or_else(|| format!("invalid date or time: {}", value))?;
+    let date_only = value.trim().len() == 10 && value.contains('-');
+    Ok(if date_only && end_of_day {
+        ts + SECONDS_PER_DAY - 1
+    } else {
+        ts
+    })
+}
+
+fn read_input(path: Option<&PathBuf>) -> io::Result<String> {
+    match path {
+        Some(path) if path.as_os_str() != "-" => fs::read_to_string(path),
+        _ => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+    }
+}
+
+/// History from `--history`: per inventory id, or the same events for every input.
+enum ImportedHistory {
+    ByInventory(HashMap<String, Vec<ProshipInventoryHistory>>),
+    All(Vec<ProshipInventoryHistory>),
+}
+
+/// CSV (by extension) or spreadsheet file as a table.
+fn read_table(path: &Path, sheet: Option<&str>, delimiter: char) -> Result<Table, String> {
+    let error = |e: String| format!("{}: {}", path.display(), e);
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv") || ext.eq_ignore_ascii_case("txt"));
+    if is_csv {
+        let text = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        Table::from_csv(&text, delimiter)
+    } else {
+        let bytes = fs::read(path).map_err(|e| error(e.to_string()))?;
+        Table::from_xlsx(&bytes, sheet)
+    }
+    .map_err(error)
+}
+
+/// Column and status mappings of `--map` and `--status`.
+fn import_options(args: &Args) -> Result<ImportOptions, String> {
+    let mut options = ImportOptions {
+        utc_offset_seconds: args.timezone,
+        ..ImportOptions::default()
+    };
+    for (field, header) in &args.column_mapping {
+        options.columns.set(field, header);
+    }
+    let known = StatusMapping::default();
+    for (name, status) in &args.status_mapping {
+        let status = match status.parse::<i32>() {
+            Ok(code @ 0..=7) => EnumProshipType_InventoryStatus::from_i32(code),
+            _ => known
+                .status(status)
+                .ok_or_else(|| format!("unknown status: {}", status))?,
+        };
+        options.statuses.set(name, status);
+    }
+    Ok(options)
+}
+
+fn import_history(args: &Args) -> Result<Option<ImportedHistory>, String> {
+    let Some(path) = &args.history else {
+        return Ok(None);
+    };
+    let error = |e: String| format!("{}: {}", path.display(), e);
+    let table = read_table(path, args.history_sheet.as_deref(), args.history_delimiter)?;
+    let options = import_options(args)?;
+
+    Ok(Some(if table_has_inventory_ids(&table, &options) {
+        ImportedHistory::ByInventory(
+            history_by_inventory_from_table(&table, &options).map_err(error)?,
+        )
+    } else {
+        ImportedHistory::All(history_from_table(&table, &options).map_err(error)?)
+    }))
+}
+
+fn import_inventories(args: &Args) -> Result<Option<Vec<ProshipInventory>>, String> {
+    let Some(path) = &args.inventories else {
+        return Ok(None);
+    };
+    let table = read_table(
+        path,
+        args.inventories_sheet.as_deref(),
+        args.inventories_delimiter,
+    )?;
+    inventories_from_table(&table, &import_options(args)?)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn to_json(output: JsonOutput, shape: Shape) -> serde_json::Value {
+    match shape {
+        Shape::Full => output.into_json(),
+        Shape::Rows => serde_json::Value::Array(output.into_rows()),
+        Shape::Summary => {
+            let mut result = output.into_json();
+            serde_json::json!({
+                "totalDuration": result["totalDuration"].take(),
+                "warnings": result["warnings"].take(),
+                "goodsIssueLedger": result["goodsIssueLedger"].take(),
+            })
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let input_json = read_input(args.input.as_ref()).map_err(|e| e.to_string())?;
+    let mut inputs = JsonInput::parse_batch(&input_json).map_err(|e| {
+        serde_json::to_string(&e).unwrap_or_else(|_| "Invalid input JSON".to_string())
+    })?;
+    if inputs.len() != 1 && !args.format.accepts_batch() {
+        return Err(
+            "an array of inputs is only supported with --format xlsx, parquet or costing"
+                .to_string(),
+        );
+    }
+
+    let tz = args.timezone;
+    let as_of = args
+        .as_of
+        .as_deref()
+        .map(|v| parse_time(v, tz, true))
+        .transpose()?;
+    let from = args
+        .from
+        .as_deref()
+        .map(|v| parse_time(v, tz, false))
+        .transpose()?;
+    let to = args
+        .to
+        .as_deref()
+        .map(|v| parse_time(v, tz, true))
+        .transpose()?;
+
+    if let Some(inventories) = import_inventories(&args)? {
+        apply_inventories(&mut inputs, inventories)?;
+    }
+    let imported = import_history(&args)?;
+    let configure = |input: &mut ProcessInventoryHistoryInput<'_>| {
+        match &imported {
+            Some(ImportedHistory::ByInventory(history)) => {
+                input.inventory_history_list =
+                    history.get(input.inventory_id).cloned().unwrap_or_default();
+            }
+            Some(ImportedHistory::All(history)) => {
+                input.inventory_history_list = history.clone();
+            }
+            None => {}
+        }
+        input.options.utc_offset_seconds = tz;
+        input.options.as_of = as_of;
+        if from.is_some() {
+            input.from_date = from;
+        }
+        if to.is_some() {
+            input.to_date = to;
+        }
+    };
+
+    if args.format == Format::Costing {
+        let value = costing_report_json(inputs, args.costing_method, configure);
+        return write_output(args.output.as_ref(), json_bytes(&value, args.pretty)?);
+    }
+
+    let mut results: Vec<(String, JsonOutput)> = inputs
+        .into_iter()
+        .map(|input| {
+            let inventory_id = input.inventory_id().to_string();
+            (inventory_id, input.process(configure))
+        })
+        .collect();
+    let columns = if args.columns.is_empty() {
+        CsvColumn::default_columns()
+    } else {
+        args.columns
+    };
+
+    let items = || -> Vec<BatchResult<'_>> {
+        results
+            .iter()
+            .map(|(inventory_id, output)| BatchResult {
+                inventory_id,
+                output: &output.output,
+            })
+            .collect()
+    };
+
+    let bytes = match args.format {
+        #[cfg(feature = "arrow")]
+        Format::Parquet => {
+            // Scales of the first input, as for costing.
+            let options = results
+                .first()
+                .map_or_else(ArrowOptions::default, |(_, first)| {
+                    ArrowOptions::new(&first.numeric, first.value_precision)
+                });
+            write_inventory_parquet(&items(), &options)
+                .map_err(|e| format!("failed to write Parquet: {}", e))?
+        }
+        Format::Xlsx => write_inventory_xlsx(
+            &items(),
+            &XlsxOptions {
+                columns,
+                language: args.lang,
+            },
+        )
+        .map_err(|e| format!("failed to write XLSX: {}", e))?,
+        Format::Csv => write_inventory_csv(
+            &results[0].1.output,
+            &CsvOptions {
+                columns,
+                language: args.lang,
+                delimiter: args.delimiter,
+                decimal_separator: args.decimal_separator,
+                precision: args.precision,
+                include_header: !args.no_header,
+                byte_order_mark: args.bom,
+            },
+        )
+        .into_bytes(),
+        Format::Json => {
+            let (_, output) = results.remove(0);
+            json_bytes(&to_json(output, args.shape), args.pretty)?
+        }
+        Format::Costing => unreachable!("costing is written above"),
+    };
+
+    write_output(args.output.as_ref(), bytes)
+}
+
+fn json_bytes(value: &serde_json::Value, pretty: bool) -> Result<Vec<u8>, String> {
+    let mut text = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+    .map_err(|e| e.to_string())?;
+    text.push('\n');
+    Ok(text.into_bytes())
+}
+
+fn write_output(path: Option<&PathBuf>, bytes: Vec<u8>) -> Result<(), String> {
+    match path {
+        Some(path) => fs::write(path, bytes),
+        None => io::stdout().write_all(&bytes),
+    }
+    .map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("inventory-processor: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-10-05 00:00 at GMT+7.
+    const OCT_5: i64 = 1_791_133_200;
+
+    fn args(extra: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(["inventory-processor"].iter().chain(extra))
+    }
+
+    #[test]
+    fn utc_offsets() {
+        assert_eq!(parse_utc_offset("+07:00"), Ok(7 * 3600));
+        assert_eq!(parse_utc_offset("-0530"), Ok(-(5 * 3600 + 30 * 60)));
+        assert_eq!(parse_utc_offset("+7"), Ok(7 * 3600));
+        assert_eq!(parse_utc_offset(" utc "), Ok(0));
+        assert_eq!(parse_utc_offset("Z"), Ok(0));
+        for bad in ["07:00", "+24:00", "+07:60", "+7:0", "+07:00:00", "gmt"] {
+            assert!(parse_utc_offset(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(args(&["--timezone", "-05:00"]).unwrap().timezone, -5 * 3600);
+    }
+
+    #[test]
+    fn dates_alone_end_the_day_only_where_asked() {
+        let tz = 7 * 3600;
+        assert_eq!(parse_time("2026-10-05", tz, false), Ok(OCT_5));
+        assert_eq!(
+            parse_time("2026-10-05", tz, true),
+            Ok(OCT_5 + SECONDS_PER_DAY - 1)
+        );
+        assert_eq!(
+            parse_time("2026-10-05T10:00:00+07:00", tz, true),
+            Ok(OCT_5 + 10 * 3600)
+        );
+        assert_eq!(parse_time("2026-10-05", 0, false), Ok(OCT_5 + tz as i64));
+        assert!(parse_time("yesterday", tz, true).is_err());
+    }
+
+    #[test]
+    fn delimiters_reject_quotes_and_line_breaks() {
+        assert_eq!(args(&["--delimiter", ";"]).unwrap().delimiter, ';');
+        assert_eq!(
+            args(&["--history-delimiter", "\t"])
+                .unwrap()
+                .history_delimiter,
+            '\t'
+        );
+        for flag in [
+            "--delimiter",
+            "--history-delimiter",
+            "--inventories-delimiter",
+        ] {
+            for bad in ["\"", "\n", "\r", ";;"] {
+                assert!(args(&[flag, bad]).is_err(), "{} {:?}", flag, bad);
+            }
+        }
+    }
+
+    fn output() -> JsonOutput {
+        let input = serde_json::json!({
+            "inventory": { "id": "inv1", "stockQty": 100, "innerQtyOnMas": 1, "volume": 0.01 },
+            "inventoryList": [],
+            "asinOutboundList": [],
+            "goods": null,
+            "goodsReceipt": { "importedAt": "2026-10-01T08:00:00+07:00" },
+            "supplier": null,
+            "customer": null,
+            "inventoryHistoryList": [{
+                "id": "a",
+                "createdAt": "2026-10-01T08:00:00+07:00",
+                "stockQty": 100,
+                "oldStatus": 7,
+                "newStatus": 1,
+                "quantity": 100
+            }],
+            "receiptOrders": [],
+            "inventoryId": "inv1",
+            "inventoryIdsMap": { "inv1": 1 },
+            "toDate": "2026-10-03"
+        });
+        let mut inputs = JsonInput::parse_batch(&input.to_string()).ok().unwrap();
+        inputs.pop().unwrap().process(|_| {})
+    }
+
+    #[test]
+    fn shapes() {
+        assert!(args(&["--shape", "rows"]).unwrap().shape == Shape::Rows);
+        assert!(args(&["--shape", "table"]).is_err());
+
+        let full = to_json(output(), Shape::Full);
+        let days = full["entries"].as_object().unwrap().len();
+        assert_eq!(days, 2);
+
+        let rows = to_json(output(), Shape::Rows);
+        let keys: Vec<&str> = rows
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row["key"].as_str().unwrap())
+            .collect();
+        assert_eq!(keys, ["20261001", "20261003"]);
+        assert_eq!(
+            rows[0]["closingStock"],
+            full["entries"]["20261001"]["data"]["closingStock"]
+        );
+
+        let summary = to_json(output(), Shape::Summary);
+        let mut fields: Vec<&String> = summary.as_object().unwrap().keys().collect();
+        fields.sort();
+        assert_eq!(fields, ["goodsIssueLedger", "totalDuration", "warnings"]);
+        assert_eq!(summary["totalDuration"], full["totalDuration"]);
+    }
+}
@@ -0,0 +1,183 @@
+//! HTTP service: serves the processor to other services (e.g. as a sidecar)
+//! with the JSON contract of process_inventory_history_wasm.
+//! Build: cargo build --release --features server
+
+use clap::Parser;
+use rust_inventory_processor::{ErrorPayloadSerializable, JsonInput};
+use std::io::Read;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+#[derive(Parser)]
+#[command(name = "inventory-server", version, about)]
+struct Args {
+    /// Address to listen on; port 0 picks a free port (printed on start).
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+    /// Largest accepted request body, in bytes.
+    #[arg(long, default_value_t = 10 * 1024 * 1024)]
+    max_body_bytes: usize,
+    /// Largest number of inputs in one batch request.
+    #[arg(long, default_value_t = 1000)]
+    max_batch: usize,
+    /// Worker threads; the number of CPUs when omitted.
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
+struct Reply {
+    status: u16,
+    body: serde_json::Value,
+}
+
+impl Reply {
+    fn ok(body: serde_json::Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::rejected(
+            status,
+            ErrorPayloadSerializable {
+                error: message.into(),
+                issues: Vec::new(),
+            },
+        )
+    }
+
+    fn rejected(status: u16, error: ErrorPayloadSerializable) -> Self {
+        Self {
+            status,
+            body: serde_json::to_value(error).expect("Failed to serialize error"),
+        }
+    }
+}
+
+fn read_body(request: &mut Request, limit: usize) -> Result<String, Reply> {
+    let too_large = || Reply::error(413, format!("Request body over {} bytes", limit));
+    if request.body_length().is_some_and(|len| len > limit) {
+        return Err(too_large());
+    }
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(limit as u64 + 1)
+        .read_to_string(&mut body)
+        .map_err(|e| Reply::error(400, format!("Invalid request body: {}", e)))?;
+    if body.len() > limit {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+/// A single input answers with its result, an array of inputs with the array
+/// of results in the same order.
+fn process(body: &str, max_batch: usize) -> Reply {
+    let batch = body.trim_start().starts_with('[');
+    let inputs = match JsonInput::parse_batch(body) {
+        Ok(inputs) => inputs,
+        Err(error) => return Reply::rejected(400, error),
+    };
+    if inputs.len() > max_batch {
+        return Reply::error(
+            413,
+            format!("Batch of {} inputs over {}", inputs.len(), max_batch),
+        );
+    }
+
+    let results = catch_unwind(AssertUnwindSafe(|| {
+        inputs
+            .into_iter()
+            .map(|input| input.process(|_| {}).into_json())
+            .collect::<Vec<_>>()
+    }));
+    match results {
+        Ok(results) if batch => Reply::ok(serde_json::Value::Array(results)),
+        Ok(mut results) => Reply::ok(results.remove(0)),
+        Err(e) => {
+            let message = if let Some(s) = e.downcast_ref::<&str>() {
+                s.to_string()
+            } else if let Some(s) = e.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "Processing failed".to_string()
+            };
+            Reply::error(500, message)
+        }
+    }
+}
+
+fn handle(request: &mut Request, args: &Args) -> Reply {
+    let path = request.url().split('?').next().unwrap_or_default();
+    match (request.method(), path) {
+        (Method::Get, "/health") => Reply::ok(serde_json::json!({ "status": "ok" })),
+        (Method::Get, "/version") => Reply::ok(serde_json::json!({
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        })),
+        (Method::Post, "/process") => match read_body(request, args.max_body_bytes) {
+            Ok(body) => process(&body, args.max_batch),
+            Err(reply) => reply,
+        },
+        (_, "/health" | "/version" | "/process") => Reply::error(405, "Method not allowed"),
+        _ => Reply::error(404, "Not found"),
+    }
+}
+
+fn serve(mut request: Request, args: &Args) {
+    let started = Instant::now();
+    let reply = handle(&mut request, args);
+    eprintln!(
+        "{} {} {} {}ms",
+        request.method(),
+        request.url(),
+        reply.status,
+        started.elapsed().as_millis()
+    );
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").expect("Invalid header");
+    let response = Response::from_string(reply.body.to_string())
+        .with_status_code(reply.status)
+        .with_header(content_type);
+    if let Err(e) = request.respond(response) {
+        eprintln!("inventory-server: {}", e);
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Arc::new(Args::parse());
+    let server = match Server::http(&args.listen) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            eprintln!("inventory-server: {}: {}", args.listen, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    eprintln!(
+        "inventory-server: listening on http://{}",
+        server.server_addr()
+    );
+
+    let threads = args
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(4, |n| n.get()));
+    let workers: Vec<_> = (0..threads.max(1))
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let args = Arc::clone(&args);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    serve(request, &args);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,222 @@
+//! C ABI for in-process callers that are not JavaScript (.NET, Go), taking
+//! and returning the JSON contract of process_inventory_history_wasm.
+//! Header: include/inventory_processor.h, a copy of the one build.rs generates.
+
+use crate::wasm::try_process_inventory_history_json;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+/// Result of the `ipr_` calls; anything but `IPR_STATUS_OK` sets the
+/// last-error message.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IprStatus {
+    Ok = 0,
+    /// A required pointer argument was NULL.
+    NullPointer = 1,
+    /// The input is not valid UTF-8.
+    InvalidUtf8 = 2,
+    /// The input does not match the JSON contract; the message is the error
+    /// payload (`{ error, issues }`) as JSON.
+    InvalidInput = 3,
+    /// Processing panicked.
+    ProcessingFailed = 4,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', "")).expect("NUL removed");
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+fn fail(status: IprStatus, message: String) -> IprStatus {
+    set_last_error(message);
+    status
+}
+
+/// Processes the NUL-terminated UTF-8 `input_json` like
+/// process_inventory_history_wasm. On `IPR_STATUS_OK`, `*output_json` is the
+/// result JSON, to be released with ipr_free_string; otherwise it is NULL.
+///
+/// # Safety
+///
+/// `input_json` must be NULL or point to a NUL-terminated string, and
+/// `output_json` must be NULL or valid for writing a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ipr_process_json(
+    input_json: *const c_char,
+    output_json: *mut *mut c_char,
+) -> IprStatus {
+    clear_last_error();
+    if output_json.is_null() {
+        return fail(IprStatus::NullPointer, "output_json is NULL".to_string());
+    }
+    *output_json = ptr::null_mut();
+    if input_json.is_null() {
+        return fail(IprStatus::NullPointer, "input_json is NULL".to_string());
+    }
+    let input_json = match CStr::from_ptr(input_json).to_str() {
+        Ok(input_json) => input_json,
+        Err(e) => return fail(IprStatus::InvalidUtf8, e.to_string()),
+    };
+
+    match catch_unwind(AssertUnwindSafe(|| {
+        try_process_inventory_history_json(input_json)
+    })) {
+        Ok(Ok(result)) => {
+            *output_json = CString::new(result)
+                .expect("JSON has no NUL bytes")
+                .into_raw();
+            IprStatus::Ok
+        }
+        Ok(Err(error)) => fail(
+            IprStatus::InvalidInput,
+            serde_json::to_string(&error).expect("Failed to serialize error"),
+        ),
+        Err(e) => {
+            let message = if let Some(s) = e.downcast_ref::<&str>() {
+                s.to_string()
+            } else if let Some(s) = e.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "Processing failed".to_string()
+            };
+            fail(IprStatus::ProcessingFailed, message)
+        }
+    }
+}
+
+/// Releases a string returned by ipr_process_json; NULL is ignored.
+///
+/// # Safety
+///
+/// `s` must be NULL or a string from ipr_process_json not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn ipr_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Message of the last failed `ipr_` call on this thread, or NULL. Owned by
+/// the library and valid until the next ipr_process_json call on the thread.
+#[no_mangle]
+pub extern "C" fn ipr_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// Library version, e.g. "0.1.0" (static string).
+#[no_mangle]
+pub extern "C" fn ipr_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = r#"{
+        "inventory": { "id": "inv1", "stockQty": 100, "innerQtyOnMas": 1, "volume": 0.01 },
+        "inventoryList": [],
+        "asinOutboundList": [],
+        "goods": null,
+        "goodsReceipt": { "importedAt": "2026-10-01T08:00:00+07:00" },
+        "supplier": null,
+        "customer": null,
+        "inventoryHistoryList": [{
+            "id": "a",
+            "createdAt": "2026-10-01T08:00:00+07:00",
+            "stockQty": 100,
+            "oldStatus": 7,
+            "newStatus": 1,
+            "quantity": 100
+        }],
+        "receiptOrders": [],
+        "inventoryId": "inv1",
+        "inventoryIdsMap": { "inv1": 1 },
+        "toDate": "2026-10-03"
+    }"#;
+
+    /// ipr_process_json on `input`: status, output and last-error message.
+    fn process(input: *const c_char) -> (IprStatus, Option<String>, Option<String>) {
+        let mut output: *mut c_char = ptr::dangling_mut();
+        let status = unsafe { ipr_process_json(input, &mut output) };
+        let text = |s: *const c_char| {
+            (!s.is_null()).then(|| unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string())
+        };
+        let result = text(output);
+        unsafe { ipr_free_string(output) };
+        (status, result, text(ipr_last_error_message()))
+    }
+
+    #[test]
+    fn committed_header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/inventory_processor.h"));
+        assert!(
+            generated == include_str!("../include/inventory_processor.h"),
+            "include/inventory_processor.h is out of date; copy {}/inventory_processor.h over it",
+            env!("OUT_DIR")
+        );
+    }
+
+    #[test]
+    fn processes_json() {
+        let input = CString::new(INPUT).unwrap();
+        let (status, output, error) = process(input.as_ptr());
+        assert_eq!(status, IprStatus::Ok);
+        assert_eq!(error, None);
+        let output: serde_json::Value = serde_json::from_str(&output.unwrap()).unwrap();
+        assert!(output["entries"].as_object().is_some_and(|e| !e.is_empty()));
+    }
+
+    #[test]
+    fn invalid_input_sets_the_error_payload() {
+        let input = CString::new(r#"{"inventoryId": 1}"#).unwrap();
+        let (status, output, error) = process(input.as_ptr());
+        assert_eq!(status, IprStatus::InvalidInput);
+        assert_eq!(output, None);
+        let error: serde_json::Value = serde_json::from_str(&error.unwrap()).unwrap();
+        assert!(error["error"].is_string());
+        assert!(error["issues"].is_array());
+
+        // A later successful call clears it.
+        let input = CString::new(INPUT).unwrap();
+        assert_eq!(process(input.as_ptr()).2, None);
+    }
+
+    #[test]
+    fn rejects_null_pointers_and_invalid_utf8() {
+        let (status, output, error) = process(ptr::null());
+        assert_eq!(status, IprStatus::NullPointer);
+        assert_eq!(output, None);
+        assert_eq!(error.as_deref(), Some("input_json is NULL"));
+
+        let input = CString::new(INPUT).unwrap();
+        let status = unsafe { ipr_process_json(input.as_ptr(), ptr::null_mut()) };
+        assert_eq!(status, IprStatus::NullPointer);
+
+        let input = CString::new(b"{\"id\": \"\xff\"}".to_vec()).unwrap();
+        let (status, output, error) = process(input.as_ptr());
+        assert_eq!(status, IprStatus::InvalidUtf8);
+        assert_eq!(output, None);
+        assert!(error.is_some());
+
+        // Freeing NULL does nothing.
+        unsafe { ipr_free_string(ptr::null_mut()) };
+    }
+
+    #[test]
+    fn version_is_the_package_version() {
+        let version = unsafe { CStr::from_ptr(ipr_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
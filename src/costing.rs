@@ -0,0 +1,556 @@
+//! Cost layers across receipts: FIFO, LIFO and weighted average costing of
+//! goods issued from several lots of the same goods.
+
+use std::collections::HashMap;
+
+use crate::process_inventory_list::{
+    Decimal, EnumProshipType_InventoryStatus, NumericConfig, ProshipGoodsReceipt, ProshipInventory,
+    ProshipInventoryHistory,
+};
+
+#[derive(Clone, PartialEq, Copy, Default)]
+pub enum CostingMethod {
+    #[default]
+    Fifo,
+    Lifo,
+    WeightedAverage,
+}
+
+impl CostingMethod {
+    pub fn from_name(v: &str) -> Option<Self> {
+        match v
+            .trim()
+            .to_ascii_lowercase()
+            .replace(['_', '-', ' '], "")
+            .as_str()
+        {
+            "fifo" => Some(Self::Fifo),
+            "lifo" => Some(Self::Lifo),
+            "weightedaverage" | "average" | "avg" | "wac" => Some(Self::WeightedAverage),
+            _ => None,
+        }
+    }
+}
+
+/// One receipt (lot) of the goods being costed.
+pub struct CostingLot<'a> {
+    pub inventory: &'a ProshipInventory,
+    pub goods_receipt: &'a ProshipGoodsReceipt,
+    /// Quantity received on the goods receipt; see `inventory_ids` of the processor input.
+    pub received_qty: i32,
+    pub inventory_history_list: &'a [ProshipInventoryHistory],
+}
+
+/// Remaining quantity of a receipt at its unit cost. Under weighted average
+/// there is one pooled layer whose `inventory_id` is empty once lots are mixed.
+#[derive(Clone, PartialEq, Default)]
+pub struct CostLayer {
+    pub inventory_id: String,
+    pub received_at: i64,
+    pub unit_cost: Decimal,
+    pub remaining_qty: i32,
+}
+
+/// Part of a movement taken from, or returned to, one layer.
+#[derive(Clone, PartialEq, Default)]
+pub struct CostLayerConsumption {
+    pub inventory_id: String,
+    pub received_at: i64,
+    pub quantity: i32,
+    pub unit_cost: Decimal,
+    pub cost: Decimal,
+}
+
+/// Cost of one transition moving stock out of AVAILABLE / ON_HAND (an issue)
+/// or back into it from a disposal status (a restore).
+#[derive(Clone, PartialEq, Default)]
+pub struct CostedIssue {
+    pub inventory_id: String,
+    pub goods_issue_id: String,
+    pub created_at: i64,
+    pub old_status: EnumProshipType_InventoryStatus,
+    pub new_status: EnumProshipType_InventoryStatus,
+    pub quantity: i32,
+    pub cost: Decimal,
+    /// Issues: quantity not matched to any layer (costed at zero). Restores:
+    /// quantity without a recorded disposal, returned at the lot's receipt cost.
+    pub uncosted_qty: i32,
+    pub layers: Vec<CostLayerConsumption>,
+}
+
+/// Shipped quantity and cost summed per goods issue.
+#[derive(Clone, PartialEq, Default)]
+pub struct GoodsIssueCost {
+    pub quantity: i32,
+    pub cost: Decimal,
+}
+
+pub struct CostingOutput {
+    /// Shipments and disposals in time order.
+    pub issues: Vec<CostedIssue>,
+    pub restores: Vec<CostedIssue>,
+    pub goods_issue_costs: HashMap<String, GoodsIssueCost>,
+    pub remaining_layers: Vec<CostLayer>,
+    /// Cost of the quantity moved to EXPORTED.
+    pub cost_of_goods_issued: Decimal,
+    /// Cost of the quantity still in damaged, return or liquidation.
+    pub disposal_cost: Decimal,
+    pub remaining_value: Decimal,
+}
+
+enum CostingEvent<'a> {
+    Receipt(usize),
+    Issue(usize, &'a ProshipInventoryHistory),
+    Restore(usize, &'a ProshipInventoryHistory),
+}
+
+fn is_stock_status(status: EnumProshipType_InventoryStatus) -> bool {
+    matches!(
+        status,
+        EnumProshipType_InventoryStatus::AVALABLE_INVENTORY_STATUS
+            | EnumProshipType_InventoryStatus::ON_HAND_INVENTORY_STATUS
+    )
+}
+
+fn is_disposal_status(status: EnumProshipType_InventoryStatus) -> bool {
+    matches!(
+        status,
+        EnumProshipType_InventoryStatus::DAMAGED_INVENTORY_STATUS
+            | EnumProshipType_InventoryStatus::RETURN_INVENTORY_STATUS
+            | EnumProshipType_InventoryStatus::LIQUIDATION_INVENTORY_STATUS
+    )
+}
+
+/// Unit price of the lot's receipt, rounded like the report's `unit_price`.
+fn receipt_unit_cost(lot: &CostingLot<'_>, numeric: &NumericConfig) -> Decimal {
+    numeric.round_price(lot.inventory.get_unit_price())
+}
+
+/// Adds `quantity` at `unit_cost` to the layers: into the pool under weighted
+/// average, otherwise into the layer of the same receipt, recreating it in
+/// receipt order when it was used up.
+fn add_to_layers(
+    layers: &mut Vec<CostLayer>,
+    method: CostingMethod,
+    numeric: &NumericConfig,
+    part: CostLayer,
+) {
+    if method == CostingMethod::WeightedAverage {
+        // A single pooled layer carries the running average cost.
+        if let Some(pool) = layers.first_mut() {
+            let total_qty = pool.remaining_qty + part.remaining_qty;
+            let total_cost = pool.unit_cost * Decimal::from_i64(pool.remaining_qty as i64)
+                + part.unit_cost * Decimal::from_i64(part.remaining_qty as i64);
+            pool.unit_cost = numeric.per_unit(total_cost, total_qty, 6);
+            pool.remaining_qty = total_qty;
+            if pool.inventory_id != part.inventory_id {
+                pool.inventory_id.clear();
+            }
+            pool.received_at = pool.received_at.max(part.received_at);
+            return;
+        }
+    } else if let Some(layer) = layers.iter_mut().find(|l| {
+        l.inventory_id == part.inventory_id
+            && l.received_at == part.received_at
+            && l.unit_cost == part.unit_cost
+    }) {
+        layer.remaining_qty += part.remaining_qty;
+        return;
+    }
+    let at = layers.partition_point(|l| l.received_at <= part.received_at);
+    layers.insert(at, part);
+}
+
+/// Assigns cost to every transition of `lots` out of AVAILABLE / ON_HAND into
+/// EXPORTED, DAMAGED, RETURN or LIQUIDATION, and returns stock moved back
+/// from a disposal status to the layers it was taken from.
+///
+/// Receipts become layers at `goods_receipt.imported_at` with the lot's
+/// `unit_price`; issues from any lot consume layers in `method` order.
+/// Events are replayed in time order, receipts before history events at the
+/// same second.
+pub fn calculate_issue_costs(
+    lots: &[CostingLot<'_>],
+    method: CostingMethod,
+    numeric: &NumericConfig,
+    value_precision: u32,
+) -> CostingOutput {
+    let mut events: Vec<(i64, u8, CostingEvent<'_>)> = Vec::new();
+    for (index, lot) in lots.iter().enumerate() {
+        events.push((
+            lot.goods_receipt.get_imported_at(),
+            0,
+            CostingEvent::Receipt(index),
+        ));
+        for history in lot.inventory_history_list {
+            let (old_status, new_status) = (history.get_old_status(), history.get_new_status());
+            let event = if is_stock_status(old_status)
+                && (is_disposal_status(new_status)
+                    || new_status == EnumProshipType_InventoryStatus::EXPORTED_INVENTORY_STATUS)
+            {
+                CostingEvent::Issue(index, history)
+            } else if is_disposal_status(old_status) && is_stock_status(new_status) {
+                CostingEvent::Restore(index, history)
+            } else {
+                continue;
+            };
+            events.push((history.get_created_at(), 1, event));
+        }
+    }
+    events.sort_by_key(|(created_at, order, _)| (*created_at, *order));
+
+    let mut layers: Vec<CostLayer> = Vec::new();
+    let mut issues: Vec<CostedIssue> = Vec::new();
+    let mut restores: Vec<CostedIssue> = Vec::new();
+    let mut goods_issue_costs: HashMap<String, GoodsIssueCost> = HashMap::new();
+    // Layer parts in each lot's disposal statuses, last disposed last.
+    let mut disposed: HashMap<(usize, u8), Vec<CostLayerConsumption>> = HashMap::new();
+
+    for (created_at, _, event) in events {
+        match event {
+            CostingEvent::Receipt(index) => {
+                let lot = &lots[index];
+                if lot.received_qty <= 0 {
+                    continue;
+                }
+                let part = CostLayer {
+                    inventory_id: lot.inventory.id.clone(),
+                    received_at: created_at,
+                    unit_cost: receipt_unit_cost(lot, numeric),
+                    remaining_qty: lot.received_qty,
+                };
+                if method == CostingMethod::WeightedAverage {
+                    add_to_layers(&mut layers, method, numeric, part);
+                } else {
+                    layers.push(part);
+                }
+            }
+            CostingEvent::Issue(index, history) => {
+                let mut issue = CostedIssue {
+                    inventory_id: lots[index].inventory.id.clone(),
+                    goods_issue_id: history.get_goods_issue_id().to_string(),
+                    created_at,
+                    old_status: history.get_old_status(),
+                    new_status: history.get_new_status(),
+                    quantity: history.get_quantity(),
+                    ..Default::default()
+                };
+                let mut remaining = history.get_quantity();
+                while remaining > 0 {
+                    let layer = match method {
+                        CostingMethod::Lifo => {
+                            layers.iter_mut().rev().find(|l| l.remaining_qty > 0)
+                        }
+                        CostingMethod::Fifo | CostingMethod::WeightedAverage => {
+                            layers.iter_mut().find(|l| l.remaining_qty > 0)
+                        }
+                    };
+                    let Some(layer) = layer else {
+                        break;
+                    };
+                    let quantity = remaining.min(layer.remaining_qty);
+                    layer.remaining_qty -= quantity;
+                    remaining -= quantity;
+                    let cost = numeric.round_decimal(
+                        layer.unit_cost * Decimal::from_i64(quantity as i64),
+                        value_precision,
+                    );
+                    issue.cost = numeric.round_decimal(issue.cost + cost, value_precision);
+                    issue.layers.push(CostLayerConsumption {
+                        inventory_id: layer.inventory_id.clone(),
+                        received_at: layer.received_at,
+                        quantity,
+                        unit_cost: layer.unit_cost,
+                        cost,
+                    });
+                }
+                issue.uncosted_qty = remaining;
+
+                if is_disposal_status(issue.new_status) {
+                    disposed
+                        .entry((index, issue.new_status as u8))
+                        .or_default()
+                        .extend(issue.layers.iter().cloned());
+                } else {
+                    let total = goods_issue_costs
+                        .entry(issue.goods_issue_id.clone())
+                        .or_default();
+                    total.quantity += issue.quantity;
+                    total.cost = numeric.round_decimal(total.cost + issue.cost, value_precision);
+                }
+                issues.push(issue);
+            }
+            CostingEvent::Restore(index, history) => {
+                let lot = &lots[index];
+                let mut restore = CostedIssue {
+                    inventory_id: lot.inventory.id.clone(),
+                    goods_issue_id: history.get_goods_issue_id().to_string(),
+                    created_at,
+                    old_status: history.get_old_status(),
+                    new_status: history.get_new_status(),
+                    quantity: history.get_quantity(),
+                    ..Default::default()
+                };
+                let parts = disposed
+                    .entry((index, restore.old_status as u8))
+                    .or_default();
+                let mut remaining = history.get_quantity();
+                while remaining > 0 {
+                    let Some(part) = parts.last_mut() else {
+                        break;
+                    };
+                    let quantity = remaining.min(part.quantity);
+                    part.quantity -= quantity;
+                    remaining -= quantity;
+                    restore.layers.push(CostLayerConsumption {
+                        quantity,
+                        cost: numeric.round_decimal(
+                            part.unit_cost * Decimal::from_i64(quantity as i64),
+                            value_precision,
+                        ),
+                        ..part.clone()
+                    });
+                    if part.quantity == 0 {
+                        parts.pop();
+                    }
+                }
+                if remaining > 0 {
+                    let unit_cost = receipt_unit_cost(lot, numeric);
+                    restore.layers.push(CostLayerConsumption {
+                        inventory_id: lot.inventory.id.clone(),
+                        received_at: lot.goods_receipt.get_imported_at(),
+                        quantity: remaining,
+                        unit_cost,
+                        cost: numeric.round_decimal(
+                            unit_cost * Decimal::from_i64(remaining as i64),
+                            value_precision,
+                        ),
+                    });
+                }
+                restore.uncosted_qty = remaining;
+
+                for part in &restore.layers {
+                    restore.cost = numeric.round_decimal(restore.cost + part.cost, value_precision);
+                    add_to_layers(
+                        &mut layers,
+                        method,
+                        numeric,
+                        CostLayer {
+                            inventory_id: part.inventory_id.clone(),
+                            received_at: part.received_at,
+                            unit_cost: part.unit_cost,
+                            remaining_qty: part.quantity,
+                        },
+                    );
+                }
+                restores.push(restore);
+            }
+        }
+    }
+
+    layers.retain(|l| l.remaining_qty > 0);
+    let cost_of_goods_issued = numeric.round_decimal(
+        issues
+            .iter()
+            .filter(|i| !is_disposal_status(i.new_status))
+            .map(|i| i.cost)
+            .sum(),
+        value_precision,
+    );
+    let disposal_cost = numeric.round_decimal(
+        disposed
+            .values()
+            .flatten()
+            .map(|p| p.unit_cost * Decimal::from_i64(p.quantity as i64))
+            .sum(),
+        value_precision,
+    );
+    let remaining_value = numeric.round_decimal(
+        layers
+            .iter()
+            .map(|l| l.unit_cost * Decimal::from_i64(l.remaining_qty as i64))
+            .sum(),
+        value_precision,
+    );
+
+    CostingOutput {
+        issues,
+        restores,
+        goods_issue_costs,
+        remaining_layers: layers,
+        cost_of_goods_issued,
+        disposal_cost,
+        remaining_value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_inventory_list::NumericMode;
+
+    use EnumProshipType_InventoryStatus::*;
+
+    fn inventory(id: &str, unit_price: f32) -> ProshipInventory {
+        ProshipInventory {
+            id: id.to_string(),
+            unit_price,
+            ..Default::default()
+        }
+    }
+
+    fn receipt(imported_at: i64) -> ProshipGoodsReceipt {
+        ProshipGoodsReceipt { imported_at }
+    }
+
+    fn event(
+        created_at: i64,
+        old_status: EnumProshipType_InventoryStatus,
+        new_status: EnumProshipType_InventoryStatus,
+        quantity: i32,
+    ) -> ProshipInventoryHistory {
+        ProshipInventoryHistory {
+            created_at,
+            old_status,
+            new_status,
+            quantity,
+            goods_issue_id: if new_status == EXPORTED_INVENTORY_STATUS {
+                format!("GI-{}", created_at)
+            } else {
+                String::new()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn decimal(v: &str) -> Decimal {
+        Decimal::parse(v)
+    }
+
+    fn numeric() -> NumericConfig {
+        NumericConfig {
+            mode: NumericMode::Decimal,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fifo_restores_disposed_stock_to_its_layer() {
+        let (lot_a, lot_b) = (inventory("A", 1.0), inventory("B", 2.0));
+        let (receipt_a, receipt_b) = (receipt(100), receipt(200));
+        let history_a = [
+            event(300, AVALABLE_INVENTORY_STATUS, DAMAGED_INVENTORY_STATUS, 4),
+            event(500, DAMAGED_INVENTORY_STATUS, AVALABLE_INVENTORY_STATUS, 4),
+        ];
+        let history_b = [
+            event(400, ON_HAND_INVENTORY_STATUS, EXPORTED_INVENTORY_STATUS, 10),
+            event(600, ON_HAND_INVENTORY_STATUS, EXPORTED_INVENTORY_STATUS, 5),
+        ];
+        let lots = [
+            CostingLot {
+                inventory: &lot_a,
+                goods_receipt: &receipt_a,
+                received_qty: 10,
+                inventory_history_list: &history_a,
+            },
+            CostingLot {
+                inventory: &lot_b,
+                goods_receipt: &receipt_b,
+                received_qty: 10,
+                inventory_history_list: &history_b,
+            },
+        ];
+
+        let output = calculate_issue_costs(&lots, CostingMethod::Fifo, &numeric(), 2);
+
+        let costs: Vec<Decimal> = output.issues.iter().map(|i| i.cost).collect();
+        assert_eq!(costs, [decimal("4"), decimal("14"), decimal("6")]);
+        assert_eq!(output.restores.len(), 1);
+        assert_eq!(output.restores[0].cost, decimal("4"));
+        assert_eq!(output.restores[0].uncosted_qty, 0);
+        // The last shipment takes the restored units of A before B.
+        let last = &output.issues[2].layers;
+        assert_eq!(last[0].inventory_id, "A");
+        assert_eq!(last[0].quantity, 4);
+        assert_eq!(last[1].inventory_id, "B");
+        assert_eq!(last[1].quantity, 1);
+        assert_eq!(output.cost_of_goods_issued, decimal("20"));
+        assert_eq!(output.goods_issue_costs["GI-400"].cost, decimal("14"));
+        assert_eq!(output.disposal_cost, decimal("0"));
+        assert_eq!(output.remaining_layers.len(), 1);
+        assert_eq!(output.remaining_layers[0].remaining_qty, 5);
+        assert_eq!(output.remaining_value, decimal("10"));
+    }
+
+    #[test]
+    fn weighted_average_excludes_disposed_stock_from_the_pool() {
+        let (lot_a, lot_b) = (inventory("A", 1.0), inventory("B", 3.0));
+        let (receipt_a, receipt_b) = (receipt(100), receipt(200));
+        let history_a = [event(
+            150,
+            AVALABLE_INVENTORY_STATUS,
+            LIQUIDATION_INVENTORY_STATUS,
+            5,
+        )];
+        let history_b = [event(
+            300,
+            ON_HAND_INVENTORY_STATUS,
+            EXPORTED_INVENTORY_STATUS,
+            6,
+        )];
+        let lots = [
+            CostingLot {
+                inventory: &lot_a,
+                goods_receipt: &receipt_a,
+                received_qty: 10,
+                inventory_history_list: &history_a,
+            },
+            CostingLot {
+                inventory: &lot_b,
+                goods_receipt: &receipt_b,
+                received_qty: 10,
+                inventory_history_list: &history_b,
+            },
+        ];
+
+        let output = calculate_issue_costs(&lots, CostingMethod::WeightedAverage, &numeric(), 2);
+
+        // 5 left at 1 plus 10 at 3 average to 2.333333.
+        assert_eq!(output.issues[0].cost, decimal("5"));
+        assert_eq!(output.issues[1].cost, decimal("14"));
+        assert_eq!(output.cost_of_goods_issued, decimal("14"));
+        assert_eq!(output.disposal_cost, decimal("5"));
+        assert_eq!(output.remaining_layers.len(), 1);
+        assert_eq!(output.remaining_layers[0].unit_cost, decimal("2.333333"));
+        assert_eq!(output.remaining_layers[0].remaining_qty, 9);
+        assert_eq!(output.remaining_value, decimal("21"));
+    }
+
+    #[test]
+    fn receipt_costs_use_the_price_precision() {
+        let lot = inventory("A", 1.23456);
+        let goods_receipt = receipt(100);
+        let history = [event(
+            200,
+            ON_HAND_INVENTORY_STATUS,
+            EXPORTED_INVENTORY_STATUS,
+            10,
+        )];
+        let lots = [CostingLot {
+            inventory: &lot,
+            goods_receipt: &goods_receipt,
+            received_qty: 10,
+            inventory_history_list: &history,
+        }];
+        let cost = |price_precision| {
+            let numeric = NumericConfig {
+                price_precision,
+                ..numeric()
+            };
+            calculate_issue_costs(&lots, CostingMethod::Fifo, &numeric, 4).issues[0].cost
+        };
+
+        assert_eq!(cost(Some(2)), decimal("12.3"));
+        assert_eq!(cost(None), decimal("12.3456"));
+    }
+}
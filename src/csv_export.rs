@@ -0,0 +1,478 @@
+//! CSV export of the daily inventory rows, for spreadsheets.
+
+use crate::process_inventory_list::{
+    Decimal, InventoryData, ProcessInventoryHistoryOutput, RoundingMode,
+};
+
+/// Language of the CSV header row.
+#[derive(Clone, PartialEq, Copy, Default)]
+pub enum CsvLanguage {
+    #[default]
+    English,
+    Vietnamese,
+}
+
+impl CsvLanguage {
+    pub fn from_name(v: &str) -> Option<Self> {
+        match v.trim().to_ascii_lowercase().as_str() {
+            "en" | "eng" | "english" => Some(Self::English),
+            "vi" | "vn" | "vietnamese" | "tiengviet" => Some(Self::Vietnamese),
+            _ => None,
+        }
+    }
+}
+
+/// One CSV column. Nested fields of the JSON row are flattened, e.g.
+/// `dimension.length` is `DimensionLength` and `closingBalances.availableQty`
+/// is `AvailableQty`.
+#[derive(Clone, PartialEq, Copy)]
+pub enum CsvColumn {
+    /// Row key, yyyymmdd in the report timezone.
+    Key,
+    Date,
+    OpeningStock,
+    OpeningCbm,
+    OpeningMasterQty,
+    OpeningPalletQty,
+    Asin,
+    AsinOutbound,
+    UnitPrice,
+    ReceivedDate,
+    InnerQtyOnMas,
+    LineInCd,
+    PoNo,
+    MasterDimensionLength,
+    MasterDimensionWidth,
+    MasterDimensionHeight,
+    DimensionLength,
+    DimensionWidth,
+    DimensionHeight,
+    InboundQty,
+    InboundCbm,
+    InboundMasterQty,
+    InboundPalletQty,
+    ClosingStock,
+    ClosingCbm,
+    ClosingMasterQty,
+    ClosingPalletQty,
+    AllocatedQty,
+    AllocatedCbm,
+    AllocatedMasterQty,
+    DisposalStock,
+    DisposalCbm,
+    DisposalMasterQty,
+    RestoreStockQty,
+    RestoreStockCbm,
+    RestoreMasterQty,
+    OutboundQty,
+    OutboundCbm,
+    OutboundMasterQty,
+    OutboundPalletQty,
+    PalletFootprint,
+    StorageTimeDays,
+    Currency,
+    OpeningValue,
+    InboundValue,
+    OutboundValue,
+    DisposalValue,
+    ClosingValue,
+    AvailableQty,
+    OnHandQty,
+    DamagedQty,
+    ReturnQty,
+    LiquidationQty,
+    ExportedQty,
+    GoodsIssueIds,
+}
+
+pub(crate) enum CsvValue {
+    Int(i64),
+    F32(f32),
+    F64(f64),
+    Decimal(Decimal),
+    Text(String),
+}
+
+impl CsvColumn {
+    pub const ALL: [CsvColumn; 55] = [
+        Self::Key,
+        Self::Date,
+        Self::OpeningStock,
+        Self::OpeningCbm,
+        Self::OpeningMasterQty,
+        Self::OpeningPalletQty,
+        Self::Asin,
+        Self::AsinOutbound,
+        Self::UnitPrice,
+        Self::ReceivedDate,
+        Self::InnerQtyOnMas,
+        Self::LineInCd,
+        Self::PoNo,
+        Self::MasterDimensionLength,
+        Self::MasterDimensionWidth,
+        Self::MasterDimensionHeight,
+        Self::DimensionLength,
+        Self::DimensionWidth,
+        Self::DimensionHeight,
+        Self::InboundQty,
+        Self::InboundCbm,
+        Self::InboundMasterQty,
+        Self::InboundPalletQty,
+        Self::ClosingStock,
+        Self::ClosingCbm,
+        Self::ClosingMasterQty,
+        Self::ClosingPalletQty,
+        Self::AllocatedQty,
+        Self::AllocatedCbm,
+        Self::AllocatedMasterQty,
+        Self::DisposalStock,
+        Self::DisposalCbm,
+        Self::DisposalMasterQty,
+        Self::RestoreStockQty,
+        Self::RestoreStockCbm,
+        Self::RestoreMasterQty,
+        Self::OutboundQty,
+        Self::OutboundCbm,
+        Self::OutboundMasterQty,
+        Self::OutboundPalletQty,
+        Self::PalletFootprint,
+        Self::StorageTimeDays,
+        Self::Currency,
+        Self::OpeningValue,
+        Self::InboundValue,
+        Self::OutboundValue,
+        Self::DisposalValue,
+        Self::ClosingValue,
+        Self::AvailableQty,
+        Self::OnHandQty,
+        Self::DamagedQty,
+        Self::ReturnQty,
+        Self::LiquidationQty,
+        Self::ExportedQty,
+        Self::GoodsIssueIds,
+    ];
+
+    /// The day, stock movement and goods issue columns most reports need.
+    pub fn default_columns() -> Vec<CsvColumn> {
+        vec![
+            Self::Key,
+            Self::OpeningStock,
+            Self::InboundQty,
+            Self::OutboundQty,
+            Self::DisposalStock,
+            Self::RestoreStockQty,
+            Self::ClosingStock,
+            Self::ClosingCbm,
+            Self::StorageTimeDays,
+            Self::GoodsIssueIds,
+        ]
+    }
+
+    /// Name of the field in the JSON row (camelCase), also used to select columns.
+    pub fn name(&self) -> &'static str {
+        self.labels().0
+    }
+
+    /// Column for a JSON field name; case, `_`, `-` and spaces are ignored,
+    /// so `closing_cbm` and `closingCbm` both match.
+    pub fn from_name(v: &str) -> Option<Self> {
+        let normalize = |v: &str| v.to_ascii_lowercase().replace(['_', '-', ' ', '.'], "");
+        let v = normalize(v.trim());
+        Self::ALL.into_iter().find(|c| normalize(c.name()) == v)
+    }
+
+    pub fn header(&self, language: CsvLanguage) -> &'static str {
+        let (_, english, vietnamese) = self.labels();
+        match language {
+            CsvLanguage::English => english,
+            CsvLanguage::Vietnamese => vietnamese,
+        }
+    }
+
+    /// JSON name, English header and Vietnamese header.
+    fn labels(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Self::Key => ("key", "Day", "Ngày"),
+            Self::Date => ("date", "Timestamp", "Thời điểm"),
+            Self::OpeningStock => ("openingStock", "Opening stock", "Tồn đầu kỳ"),
+            Self::OpeningCbm => ("openingCbm", "Opening CBM", "CBM đầu kỳ"),
+            Self::OpeningMasterQty => (
+                "openingMasterQty",
+                "Opening master cartons",
+                "Thùng master đầu kỳ",
+            ),
+            Self::OpeningPalletQty => ("openingPalletQty", "Opening pallets", "Pallet đầu kỳ"),
+            Self::Asin => ("asin", "ASIN", "ASIN"),
+            Self::AsinOutbound => ("asinOutbound", "Outbound ASINs", "ASIN xuất"),
+            Self::UnitPrice => ("unitPrice", "Unit price", "Đơn giá"),
+            Self::ReceivedDate => ("receivedDate", "Received date", "Ngày nhận hàng"),
+            Self::InnerQtyOnMas => (
+                "innerQtyOnMas",
+                "Units per master carton",
+                "Số lượng/thùng master",
+            ),
+            Self::LineInCd => ("lineInCd", "Line in CD", "Line in CD"),
+            Self::PoNo => ("poNo", "PO number", "Số PO"),
+            Self::MasterDimensionLength => (
+                "masterDimensionLength",
+                "Master carton length",
+                "Dài thùng master",
+            ),
+            Self::MasterDimensionWidth => (
+                "masterDimensionWidth",
+                "Master carton width",
+                "Rộng thùng master",
+            ),
+            Self::MasterDimensionHeight => (
+                "masterDimensionHeight",
+                "Master carton height",
+                "Cao thùng master",
+            ),
+            Self::DimensionLength => ("dimensionLength", "Length", "Dài"),
+            Self::DimensionWidth => ("dimensionWidth", "Width", "Rộng"),
+            Self::DimensionHeight => ("dimensionHeight", "Height", "Cao"),
+            Self::InboundQty => ("inboundQty", "Inbound qty", "SL nhập"),
+            Self::InboundCbm => ("inboundCbm", "Inbound CBM", "CBM nhập"),
+            Self::InboundMasterQty => (
+                "inboundMasterQty",
+                "Inbound master cartons",
+                "Thùng master nhập",
+            ),
+            Self::InboundPalletQty => ("inboundPalletQty", "Inbound pallets", "Pallet nhập"),
+            Self::ClosingStock => ("closingStock", "Closing stock", "Tồn cuối kỳ"),
+            Self::ClosingCbm => ("closingCbm", "Closing CBM", "CBM cuối kỳ"),
+            Self::ClosingMasterQty => (
+                "closingMasterQty",
+                "Closing master cartons",
+                "Thùng master cuối kỳ",
+            ),
+            Self::ClosingPalletQty => ("closingPalletQty", "Closing pallets", "Pallet cuối kỳ"),
+            Self::AllocatedQty => ("allocatedQty", "Allocated qty", "SL đã phân bổ"),
+            Self::AllocatedCbm => ("allocatedCbm", "Allocated CBM", "CBM đã phân bổ"),
+            Self::AllocatedMasterQty => (
+                "allocatedMasterQty",
+                "Allocated master cartons",
+                "Thùng master đã phân bổ",
+            ),
+            Self::DisposalStock => ("disposalStock", "Disposal qty", "SL hủy"),
+            Self::DisposalCbm => ("disposalCbm", "Disposal CBM", "CBM hủy"),
+            Self::DisposalMasterQty => (
+                "disposalMasterQty",
+                "Disposal master cartons",
+                "Thùng master hủy",
+            ),
+            Self::RestoreStockQty => ("restoreStockQty", "Restored qty", "SL hoàn lại"),
+            Self::RestoreStockCbm => ("restoreStockCbm", "Restored CBM", "CBM hoàn lại"),
+            Self::RestoreMasterQty => (
+                "restoreMasterQty",
+                "Restored master cartons",
+                "Thùng master hoàn lại",
+            ),
+            Self::OutboundQty => ("outboundQty", "Outbound qty", "SL xuất"),
+            Self::OutboundCbm => ("outboundCbm", "Outbound CBM", "CBM xuất"),
+            Self::OutboundMasterQty => (
+                "outboundMasterQty",
+                "Outbound master cartons",
+                "Thùng master xuất",
+            ),
+            Self::OutboundPalletQty => ("outboundPalletQty", "Outbound pallets", "Pallet xuất"),
+            Self::PalletFootprint => ("palletFootprint", "Pallet footprint", "Diện tích pallet"),
+            Self::StorageTimeDays => ("storageTimeDays", "Storage days", "Số ngày lưu kho"),
+            Self::Currency => ("currency", "Currency", "Tiền tệ"),
+            Self::OpeningValue => ("openingValue", "Opening value", "Giá trị đầu kỳ"),
+            Self::InboundValue => ("inboundValue", "Inbound value", "Giá trị nhập"),
+            Self::OutboundValue => ("outboundValue", "Outbound value", "Giá trị xuất"),
+            Self::DisposalValue => ("disposalValue", "Disposal value", "Giá trị hủy"),
+            Self::ClosingValue => ("closingValue", "Closing value", "Giá trị cuối kỳ"),
+            Self::AvailableQty => ("availableQty", "Available qty", "SL khả dụng"),
+            Self::OnHandQty => ("onHandQty", "On-hand qty", "SL đang giữ"),
+            Self::DamagedQty => ("damagedQty", "Damaged qty", "SL hư hỏng"),
+            Self::ReturnQty => ("returnQty", "Return qty", "SL trả về"),
+            Self::LiquidationQty => ("liquidationQty", "Liquidation qty", "SL thanh lý"),
+            Self::ExportedQty => ("exportedQty", "Exported qty", "SL đã xuất"),
+            Self::GoodsIssueIds => ("goodsIssueIds", "Goods issue IDs", "Mã phiếu xuất"),
+        }
+    }
+
+    pub(crate) fn value(
+        &self,
+        key: &str,
+        d: &InventoryData,
+        goods_issue_ids: &[&String],
+    ) -> CsvValue {
+        let b = &d.closing_balances;
+        match self {
+            Self::Key => CsvValue::Text(key.to_string()),
+            Self::Date => CsvValue::Int(d.date),
+            Self::OpeningStock => CsvValue::Int(d.opening_stock as i64),
+            Self::OpeningCbm => CsvValue::Decimal(d.get_opening_cbm_decimal()),
+            Self::OpeningMasterQty => CsvValue::Int(d.opening_master_qty as i64),
+            Self::OpeningPalletQty => CsvValue::Int(d.opening_pallet_qty as i64),
+            Self::Asin => CsvValue::Text(d.asin.clone()),
+            Self::AsinOutbound => CsvValue::Text(d.asin_outbound.join(", ")),
+            Self::UnitPrice => CsvValue::Decimal(d.get_unit_price_decimal()),
+            Self::ReceivedDate => CsvValue::Int(d.received_date),
+            Self::InnerQtyOnMas => CsvValue::Int(d.inner_qty_on_mas as i64),
+            Self::LineInCd => CsvValue::Text(d.line_in_cd.clone()),
+            Self::PoNo => CsvValue::Text(d.po_no.clone()),
+            Self::MasterDimensionLength => CsvValue::F64(d.master_dimension.length),
+            Self::MasterDimensionWidth => CsvValue::F64(d.master_dimension.width),
+            Self::MasterDimensionHeight => CsvValue::F64(d.master_dimension.height),
+            Self::DimensionLength => CsvValue::F64(d.dimension.length),
+            Self::DimensionWidth => CsvValue::F64(d.dimension.width),
+            Self::DimensionHeight => CsvValue::F64(d.dimension.height),
+            Self::InboundQty => CsvValue::Int(d.inbound_qty as i64),
+            Self::InboundCbm => CsvValue::Decimal(d.get_inbound_cbm_decimal()),
+            Self::InboundMasterQty => CsvValue::Int(d.inbound_master_qty as i64),
+            Self::InboundPalletQty => CsvValue::Int(d.inbound_pallet_qty as i64),
+            Self::ClosingStock => CsvValue::Int(d.closing_stock as i64),
+            Self::ClosingCbm => CsvValue::Decimal(d.get_closing_cbm_decimal()),
+            Self::ClosingMasterQty => CsvValue::Int(d.closing_master_qty as i64),
+            Self::ClosingPalletQty => CsvValue::Int(d.closing_pallet_qty as i64),
+            Self::AllocatedQty => CsvValue::Int(d.allocated_qty as i64),
+            Self::AllocatedCbm => CsvValue::Decimal(d.get_allocated_cbm_decimal()),
+            Self::AllocatedMasterQty => CsvValue::Int(d.allocated_master_qty as i64),
+            Self::DisposalStock => CsvValue::Int(d.disposal_stock as i64),
+            Self::DisposalCbm => CsvValue::Decimal(d.get_disposal_cbm_decimal()),
+            Self::DisposalMasterQty => CsvValue::Int(d.disposal_master_qty as i64),
+            Self::RestoreStockQty => CsvValue::Int(d.restore_stock_qty as i64),
+            Self::RestoreStockCbm => CsvValue::Decimal(d.get_restore_stock_cbm_decimal()),
+            Self::RestoreMasterQty => CsvValue::Int(d.restore_master_qty as i64),
+            Self::OutboundQty => CsvValue::Int(d.outbound_qty as i64),
+            Self::OutboundCbm => CsvValue::Decimal(d.get_outbound_cbm_decimal()),
+            Self::OutboundMasterQty => CsvValue::Int(d.outbound_master_qty as i64),
+            Self::OutboundPalletQty => CsvValue::Int(d.outbound_pallet_qty as i64),
+            Self::PalletFootprint => CsvValue::F32(d.pallet_footprint),
+            Self::StorageTimeDays => CsvValue::Int(d.storage_time_days as i64),
+            Self::Currency => CsvValue::Text(d.currency.clone()),
+            Self::OpeningValue => CsvValue::Decimal(d.opening_value),
+            Self::InboundValue => CsvValue::Decimal(d.inbound_value),
+            Self::OutboundValue => CsvValue::Decimal(d.outbound_value),
+            Self::DisposalValue => CsvValue::Decimal(d.disposal_value),
+            Self::ClosingValue => CsvValue::Decimal(d.closing_value),
+            Self::AvailableQty => CsvValue::Int(b.available_qty as i64),
+            Self::OnHandQty => CsvValue::Int(b.on_hand_qty as i64),
+            Self::DamagedQty => CsvValue::Int(b.damaged_qty as i64),
+            Self::ReturnQty => CsvValue::Int(b.return_qty as i64),
+            Self::LiquidationQty => CsvValue::Int(b.liquidation_qty as i64),
+            Self::ExportedQty => CsvValue::Int(b.exported_qty as i64),
+            Self::GoodsIssueIds => CsvValue::Text(
+                goods_issue_ids
+                    .iter()
+                    .map(|s| s.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct CsvOptions {
+    pub columns: Vec<CsvColumn>,
+    pub language: CsvLanguage,
+    pub delimiter: char,
+    /// `,` for spreadsheets in locales such as vi-VN; use `;` as the delimiter then.
+    pub decimal_separator: char,
+    /// Fixed number of decimals for CBM, price, dimension and value columns;
+    /// `None` writes the shortest exact form.
+    pub precision: Option<usize>,
+    pub include_header: bool,
+    /// Start with a UTF-8 byte order mark so Excel shows Vietnamese headers correctly.
+    pub byte_order_mark: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            columns: CsvColumn::default_columns(),
+            language: CsvLanguage::English,
+            delimiter: ',',
+            decimal_separator: '.',
+            precision: None,
+            include_header: true,
+            byte_order_mark: false,
+        }
+    }
+}
+
+impl CsvOptions {
+    fn format_number(&self, v: String) -> String {
+        if self.decimal_separator == '.' {
+            v
+        } else {
+            v.replace('.', &self.decimal_separator.to_string())
+        }
+    }
+
+    fn format(&self, value: CsvValue) -> String {
+        match value {
+            CsvValue::Int(v) => v.to_string(),
+            CsvValue::F32(v) => self.format_number(match self.precision {
+                Some(p) => format!("{:.*}", p, v),
+                None => v.to_string(),
+            }),
+            CsvValue::F64(v) => self.format_number(match self.precision {
+                Some(p) => format!("{:.*}", p, v),
+                None => v.to_string(),
+            }),
+            CsvValue::Decimal(v) => self.format_number(match self.precision {
+                Some(p) => v.round(p as u32, RoundingMode::HalfUp).to_string(),
+                None => v.normalize().to_string(),
+            }),
+            CsvValue::Text(v) => v,
+        }
+    }
+
+    /// Quotes fields containing the delimiter, quotes or line breaks (RFC 4180).
+    fn escape(&self, field: &str) -> String {
+        if field.contains([self.delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    fn push_line(&self, csv: &mut String, fields: impl Iterator<Item = String>) {
+        for (i, field) in fields.enumerate() {
+            if i > 0 {
+                csv.push(self.delimiter);
+            }
+            csv.push_str(&self.escape(&field));
+        }
+        csv.push_str("\r\n");
+    }
+}
+
+/// One line per daily row of `merged_inventory_history`, sorted by key.
+pub fn write_inventory_csv(output: &ProcessInventoryHistoryOutput, options: &CsvOptions) -> String {
+    let mut csv = String::new();
+    if options.byte_order_mark {
+        csv.push('\u{feff}');
+    }
+    if options.include_header {
+        options.push_line(
+            &mut csv,
+            options
+                .columns
+                .iter()
+                .map(|c| c.header(options.language).to_string()),
+        );
+    }
+
+    let mut keys: Vec<&String> = output.merged_inventory_history.keys().collect();
+    keys.sort();
+    for key in keys {
+        let (data, goods_issue_ids) = &output.merged_inventory_history[key];
+        let mut goods_issue_ids: Vec<&String> = goods_issue_ids.iter().collect();
+        goods_issue_ids.sort();
+        options.push_line(
+            &mut csv,
+            options
+                .columns
+                .iter()
+                .map(|c| options.format(c.value(key, data, &goods_issue_ids))),
+        );
+    }
+    csv
+}
@@ -1,12 +1,76 @@
 //! Rust inventory history processor.
 //! WASM build: wasm-pack build --target web --features wasm
 
+mod aging;
+mod costing;
+mod csv_export;
 mod process_inventory_list;
+mod spreadsheet_import;
 
+pub use aging::{
+    aging_report, daily_aging_report, default_aging_buckets, AgingBucket, AgingBucketTotal,
+    AgingLot, AgingRangeError, AgingReport, MAX_AGING_REPORT_DAYS,
+};
+pub use costing::{
+    calculate_issue_costs, CostLayer, CostLayerConsumption, CostedIssue, CostingLot, CostingMethod,
+    CostingOutput, GoodsIssueCost,
+};
+pub use csv_export::{write_inventory_csv, CsvColumn, CsvLanguage, CsvOptions};
 pub use process_inventory_list::{
-    process_inventory_history_data, ProcessInventoryHistoryInput,
-    ProcessInventoryHistoryOutput,
+    calculate_cbm, calculate_cbm_decimal, calculate_cbm_with, calculate_master_qty,
+    calculate_pallet_qty, is_valid_timestamp, is_valid_utc_offset, parse_timestamp,
+    parse_timestamp_with_offset, process_inventory_history_data,
+    process_inventory_history_incremental, process_inventory_history_iter, snapshot_inventory_at,
+    BatchResult, Decimal, DimensionUnit, EnumProshipType_InventoryStatus, FxRate, GoodsIssueLedger,
+    GoodsIssueMovement, HistoryColumns, HistoryColumnsError, InventoryData,
+    InventoryHistoryProcessor, InventorySnapshot, NumericConfig, NumericMode, OutOfOrderEvent,
+    ProcessInventoryHistoryInput, ProcessInventoryHistoryOutput, ProcessOptions,
+    ProcessorCheckpoint, ProshipDimension, ProshipGoodsReceipt, ProshipInventory,
+    ProshipInventoryHistory, RoundingMode, StatusBalances, ValuationOptions, GMT7_OFFSET_SECONDS,
+    MAX_TIMESTAMP, MIN_TIMESTAMP,
 };
+pub use spreadsheet_import::{
+    history_by_inventory_from_table, history_from_table, inventories_from_table,
+    table_has_inventory_ids, ColumnMapping, ImportOptions, StatusMapping, Table,
+};
+
+#[cfg(feature = "xlsx")]
+mod xlsx_export;
+
+#[cfg(feature = "xlsx")]
+pub use rust_xlsxwriter::XlsxError;
+#[cfg(feature = "xlsx")]
+pub use xlsx_export::{write_inventory_xlsx, XlsxOptions};
+
+#[cfg(feature = "arrow")]
+mod arrow_export;
+
+#[cfg(feature = "arrow")]
+pub use arrow_export::{inventory_record_batch, write_inventory_parquet, ArrowOptions};
 
 #[cfg(feature = "wasm")]
 mod wasm;
+
+#[cfg(feature = "wasm")]
+pub use wasm::{
+    apply_inventories, costing_report_json, costing_report_native,
+    process_inventory_history_columnar, process_inventory_history_csv_native,
+    process_inventory_history_native, process_inventory_history_ndjson, typescript_declarations,
+    ErrorPayloadSerializable, JsonInput, JsonOutput, NdjsonInventoryProcessor,
+    ValidationIssueSerializable,
+};
+
+#[cfg(all(feature = "wasm", feature = "xlsx"))]
+pub use wasm::process_inventory_history_xlsx_native;
+
+#[cfg(feature = "capi")]
+mod capi;
+
+#[cfg(feature = "capi")]
+pub use capi::{ipr_free_string, ipr_last_error_message, ipr_process_json, ipr_version, IprStatus};
+
+#[cfg(feature = "schema")]
+mod schema;
+
+#[cfg(feature = "schema")]
+pub use schema::{error_schema, input_schema, output_schema, validate_input};
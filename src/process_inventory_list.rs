@@ -1,12 +1,13 @@
 //! Split: process_inventory_history_data (pure) + handle_inventory_list (fetch + process + return)
 //! Self-contained - no external imports. For TypeScript implementation reference.
 
+use std::borrow::Borrow;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
-#[cfg(not(feature = "wasm"))]
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use chrono::{Datelike, DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
 
 // =============================================================================
 // Types - all definitions inline for standalone use
@@ -42,19 +43,62 @@ impl EnumProshipType_InventoryStatus {
     }
 }
 
+/// Unit of `ProshipDimension` length/width/height.
+#[derive(Clone, PartialEq, Copy, Default, Debug)]
+pub enum DimensionUnit {
+    #[default]
+    Centimeter,
+    Millimeter,
+    Inch,
+}
+
+impl DimensionUnit {
+    /// Unit for a name like "cm", "mm" or "inch"; None for an unknown name.
+    pub fn from_name(v: &str) -> Option<Self> {
+        match v.trim().to_ascii_lowercase().as_str() {
+            "cm" | "centimeter" | "centimetre" => Some(Self::Centimeter),
+            "mm" | "millimeter" | "millimetre" => Some(Self::Millimeter),
+            "in" | "inch" | "inches" => Some(Self::Inch),
+            _ => None,
+        }
+    }
+
+    /// Length of one unit in meters.
+    pub fn to_meters(&self) -> f64 {
+        match self {
+            Self::Centimeter => 0.01,
+            Self::Millimeter => 0.001,
+            Self::Inch => 0.0254,
+        }
+    }
+}
+
 #[derive(PartialEq, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct ProshipDimension {
     pub length: f64,
     pub width: f64,
     pub height: f64,
 }
 
+impl ProshipDimension {
+    /// Volume in cubic meters for dimensions measured in `unit`.
+    pub fn volume_cbm(&self, unit: DimensionUnit) -> f64 {
+        let factor = unit.to_meters();
+        (self.length * factor) * (self.width * factor) * (self.height * factor)
+    }
+}
+
 #[derive(PartialEq, Clone, Default)]
 pub struct ProshipInventory {
     pub id: String,
     pub creator_id: i64,
-    pub created_at: i32,
-    pub updated_at: i32,
+    pub created_at: i64,
+    pub updated_at: i64,
     pub status: EnumProshipType_InventoryStatus,
     pub shelf_code: String,
     pub customer_id: i64,
@@ -64,7 +108,7 @@ pub struct ProshipInventory {
     pub goods_issue_id: String,
     pub goods_id: String,
     pub duration: i32,
-    pub export_at: i32,
+    pub export_at: i64,
     pub asin: String,
     pub supplier_id: String,
     pub asin_outbound: String,
@@ -78,6 +122,14 @@ pub struct ProshipInventory {
     pub master_volume: f32,
     pub master_qty: i32,
     pub do_no: String,
+    /// Unit of the dimensions; None for centimeters.
+    pub dimension_unit: Option<DimensionUnit>,
+    /// Unit name as given when it wasn't recognised, reported in the
+    /// resolve_volumes warnings; empty otherwise.
+    pub unknown_dimension_unit: String,
+    pub cartons_per_pallet: i32,
+    pub pallet_footprint: f32,
+    pub currency: String,
 }
 
 impl ProshipInventory {
@@ -111,25 +163,68 @@ impl ProshipInventory {
     pub fn get_dimension(&self) -> ProshipDimension {
         self.dimension.clone().unwrap_or_default()
     }
+    pub fn get_dimension_unit(&self) -> DimensionUnit {
+        self.dimension_unit.unwrap_or_default()
+    }
+    pub fn get_cartons_per_pallet(&self) -> i32 {
+        self.cartons_per_pallet
+    }
+    pub fn get_pallet_footprint(&self) -> f32 {
+        self.pallet_footprint
+    }
+    pub fn get_currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// Unit and master carton volume, falling back to `dimension`/`master_dimension`
+    /// when the explicit volume is missing or differs from the dimensions by more
+    /// than `tolerance` (relative). Mismatches are reported in the returned warnings.
+    pub fn resolve_volumes(&self, tolerance: f32) -> (f32, f32, Vec<String>) {
+        let mut warnings = Vec::new();
+        if !self.unknown_dimension_unit.is_empty() {
+            warnings.push(format!(
+                "Unknown dimensionUnit {:?}, using centimeters",
+                self.unknown_dimension_unit
+            ));
+        }
+        let unit = self.get_dimension_unit();
+        let volume = resolve_volume(
+            "volume",
+            self.volume,
+            self.dimension.as_ref(),
+            unit,
+            tolerance,
+            &mut warnings,
+        );
+        let master_volume = resolve_volume(
+            "masterVolume",
+            self.master_volume,
+            self.master_dimension.as_ref(),
+            unit,
+            tolerance,
+            &mut warnings,
+        );
+        (volume, master_volume, warnings)
+    }
 }
 
 #[derive(Default)]
 pub struct ProshipGoodsReceipt {
-    pub imported_at: i32,
+    pub imported_at: i64,
 }
 
 impl ProshipGoodsReceipt {
     pub fn new() -> Self {
         Default::default()
     }
-    pub fn get_imported_at(&self) -> i32 {
+    pub fn get_imported_at(&self) -> i64 {
         self.imported_at
     }
 }
 
-#[derive(Default)]
+#[derive(PartialEq, Clone, Default)]
 pub struct ProshipInventoryHistory {
-    pub created_at: i32,
+    pub created_at: i64,
     pub stock_qty: i32,
     pub old_status: EnumProshipType_InventoryStatus,
     pub new_status: EnumProshipType_InventoryStatus,
@@ -141,7 +236,7 @@ impl ProshipInventoryHistory {
     pub fn new() -> Self {
         Default::default()
     }
-    pub fn get_created_at(&self) -> i32 {
+    pub fn get_created_at(&self) -> i64 {
         self.created_at
     }
     pub fn get_stock_qty(&self) -> i32 {
@@ -161,17 +256,161 @@ impl ProshipInventoryHistory {
     }
 }
 
-#[derive(Default)]
+/// History events as parallel columns (struct of arrays), e.g. typed arrays from
+/// JS. Goods issue ids are dictionary-encoded: `goods_issue_index[i]` points into
+/// `goods_issue_ids`, negative for none.
+pub struct HistoryColumns<'a> {
+    pub created_at: &'a [i64],
+    pub stock_qty: &'a [i32],
+    pub old_status: &'a [i32],
+    pub new_status: &'a [i32],
+    pub quantity: &'a [i32],
+    pub goods_issue_index: &'a [i32],
+    pub goods_issue_ids: &'a [String],
+}
+
+impl HistoryColumns<'_> {
+    pub fn len(&self) -> usize {
+        self.created_at.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.created_at.is_empty()
+    }
+
+    /// Checks that every column has one value per event and that timestamps
+    /// and goods issue indexes are in range.
+    pub fn validate(&self) -> Result<(), HistoryColumnsError> {
+        let len = self.len();
+        let goods_issue_index_len = if self.goods_issue_index.is_empty() {
+            len
+        } else {
+            self.goods_issue_index.len()
+        };
+        for (column, column_len) in [
+            ("stock_qty", self.stock_qty.len()),
+            ("old_status", self.old_status.len()),
+            ("new_status", self.new_status.len()),
+            ("quantity", self.quantity.len()),
+            ("goods_issue_index", goods_issue_index_len),
+        ] {
+            if column_len != len {
+                return Err(HistoryColumnsError {
+                    column,
+                    index: None,
+                    message: format!("expected {} values, found {}", len, column_len),
+                });
+            }
+        }
+        if let Some(i) = self
+            .created_at
+            .iter()
+            .position(|&ts| !is_valid_timestamp(ts))
+        {
+            return Err(HistoryColumnsError {
+                column: "created_at",
+                index: Some(i),
+                message: format!(
+                    "timestamp {} is out of range (years 1 to 9999)",
+                    self.created_at[i]
+                ),
+            });
+        }
+        if let Some(i) = self
+            .goods_issue_index
+            .iter()
+            .position(|&idx| idx >= 0 && idx as usize >= self.goods_issue_ids.len())
+        {
+            return Err(HistoryColumnsError {
+                column: "goods_issue_index",
+                index: Some(i),
+                message: format!(
+                    "index {} is out of range ({} goods issue ids)",
+                    self.goods_issue_index[i],
+                    self.goods_issue_ids.len()
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Calls `f` with each event in column order, like iter but reusing one
+    /// event: goods issue ids are copied into its buffer, not allocated per event.
+    /// Call validate first: mismatched columns panic.
+    pub fn for_each(&self, mut f: impl FnMut(&ProshipInventoryHistory)) {
+        let mut event = ProshipInventoryHistory::new();
+        for i in 0..self.len() {
+            event.created_at = self.created_at[i];
+            event.stock_qty = self.stock_qty[i];
+            event.old_status = EnumProshipType_InventoryStatus::from_i32(self.old_status[i]);
+            event.new_status = EnumProshipType_InventoryStatus::from_i32(self.new_status[i]);
+            event.quantity = self.quantity[i];
+            event.goods_issue_id.clear();
+            if let Some(&idx) = self.goods_issue_index.get(i) {
+                if idx >= 0 {
+                    event
+                        .goods_issue_id
+                        .push_str(&self.goods_issue_ids[idx as usize]);
+                }
+            }
+            f(&event);
+        }
+    }
+
+    /// Events in column order; goods_issue_index may be empty when no event has one.
+    /// Call validate first: mismatched columns panic.
+    pub fn iter(&self) -> impl Iterator<Item = ProshipInventoryHistory> + '_ {
+        (0..self.len()).map(move |i| ProshipInventoryHistory {
+            created_at: self.created_at[i],
+            stock_qty: self.stock_qty[i],
+            old_status: EnumProshipType_InventoryStatus::from_i32(self.old_status[i]),
+            new_status: EnumProshipType_InventoryStatus::from_i32(self.new_status[i]),
+            quantity: self.quantity[i],
+            goods_issue_id: match self.goods_issue_index.get(i) {
+                Some(&idx) if idx >= 0 => self.goods_issue_ids[idx as usize].clone(),
+                _ => String::new(),
+            },
+        })
+    }
+}
+
+/// Problem found by HistoryColumns::validate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryColumnsError {
+    /// Field name of the offending column, e.g. `stock_qty`.
+    pub column: &'static str,
+    /// Offending event; None when the column has the wrong length.
+    pub index: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for HistoryColumnsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.index {
+            Some(i) => write!(f, "{}[{}]: {}", self.column, i, self.message),
+            None => write!(f, "{}: {}", self.column, self.message),
+        }
+    }
+}
+
+impl std::error::Error for HistoryColumnsError {}
+
+#[derive(Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct InventoryData {
     pub opening_stock: i32,
     pub opening_cbm: f32,
     pub opening_master_qty: i32,
+    pub opening_pallet_qty: i32,
     pub asin: String,
     pub asin_outbound: Vec<String>,
     pub unit_price: f32,
-    pub received_date: i32,
+    pub received_date: i64,
     pub inner_qty_on_mas: i32,
-    pub date: i32,
+    pub date: i64,
     pub line_in_cd: String,
     pub po_no: String,
     pub master_dimension: ProshipDimension,
@@ -179,9 +418,11 @@ pub struct InventoryData {
     pub inbound_qty: i32,
     pub inbound_cbm: f32,
     pub inbound_master_qty: i32,
+    pub inbound_pallet_qty: i32,
     pub closing_stock: i32,
     pub closing_cbm: f32,
     pub closing_master_qty: i32,
+    pub closing_pallet_qty: i32,
     pub allocated_qty: i32,
     pub allocated_cbm: f32,
     pub allocated_master_qty: i32,
@@ -194,7 +435,50 @@ pub struct InventoryData {
     pub outbound_qty: i32,
     pub outbound_cbm: f32,
     pub outbound_master_qty: i32,
+    pub outbound_pallet_qty: i32,
+    pub pallet_footprint: f32,
     pub storage_time_days: i32,
+    pub currency: String,
+    pub opening_value: Decimal,
+    pub inbound_value: Decimal,
+    pub outbound_value: Decimal,
+    pub disposal_value: Decimal,
+    pub closing_value: Decimal,
+    pub closing_balances: StatusBalances,
+    /// Exact values behind the f32 CBM and price fields, read through the
+    /// `get_*_decimal` accessors.
+    #[cfg_attr(feature = "serde", serde(default))]
+    exact: ExactFigures,
+}
+
+/// Decimal CBM and price figures of an `InventoryData`, as computed.
+#[derive(Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+struct ExactFigures {
+    opening_cbm: Decimal,
+    unit_price: Decimal,
+    inbound_cbm: Decimal,
+    closing_cbm: Decimal,
+    allocated_cbm: Decimal,
+    disposal_cbm: Decimal,
+    restore_stock_cbm: Decimal,
+    outbound_cbm: Decimal,
+}
+
+impl ExactFigures {
+    /// `exact`, unless the f32 field no longer matches it (it was assigned
+    /// directly), in which case the field's own value.
+    fn get(&self, exact: Decimal, field: f32) -> Decimal {
+        if exact.to_f32() == field {
+            exact
+        } else {
+            Decimal::from_f32(field)
+        }
+    }
 }
 
 impl InventoryData {
@@ -205,11 +489,21 @@ impl InventoryData {
         self.opening_stock = v;
     }
     pub fn set_opening_cbm(&mut self, v: f32) {
-        self.opening_cbm = v;
+        self.set_opening_cbm_decimal(Decimal::from_f32(v));
+    }
+    pub fn set_opening_cbm_decimal(&mut self, v: Decimal) {
+        self.opening_cbm = v.to_f32();
+        self.exact.opening_cbm = v;
+    }
+    pub fn get_opening_cbm_decimal(&self) -> Decimal {
+        self.exact.get(self.exact.opening_cbm, self.opening_cbm)
     }
     pub fn set_opening_master_qty(&mut self, v: i32) {
         self.opening_master_qty = v;
     }
+    pub fn set_opening_pallet_qty(&mut self, v: i32) {
+        self.opening_pallet_qty = v;
+    }
     pub fn set_asin(&mut self, v: String) {
         self.asin = v;
     }
@@ -217,15 +511,22 @@ impl InventoryData {
         self.asin_outbound = v;
     }
     pub fn set_unit_price(&mut self, v: f32) {
-        self.unit_price = v;
+        self.set_unit_price_decimal(Decimal::from_f32(v));
+    }
+    pub fn set_unit_price_decimal(&mut self, v: Decimal) {
+        self.unit_price = v.to_f32();
+        self.exact.unit_price = v;
+    }
+    pub fn get_unit_price_decimal(&self) -> Decimal {
+        self.exact.get(self.exact.unit_price, self.unit_price)
     }
-    pub fn set_received_date(&mut self, v: i32) {
+    pub fn set_received_date(&mut self, v: i64) {
         self.received_date = v;
     }
     pub fn set_inner_qty_on_mas(&mut self, v: i32) {
         self.inner_qty_on_mas = v;
     }
-    pub fn set_date(&mut self, v: i32) {
+    pub fn set_date(&mut self, v: i64) {
         self.date = v;
     }
     pub fn set_line_in_cd(&mut self, v: String) {
@@ -244,20 +545,40 @@ impl InventoryData {
         self.inbound_qty = v;
     }
     pub fn set_inbound_cbm(&mut self, v: f32) {
-        self.inbound_cbm = v;
+        self.set_inbound_cbm_decimal(Decimal::from_f32(v));
+    }
+    pub fn set_inbound_cbm_decimal(&mut self, v: Decimal) {
+        self.inbound_cbm = v.to_f32();
+        self.exact.inbound_cbm = v;
+    }
+    pub fn get_inbound_cbm_decimal(&self) -> Decimal {
+        self.exact.get(self.exact.inbound_cbm, self.inbound_cbm)
     }
     pub fn set_inbound_master_qty(&mut self, v: i32) {
         self.inbound_master_qty = v;
     }
+    pub fn set_inbound_pallet_qty(&mut self, v: i32) {
+        self.inbound_pallet_qty = v;
+    }
     pub fn set_closing_stock(&mut self, v: i32) {
         self.closing_stock = v;
     }
     pub fn set_closing_cbm(&mut self, v: f32) {
-        self.closing_cbm = v;
+        self.set_closing_cbm_decimal(Decimal::from_f32(v));
+    }
+    pub fn set_closing_cbm_decimal(&mut self, v: Decimal) {
+        self.closing_cbm = v.to_f32();
+        self.exact.closing_cbm = v;
+    }
+    pub fn get_closing_cbm_decimal(&self) -> Decimal {
+        self.exact.get(self.exact.closing_cbm, self.closing_cbm)
     }
     pub fn set_closing_master_qty(&mut self, v: i32) {
         self.closing_master_qty = v;
     }
+    pub fn set_closing_pallet_qty(&mut self, v: i32) {
+        self.closing_pallet_qty = v;
+    }
     pub fn get_allocated_qty(&self) -> i32 {
         self.allocated_qty
     }
@@ -265,7 +586,14 @@ impl InventoryData {
         self.allocated_qty = v;
     }
     pub fn set_allocated_cbm(&mut self, v: f32) {
-        self.allocated_cbm = v;
+        self.set_allocated_cbm_decimal(Decimal::from_f32(v));
+    }
+    pub fn set_allocated_cbm_decimal(&mut self, v: Decimal) {
+        self.allocated_cbm = v.to_f32();
+        self.exact.allocated_cbm = v;
+    }
+    pub fn get_allocated_cbm_decimal(&self) -> Decimal {
+        self.exact.get(self.exact.allocated_cbm, self.allocated_cbm)
     }
     pub fn set_allocated_master_qty(&mut self, v: i32) {
         self.allocated_master_qty = v;
@@ -277,7 +605,14 @@ impl InventoryData {
         self.disposal_stock = v;
     }
     pub fn set_disposal_cbm(&mut self, v: f32) {
-        self.disposal_cbm = v;
+        self.set_disposal_cbm_decimal(Decimal::from_f32(v));
+    }
+    pub fn set_disposal_cbm_decimal(&mut self, v: Decimal) {
+        self.disposal_cbm = v.to_f32();
+        self.exact.disposal_cbm = v;
+    }
+    pub fn get_disposal_cbm_decimal(&self) -> Decimal {
+        self.exact.get(self.exact.disposal_cbm, self.disposal_cbm)
     }
     pub fn set_disposal_master_qty(&mut self, v: i32) {
         self.disposal_master_qty = v;
@@ -289,7 +624,15 @@ impl InventoryData {
         self.restore_stock_qty = v;
     }
     pub fn set_restore_stock_cbm(&mut self, v: f32) {
-        self.restore_stock_cbm = v;
+        self.set_restore_stock_cbm_decimal(Decimal::from_f32(v));
+    }
+    pub fn set_restore_stock_cbm_decimal(&mut self, v: Decimal) {
+        self.restore_stock_cbm = v.to_f32();
+        self.exact.restore_stock_cbm = v;
+    }
+    pub fn get_restore_stock_cbm_decimal(&self) -> Decimal {
+        self.exact
+            .get(self.exact.restore_stock_cbm, self.restore_stock_cbm)
     }
     pub fn set_restore_master_qty(&mut self, v: i32) {
         self.restore_master_qty = v;
@@ -300,20 +643,800 @@ impl InventoryData {
     pub fn set_outbound_qty(&mut self, v: i32) {
         self.outbound_qty = v;
     }
-    pub fn set_outbound_cbm(&mut self, v: f32) {
-        self.outbound_cbm = v;
+    pub fn set_outbound_cbm(&mut self, v: f32) {
+        self.set_outbound_cbm_decimal(Decimal::from_f32(v));
+    }
+    pub fn set_outbound_cbm_decimal(&mut self, v: Decimal) {
+        self.outbound_cbm = v.to_f32();
+        self.exact.outbound_cbm = v;
+    }
+    pub fn get_outbound_cbm_decimal(&self) -> Decimal {
+        self.exact.get(self.exact.outbound_cbm, self.outbound_cbm)
+    }
+    pub fn set_outbound_master_qty(&mut self, v: i32) {
+        self.outbound_master_qty = v;
+    }
+    pub fn set_outbound_pallet_qty(&mut self, v: i32) {
+        self.outbound_pallet_qty = v;
+    }
+    pub fn set_pallet_footprint(&mut self, v: f32) {
+        self.pallet_footprint = v;
+    }
+    pub fn set_storage_time_days(&mut self, v: i32) {
+        self.storage_time_days = v;
+    }
+    pub fn get_date(&self) -> i64 {
+        self.date
+    }
+    pub fn get_storage_time_days(&self) -> i32 {
+        self.storage_time_days
+    }
+    pub fn set_currency(&mut self, v: String) {
+        self.currency = v;
+    }
+    pub fn set_opening_value(&mut self, v: Decimal) {
+        self.opening_value = v;
+    }
+    pub fn set_inbound_value(&mut self, v: Decimal) {
+        self.inbound_value = v;
+    }
+    pub fn set_outbound_value(&mut self, v: Decimal) {
+        self.outbound_value = v;
+    }
+    pub fn set_disposal_value(&mut self, v: Decimal) {
+        self.disposal_value = v;
+    }
+    pub fn set_closing_value(&mut self, v: Decimal) {
+        self.closing_value = v;
+    }
+    pub fn get_closing_balances(&self) -> StatusBalances {
+        self.closing_balances
+    }
+    pub fn set_closing_balances(&mut self, v: StatusBalances) {
+        self.closing_balances = v;
+    }
+}
+
+/// Quantity held in each inventory status, following the history transitions.
+#[derive(Clone, PartialEq, Copy, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct StatusBalances {
+    pub available_qty: i32,
+    pub on_hand_qty: i32,
+    pub damaged_qty: i32,
+    pub return_qty: i32,
+    pub liquidation_qty: i32,
+    pub exported_qty: i32,
+}
+
+impl StatusBalances {
+    fn slot(&mut self, status: EnumProshipType_InventoryStatus) -> Option<&mut i32> {
+        match status {
+            EnumProshipType_InventoryStatus::AVALABLE_INVENTORY_STATUS => {
+                Some(&mut self.available_qty)
+            }
+            EnumProshipType_InventoryStatus::ON_HAND_INVENTORY_STATUS => {
+                Some(&mut self.on_hand_qty)
+            }
+            EnumProshipType_InventoryStatus::DAMAGED_INVENTORY_STATUS => {
+                Some(&mut self.damaged_qty)
+            }
+            EnumProshipType_InventoryStatus::RETURN_INVENTORY_STATUS => Some(&mut self.return_qty),
+            EnumProshipType_InventoryStatus::LIQUIDATION_INVENTORY_STATUS => {
+                Some(&mut self.liquidation_qty)
+            }
+            EnumProshipType_InventoryStatus::EXPORTED_INVENTORY_STATUS => {
+                Some(&mut self.exported_qty)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether `status` has a balance: everything but other and pending.
+    pub fn tracks(status: EnumProshipType_InventoryStatus) -> bool {
+        StatusBalances::default().slot(status).is_some()
+    }
+
+    /// Quantity still in the warehouse, i.e. in any status but exported;
+    /// comparable to the `stock_qty` of the history.
+    pub fn in_stock_qty(&self) -> i32 {
+        self.available_qty
+            + self.on_hand_qty
+            + self.damaged_qty
+            + self.return_qty
+            + self.liquidation_qty
+    }
+
+    /// Opening balances of a history that starts after the receipt, before
+    /// `first` is applied. The stock before `first` (its `stock_qty` less its
+    /// own effect, or `received_qty` when that is not positive) is taken as
+    /// available, except what `first` moves out of another status.
+    pub fn seed(&mut self, first: &ProshipInventoryHistory, received_qty: i32) {
+        let in_stock = |status| {
+            Self::tracks(status)
+                && status != EnumProshipType_InventoryStatus::EXPORTED_INVENTORY_STATUS
+        };
+        let quantity = first.get_quantity();
+        let mut opening = first.get_stock_qty();
+        if in_stock(first.get_new_status()) {
+            opening -= quantity;
+        }
+        if in_stock(first.get_old_status()) {
+            opening += quantity;
+        }
+        if opening <= 0 {
+            opening = received_qty.max(0);
+        }
+        let moved = match first.get_old_status() {
+            EnumProshipType_InventoryStatus::AVALABLE_INVENTORY_STATUS => 0,
+            _ => quantity.clamp(0, opening),
+        };
+        if let Some(qty) = self.slot(first.get_old_status()) {
+            *qty += moved;
+        }
+        self.available_qty += opening - moved;
+    }
+
+    /// Moves `quantity` from `old_status` to `new_status`. Other and pending
+    /// statuses are outside the balances, so moves from them only add.
+    pub fn apply(
+        &mut self,
+        old_status: EnumProshipType_InventoryStatus,
+        new_status: EnumProshipType_InventoryStatus,
+        quantity: i32,
+    ) {
+        if let Some(qty) = self.slot(old_status) {
+            *qty -= quantity;
+        }
+        if let Some(qty) = self.slot(new_status) {
+            *qty += quantity;
+        }
+    }
+
+    pub fn get(&self, status: EnumProshipType_InventoryStatus) -> i32 {
+        let mut balances = *self;
+        balances.slot(status).map_or(0, |qty| *qty)
+    }
+}
+
+/// How a history transition moves stock for its goods issue.
+#[derive(Clone, PartialEq, Copy)]
+pub enum GoodsIssueMovement {
+    /// Into ON_HAND, from available or from a disposal status.
+    Allocated,
+    /// ON_HAND back to available.
+    Released,
+    /// ON_HAND to damaged, return or liquidation.
+    Disposed,
+    /// ON_HAND to exported.
+    Shipped,
+}
+
+impl GoodsIssueMovement {
+    pub fn from_transition(
+        old_status: EnumProshipType_InventoryStatus,
+        new_status: EnumProshipType_InventoryStatus,
+    ) -> Option<Self> {
+        use EnumProshipType_InventoryStatus::*;
+        match (old_status, new_status) {
+            (
+                AVALABLE_INVENTORY_STATUS
+                | DAMAGED_INVENTORY_STATUS
+                | RETURN_INVENTORY_STATUS
+                | LIQUIDATION_INVENTORY_STATUS,
+                ON_HAND_INVENTORY_STATUS,
+            ) => Some(Self::Allocated),
+            (ON_HAND_INVENTORY_STATUS, AVALABLE_INVENTORY_STATUS) => Some(Self::Released),
+            (
+                ON_HAND_INVENTORY_STATUS,
+                DAMAGED_INVENTORY_STATUS | RETURN_INVENTORY_STATUS | LIQUIDATION_INVENTORY_STATUS,
+            ) => Some(Self::Disposed),
+            (ON_HAND_INVENTORY_STATUS, EXPORTED_INVENTORY_STATUS) => Some(Self::Shipped),
+            _ => None,
+        }
+    }
+}
+
+/// Allocation and outbound totals of one goods issue over the whole history.
+#[derive(Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct GoodsIssueLedger {
+    pub allocated_qty: i32,
+    pub released_qty: i32,
+    pub disposed_qty: i32,
+    pub shipped_qty: i32,
+    /// First allocation.
+    pub allocated_at: Option<i64>,
+    /// Latest release, disposal and shipment.
+    pub released_at: Option<i64>,
+    pub disposed_at: Option<i64>,
+    pub shipped_at: Option<i64>,
+}
+
+impl GoodsIssueLedger {
+    pub fn record(&mut self, movement: GoodsIssueMovement, quantity: i32, at: i64) {
+        match movement {
+            GoodsIssueMovement::Allocated => {
+                self.allocated_qty += quantity;
+                self.allocated_at.get_or_insert(at);
+            }
+            GoodsIssueMovement::Released => {
+                self.released_qty += quantity;
+                self.released_at = Some(at);
+            }
+            GoodsIssueMovement::Disposed => {
+                self.disposed_qty += quantity;
+                self.disposed_at = Some(at);
+            }
+            GoodsIssueMovement::Shipped => {
+                self.shipped_qty += quantity;
+                self.shipped_at = Some(at);
+            }
+        }
+    }
+
+    /// Quantity still allocated (on hand) for the goods issue.
+    pub fn get_open_qty(&self) -> i32 {
+        self.allocated_qty - self.released_qty - self.disposed_qty - self.shipped_qty
+    }
+}
+
+/// Exchange rate from `currency` into the report currency, valid from `effective_at`.
+#[derive(Clone, PartialEq, Default)]
+pub struct FxRate {
+    pub currency: String,
+    pub rate: f64,
+    pub effective_at: i64,
+}
+
+/// Report currency and FX table used for the valuation columns.
+#[derive(Clone, PartialEq)]
+pub struct ValuationOptions {
+    /// Currency of the value columns; `None` keeps the inventory's own currency.
+    pub currency: Option<String>,
+    /// Sorted by currency (upper case), then `effective_at`; see `new`.
+    fx_rates: Vec<FxRate>,
+    /// Decimal places kept for values.
+    pub value_precision: u32,
+}
+
+impl Default for ValuationOptions {
+    fn default() -> Self {
+        Self {
+            currency: None,
+            fx_rates: Vec::new(),
+            value_precision: 2,
+        }
+    }
+}
+
+impl ValuationOptions {
+    /// Options with the FX table indexed for rate_at; currency codes are
+    /// compared case-insensitively and stored upper case.
+    pub fn new(currency: Option<String>, mut fx_rates: Vec<FxRate>, value_precision: u32) -> Self {
+        for rate in &mut fx_rates {
+            rate.currency.make_ascii_uppercase();
+        }
+        fx_rates.sort_by(|a, b| (&a.currency, a.effective_at).cmp(&(&b.currency, b.effective_at)));
+        Self {
+            currency,
+            fx_rates,
+            value_precision,
+        }
+    }
+
+    pub fn fx_rates(&self) -> &[FxRate] {
+        &self.fx_rates
+    }
+
+    /// Rate for `currency` at `date`: the latest rate effective on or before
+    /// `date`. `None` when the table has no such rate.
+    pub fn rate_at(&self, currency: &str, date: i64) -> Option<f64> {
+        let report_currency = self.currency.as_deref().unwrap_or(currency);
+        if currency.is_empty() || currency.eq_ignore_ascii_case(report_currency) {
+            return Some(1.0);
+        }
+        let currency = currency.to_ascii_uppercase();
+        let start = self.fx_rates.partition_point(|r| r.currency < currency);
+        let rates = &self.fx_rates[start..];
+        let end = rates.partition_point(|r| r.currency == currency && r.effective_at <= date);
+        end.checked_sub(1).map(|i| rates[i].rate)
+    }
+}
+
+/// How computed volumes, prices and charges are rounded.
+#[derive(Clone, PartialEq, Copy, Default)]
+pub enum RoundingMode {
+    /// Ties round away from zero (legacy `round_float` behaviour).
+    #[default]
+    HalfUp,
+    /// Ties round to the nearest even digit (banker's rounding).
+    HalfEven,
+    /// Extra digits are dropped.
+    Truncate,
+}
+
+impl RoundingMode {
+    /// Mode for a name like "halfUp", "half_even" or "truncate"; None for an
+    /// unknown name.
+    pub fn from_name(v: &str) -> Option<Self> {
+        match v
+            .trim()
+            .to_ascii_lowercase()
+            .replace(['_', '-'], "")
+            .as_str()
+        {
+            "halfup" => Some(Self::HalfUp),
+            "halfeven" | "bankers" => Some(Self::HalfEven),
+            "truncate" | "down" => Some(Self::Truncate),
+            _ => None,
+        }
+    }
+}
+
+/// Arithmetic used for volume, price and charge computations.
+#[derive(Clone, PartialEq, Copy, Default)]
+pub enum NumericMode {
+    /// Binary floating point, matching the TypeScript implementation.
+    #[default]
+    Float,
+    /// Exact fixed-point decimal arithmetic, see `Decimal`.
+    Decimal,
+}
+
+impl NumericMode {
+    /// Mode for "float" or "decimal" (alias "fixed"); None for an unknown name.
+    pub fn from_name(v: &str) -> Option<Self> {
+        match v.trim().to_ascii_lowercase().as_str() {
+            "float" => Some(Self::Float),
+            "decimal" | "fixed" => Some(Self::Decimal),
+            _ => None,
+        }
+    }
+}
+
+/// Numeric mode plus precision/rounding applied to CBM and prices.
+#[derive(Clone, PartialEq, Copy)]
+pub struct NumericConfig {
+    pub mode: NumericMode,
+    /// Decimal places kept for CBM values.
+    pub cbm_precision: u32,
+    /// Decimal places kept for prices; `None` passes `unit_price` through unchanged.
+    pub price_precision: Option<u32>,
+    pub rounding: RoundingMode,
+}
+
+impl Default for NumericConfig {
+    fn default() -> Self {
+        Self {
+            mode: NumericMode::Float,
+            cbm_precision: 3,
+            price_precision: None,
+            rounding: RoundingMode::HalfUp,
+        }
+    }
+}
+
+impl NumericConfig {
+    /// Rounds `value` to `precision` places using the configured mode and rounding.
+    pub fn round(&self, value: f64, precision: u32) -> f64 {
+        match self.mode {
+            NumericMode::Float => {
+                let multiplier = 10_f64.powi(precision as i32);
+                let scaled = value * multiplier;
+                let rounded = match self.rounding {
+                    RoundingMode::HalfUp => scaled.round(),
+                    RoundingMode::HalfEven => scaled.round_ties_even(),
+                    RoundingMode::Truncate => scaled.trunc(),
+                };
+                rounded / multiplier
+            }
+            NumericMode::Decimal => Decimal::from_f64(value)
+                .round(precision, self.rounding)
+                .to_f64(),
+        }
+    }
+
+    /// `round` of a decimal; float mode rounds its f64 value.
+    pub fn round_decimal(&self, value: Decimal, precision: u32) -> Decimal {
+        match self.mode {
+            NumericMode::Float => Decimal::from_f64(self.round(value.to_f64(), precision)),
+            NumericMode::Decimal => value.round(precision, self.rounding),
+        }
+    }
+
+    /// `total / quantity` (e.g. a unit cost), rounded to `precision` places;
+    /// zero for a zero quantity.
+    pub fn per_unit(&self, total: Decimal, quantity: i32, precision: u32) -> Decimal {
+        if quantity == 0 {
+            return Decimal::default();
+        }
+        match self.mode {
+            NumericMode::Float => {
+                Decimal::from_f64(self.round(total.to_f64() / quantity as f64, precision))
+            }
+            NumericMode::Decimal => total
+                .checked_div(Decimal::from_i64(quantity as i64), precision, self.rounding)
+                .unwrap_or_else(|| Decimal::saturated(total.is_negative() == (quantity < 0))),
+        }
+    }
+
+    /// `quantity * price * rate`, rounded to `precision` places.
+    pub fn value(&self, quantity: i32, price: Decimal, rate: f64, precision: u32) -> Decimal {
+        match self.mode {
+            NumericMode::Float => {
+                Decimal::from_f64(self.round(quantity as f64 * price.to_f64() * rate, precision))
+            }
+            NumericMode::Decimal => {
+                (Decimal::from_i64(quantity as i64) * price * Decimal::from_f64(rate))
+                    .round(precision, self.rounding)
+            }
+        }
+    }
+
+    /// `price` rounded to `price_precision` places. The f32 is read through its
+    /// shortest decimal form, so 1.1_f32 prices as 1.1.
+    pub fn round_price(&self, price: f32) -> Decimal {
+        match self.price_precision {
+            Some(precision) => match self.mode {
+                NumericMode::Float => Decimal::from_f32(self.round(price as f64, precision) as f32),
+                NumericMode::Decimal => Decimal::from_f32(price).round(precision, self.rounding),
+            },
+            None => Decimal::from_f32(price),
+        }
+    }
+}
+
+/// Fixed-point decimal number: `units / 10^scale`.
+///
+/// Floats are converted through their shortest decimal representation, so
+/// `0.1_f32` becomes exactly `0.1` rather than `0.100000001490116...`. At most
+/// `MAX_SCALE` decimal places are kept; digits beyond that, or beyond what
+/// fits in 128 bits, are dropped. Arithmetic that overflows saturates at
+/// `Decimal::MAX` / `Decimal::MIN`; the `checked_` methods report it instead.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Decimal {
+    units: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Most decimal places kept.
+    pub const MAX_SCALE: u32 = 28;
+    pub const MAX: Self = Self {
+        units: i128::MAX,
+        scale: 0,
+    };
+    pub const MIN: Self = Self {
+        units: -i128::MAX,
+        scale: 0,
+    };
+
+    /// `units / 10^scale`; a scale above MAX_SCALE is rounded down to it.
+    pub fn new(units: i128, scale: u32) -> Self {
+        let value = Self { units, scale };
+        if scale > Self::MAX_SCALE {
+            value.round(Self::MAX_SCALE, RoundingMode::HalfUp)
+        } else {
+            value
+        }
+    }
+
+    pub fn from_i64(v: i64) -> Self {
+        Self::new(v as i128, 0)
+    }
+
+    pub fn from_f32(v: f32) -> Self {
+        Self::from_f64_text(v.is_infinite(), v.is_sign_negative(), &format!("{}", v))
+    }
+
+    pub fn from_f64(v: f64) -> Self {
+        Self::from_f64_text(v.is_infinite(), v.is_sign_negative(), &format!("{}", v))
+    }
+
+    fn from_f64_text(infinite: bool, negative: bool, text: &str) -> Self {
+        match (infinite, negative) {
+            (true, true) => Self::MIN,
+            (true, false) => Self::MAX,
+            _ => Self::parse(text),
+        }
+    }
+
+    /// Parses a plain decimal string such as `-12.345`. Anything else is zero;
+    /// an integer part too large for 128 bits saturates.
+    pub fn parse(s: &str) -> Self {
+        Self::try_parse(s).unwrap_or_default()
+    }
+
+    /// Parses a plain decimal string such as `-12.345`; `None` when it is not one.
+    pub fn try_parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+        if (int_part.is_empty() && frac_part.is_empty())
+            || !int_part
+                .bytes()
+                .chain(frac_part.bytes())
+                .all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+        let push_digit =
+            |units: i128, c: u8| units.checked_mul(10)?.checked_add((c - b'0') as i128);
+
+        let mut units: i128 = 0;
+        for c in int_part.bytes() {
+            match push_digit(units, c) {
+                Some(next) => units = next,
+                None => return Some(if negative { Self::MIN } else { Self::MAX }),
+            }
+        }
+        let mut scale = 0;
+        for c in frac_part.bytes().take(Self::MAX_SCALE as usize) {
+            match push_digit(units, c) {
+                Some(next) => units = next,
+                None => break,
+            }
+            scale += 1;
+        }
+        Some(Self {
+            units: if negative { -units } else { units },
+            scale,
+        })
+    }
+
+    /// Units at `scale` (not below the current scale); `None` on overflow.
+    fn rescale(&self, scale: u32) -> Option<i128> {
+        self.units
+            .checked_mul(10_i128.checked_pow(scale.checked_sub(self.scale)?)?)
+    }
+
+    /// `self * 10^scale` rounded to a whole number, as stored in a fixed-scale
+    /// column; `None` when it does not fit in 128 bits.
+    pub fn units_at(self, scale: u32, rounding: RoundingMode) -> Option<i128> {
+        self.round(scale, rounding).rescale(scale)
+    }
+
+    /// Drops the last decimal place, rounding half up.
+    fn drop_place(self) -> Self {
+        self.round(self.scale.saturating_sub(1), RoundingMode::HalfUp)
+    }
+
+    /// Rounds to `precision` decimal places (at most MAX_SCALE).
+    pub fn round(self, precision: u32, rounding: RoundingMode) -> Self {
+        let precision = precision.min(Self::MAX_SCALE);
+        if self.scale <= precision {
+            // Places a value this large cannot hold are zeros anyway.
+            return match self.rescale(precision) {
+                Some(units) => Self {
+                    units,
+                    scale: precision,
+                },
+                None => self,
+            };
+        }
+        let Some(divisor) = 10_i128.checked_pow(self.scale - precision) else {
+            return Self {
+                units: 0,
+                scale: precision,
+            };
+        };
+        let quotient = self.units / divisor;
+        let remainder = (self.units % divisor).unsigned_abs();
+        let half = divisor.unsigned_abs() / 2;
+        let sign = self.units.signum();
+        let round_away = match rounding {
+            RoundingMode::Truncate => false,
+            RoundingMode::HalfUp => remainder >= half,
+            RoundingMode::HalfEven => remainder > half || (remainder == half && quotient % 2 != 0),
+        };
+        Self {
+            units: if round_away {
+                quotient + sign
+            } else {
+                quotient
+            },
+            scale: precision,
+        }
+    }
+
+    /// Same value without trailing zero decimal places.
+    pub fn normalize(self) -> Self {
+        let mut value = self;
+        while value.scale > 0 && value.units % 10 == 0 {
+            value.units /= 10;
+            value.scale -= 1;
+        }
+        value
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        let (mut a, mut b) = (self, other);
+        loop {
+            let scale = a.scale.max(b.scale);
+            if let Some(units) = a
+                .rescale(scale)
+                .zip(b.rescale(scale))
+                .and_then(|(x, y)| x.checked_add(y))
+                .filter(|&units| units != i128::MIN)
+            {
+                return Some(Self { units, scale });
+            }
+            // Give up decimal places before giving up the sum.
+            match (a.scale, b.scale) {
+                (0, 0) => return None,
+                (x, y) if x >= y => a = a.drop_place(),
+                _ => b = b.drop_place(),
+            }
+        }
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_add(Self {
+            units: other.units.checked_neg()?,
+            scale: other.scale,
+        })
+    }
+
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        let (mut a, mut b) = (self, other);
+        loop {
+            if let Some(units) = a
+                .units
+                .checked_mul(b.units)
+                .filter(|&units| units != i128::MIN)
+            {
+                return Some(Self::new(units, a.scale + b.scale));
+            }
+            match (a.scale, b.scale) {
+                (0, 0) => return None,
+                (x, y) if x >= y => a = a.drop_place(),
+                _ => b = b.drop_place(),
+            }
+        }
+    }
+
+    /// `self / other` rounded to `precision` places (at most MAX_SCALE); `None`
+    /// when `other` is zero or the quotient overflows.
+    pub fn checked_div(self, other: Self, precision: u32, rounding: RoundingMode) -> Option<Self> {
+        if other.units == 0 {
+            return None;
+        }
+        let precision = precision.min(Self::MAX_SCALE);
+        // Numerator scale that leaves `precision` places in the quotient.
+        let scale = precision + other.scale;
+        let numerator = if self.scale > scale {
+            self.round(scale, rounding).units
+        } else {
+            self.rescale(scale)?
+        };
+        let quotient = numerator / other.units;
+        let remainder = (numerator % other.units).unsigned_abs() * 2;
+        let divisor = other.units.unsigned_abs();
+        let round_away = match rounding {
+            RoundingMode::Truncate => false,
+            RoundingMode::HalfUp => remainder >= divisor,
+            RoundingMode::HalfEven => {
+                remainder > divisor || (remainder == divisor && quotient % 2 != 0)
+            }
+        };
+        let sign = numerator.signum() * other.units.signum();
+        Some(Self {
+            units: if round_away && remainder != 0 {
+                quotient + sign
+            } else {
+                quotient
+            },
+            scale: precision,
+        })
+    }
+
+    /// Decimal::MAX or Decimal::MIN, for a result of the sign of `positive`.
+    fn saturated(positive: bool) -> Self {
+        if positive {
+            Self::MAX
+        } else {
+            Self::MIN
+        }
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.units > 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.units < 0
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(0.0)
+    }
+
+    pub fn to_f32(&self) -> f32 {
+        self.to_string().parse().unwrap_or(0.0)
+    }
+}
+
+impl std::ops::Add for Decimal {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        self.checked_add(other)
+            .unwrap_or_else(|| Self::saturated(self.is_positive()))
+    }
+}
+
+impl std::ops::Sub for Decimal {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        self.checked_sub(other)
+            .unwrap_or_else(|| Self::saturated(self.is_positive()))
+    }
+}
+
+impl std::ops::Mul for Decimal {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        self.checked_mul(other)
+            .unwrap_or_else(|| Self::saturated(self.is_negative() == other.is_negative()))
+    }
+}
+
+impl std::iter::Sum for Decimal {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |total, v| total + v)
     }
-    pub fn set_outbound_master_qty(&mut self, v: i32) {
-        self.outbound_master_qty = v;
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        let scale = self.scale.max(other.scale);
+        // A value that overflows at the common scale equals no representable one.
+        match (self.rescale(scale), other.rescale(scale)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
     }
-    pub fn set_storage_time_days(&mut self, v: i32) {
-        self.storage_time_days = v;
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.units < 0 { "-" } else { "" };
+        let digits = self.units.unsigned_abs().to_string();
+        if self.scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int_part, frac_part)
     }
-    pub fn get_date(&self) -> i32 {
-        self.date
+}
+
+/// Serialized as its decimal string, so no digit is lost.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Decimal {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
-    pub fn get_storage_time_days(&self) -> i32 {
-        self.storage_time_days
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Decimal {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::try_parse(&s)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid decimal: {}", s)))
     }
 }
 
@@ -328,57 +1451,115 @@ fn get_disposal_key(date: String, status: EnumProshipType_InventoryStatus) -> St
 fn get_on_hand_key(date: String, goods_issue_id: String) -> String {
     format!("{}-{}", date, goods_issue_id)
 }
-/// Converts epoch time to human readable string in GMT+7 with format yyyymmdd
-fn epoch_to_human_gmt7(ts: u64) -> String {
-    let vietnam_offset = FixedOffset::east_opt(7 * 3600).unwrap();
-    let naive_datetime = NaiveDateTime::from_timestamp_opt(ts as i64, 0).unwrap_or_default();
-    let datetime_again: DateTime<FixedOffset> =
-        DateTime::from_naive_utc_and_offset(naive_datetime, vietnam_offset);
+/// Default report timezone (GMT+7), as seconds east of UTC.
+pub const GMT7_OFFSET_SECONDS: i32 = 7 * 3600;
+
+/// Whether `utc_offset_seconds` is a usable report timezone, strictly within
+/// a day of UTC. Callers taking an offset from users should check it first.
+pub fn is_valid_utc_offset(utc_offset_seconds: i32) -> bool {
+    FixedOffset::east_opt(utc_offset_seconds).is_some()
+}
+
+/// Panics when the offset is out of range, see is_valid_utc_offset.
+pub(crate) fn fixed_offset(utc_offset_seconds: i32) -> FixedOffset {
+    FixedOffset::east_opt(utc_offset_seconds)
+        .unwrap_or_else(|| panic!("UTC offset out of range: {}s", utc_offset_seconds))
+}
+
+/// Earliest timestamp accepted as input: 0001-01-01T00:00:00Z.
+pub const MIN_TIMESTAMP: i64 = -62_135_596_800;
+/// Latest timestamp accepted as input: 9999-12-31T23:59:59Z.
+pub const MAX_TIMESTAMP: i64 = 253_402_300_799;
+
+/// Whether `ts` lies in MIN_TIMESTAMP..=MAX_TIMESTAMP, where every report
+/// timezone has a calendar date for it.
+pub fn is_valid_timestamp(ts: i64) -> bool {
+    (MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&ts)
+}
+
+/// `ts` at the given UTC offset; timestamps out of range are clamped to it.
+fn local_datetime(ts: i64, utc_offset_seconds: i32) -> DateTime<FixedOffset> {
+    DateTime::from_timestamp(ts.clamp(MIN_TIMESTAMP, MAX_TIMESTAMP), 0)
+        .unwrap_or_default()
+        .with_timezone(&fixed_offset(utc_offset_seconds))
+}
+
+/// Converts epoch time to human readable string in the given UTC offset with format yyyymmdd
+pub(crate) fn epoch_to_human(ts: i64, utc_offset_seconds: i32) -> String {
+    let datetime = local_datetime(ts, utc_offset_seconds);
     format!(
         "{:04}{:02}{:02}",
-        datetime_again.year(),
-        datetime_again.month(),
-        datetime_again.day()
+        datetime.year(),
+        datetime.month(),
+        datetime.day()
     )
 }
 
-/// Checks if two Unix timestamps fall on the same day in GMT+7.
-fn is_same_day_gmt7(timestamp1: u64, timestamp2: u64) -> bool {
-    let vietnam_offset = FixedOffset::east_opt(7 * 3600).unwrap();
-    let date1 = NaiveDateTime::from_timestamp_opt(timestamp1 as i64, 0)
-        .unwrap_or_default()
-        .date()
-        .and_hms_opt(0, 0, 0)
-        .unwrap()
-        .and_local_timezone(vietnam_offset)
-        .unwrap();
-    let date2 = NaiveDateTime::from_timestamp_opt(timestamp2 as i64, 0)
-        .unwrap_or_default()
-        .date()
-        .and_hms_opt(0, 0, 0)
-        .unwrap()
-        .and_local_timezone(vietnam_offset)
-        .unwrap();
-    date1 == date2
+/// Parses epoch seconds from a numeric string or an ISO-8601 date/time.
+/// Date-only and offset-less values are taken as GMT+7, like the report dates.
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    parse_timestamp_with_offset(value, GMT7_OFFSET_SECONDS)
 }
 
-/// Calculates the number of days between two Unix timestamps.
-/// Returns 0 if to is before from.
-fn days_between(from_unix_timestamp: u64, to_unix_timestamp: Option<u64>) -> i32 {
-    let vietnam_offset = FixedOffset::east_opt(7 * 3600).unwrap();
+/// parse_timestamp with date-only and offset-less values taken at `utc_offset_seconds`.
+/// Timestamps outside MIN_TIMESTAMP..=MAX_TIMESTAMP are rejected.
+pub fn parse_timestamp_with_offset(value: &str, utc_offset_seconds: i32) -> Option<i64> {
+    parse_any_timestamp(value, utc_offset_seconds).filter(|&ts| is_valid_timestamp(ts))
+}
+
+fn parse_any_timestamp(value: &str, utc_offset_seconds: i32) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(ts) = value.parse::<i64>() {
+        return Some(ts);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.timestamp());
+    }
+    let offset = fixed_offset(utc_offset_seconds);
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })?;
+    naive
+        .and_local_timezone(offset)
+        .single()
+        .map(|dt| dt.timestamp())
+}
+
+/// Checks if two Unix timestamps fall on the same day at the given UTC offset.
+fn is_same_day(timestamp1: i64, timestamp2: i64, utc_offset_seconds: i32) -> bool {
+    local_datetime(timestamp1, utc_offset_seconds).date_naive()
+        == local_datetime(timestamp2, utc_offset_seconds).date_naive()
+}
 
-    let from_dt = vietnam_offset
-        .timestamp_opt(from_unix_timestamp as i64, 0)
-        .unwrap();
-    let from_midnight = from_dt.date_naive().and_hms_opt(0, 0, 0).unwrap();
+/// Unix timestamp of local midnight of the day containing `ts`.
+pub(crate) fn start_of_day(ts: i64, utc_offset_seconds: i32) -> i64 {
+    let datetime = local_datetime(ts, utc_offset_seconds);
+    datetime
+        .date_naive()
+        .and_time(NaiveTime::MIN)
+        .and_utc()
+        .timestamp()
+        - datetime.offset().local_minus_utc() as i64
+}
+
+/// Calculates the number of days between two Unix timestamps in the given UTC offset.
+/// Returns 0 if to is before from.
+pub(crate) fn days_between(
+    from_unix_timestamp: i64,
+    to_unix_timestamp: Option<i64>,
+    utc_offset_seconds: i32,
+) -> i32 {
+    let from_date = local_datetime(from_unix_timestamp, utc_offset_seconds).date_naive();
 
     let to_timestamp = to_unix_timestamp.unwrap_or_else(now_to_epoch);
-    let to_dt = vietnam_offset
-        .timestamp_opt(to_timestamp as i64, 0)
-        .unwrap();
-    let to_midnight = to_dt.date_naive().and_hms_opt(0, 0, 0).unwrap();
+    let to_date = local_datetime(to_timestamp, utc_offset_seconds).date_naive();
 
-    let diff_in_days = (to_midnight - from_midnight).num_days();
+    let diff_in_days = (to_date - from_date).num_days();
     if diff_in_days > 0 {
         diff_in_days as i32
     } else {
@@ -386,17 +1567,17 @@ fn days_between(from_unix_timestamp: u64, to_unix_timestamp: Option<u64>) -> i32
     }
 }
 
-#[cfg(not(feature = "wasm"))]
-fn now_to_epoch() -> u64 {
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+pub(crate) fn now_to_epoch() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
-        .as_secs()
+        .as_secs() as i64
 }
 
-#[cfg(feature = "wasm")]
-fn now_to_epoch() -> u64 {
-    (js_sys::Date::now() / 1000.0) as u64
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub(crate) fn now_to_epoch() -> i64 {
+    (js_sys::Date::now() / 1000.0) as i64
 }
 
 fn round_float<T>(value: T, precision: Option<i32>) -> T
@@ -426,28 +1607,145 @@ impl FloatRound for f64 {
 }
 
 pub fn calculate_cbm(quantity: i32, inner_qty_on_mas: i32, volume: f32, master_volume: f32) -> f32 {
+    calculate_cbm_with(
+        quantity,
+        inner_qty_on_mas,
+        volume,
+        master_volume,
+        &NumericConfig::default(),
+    )
+}
+
+/// `calculate_cbm` with the numeric mode, precision and rounding from `numeric`.
+pub fn calculate_cbm_with(
+    quantity: i32,
+    inner_qty_on_mas: i32,
+    volume: f32,
+    master_volume: f32,
+    numeric: &NumericConfig,
+) -> f32 {
+    calculate_cbm_decimal(quantity, inner_qty_on_mas, volume, master_volume, numeric).to_f32()
+}
+
+/// `calculate_cbm_with` as a decimal, so decimal mode keeps the exact rounded
+/// value. In float mode it is the f32 result, read through its shortest
+/// decimal form.
+pub fn calculate_cbm_decimal(
+    quantity: i32,
+    inner_qty_on_mas: i32,
+    volume: f32,
+    master_volume: f32,
+    numeric: &NumericConfig,
+) -> Decimal {
     let master_boxes = quantity / inner_qty_on_mas; // Total master boxes
     let inner_boxes = quantity % inner_qty_on_mas; // Remaining inner boxes
-    let cbm: f32;
 
-    // Calculate total cbm
-    if master_volume > 0.0 {
-        cbm = (master_boxes as f32 * master_volume) + (inner_boxes as f32 * volume);
-    } else {
-        cbm = quantity as f32 * volume;
+    match numeric.mode {
+        NumericMode::Float => {
+            // Calculate total cbm
+            let cbm = if master_volume > 0.0 {
+                (master_boxes as f32 * master_volume) + (inner_boxes as f32 * volume)
+            } else {
+                quantity as f32 * volume
+            };
+
+            Decimal::from_f32(if numeric.rounding == RoundingMode::HalfUp {
+                round_float(cbm, Some(numeric.cbm_precision as i32))
+            } else {
+                numeric.round(cbm as f64, numeric.cbm_precision) as f32
+            })
+        }
+        NumericMode::Decimal => {
+            let volume = Decimal::from_f32(volume);
+            let master_volume = Decimal::from_f32(master_volume);
+            let cbm = if master_volume.is_positive() {
+                (Decimal::from_i64(master_boxes as i64) * master_volume)
+                    + (Decimal::from_i64(inner_boxes as i64) * volume)
+            } else {
+                Decimal::from_i64(quantity as i64) * volume
+            };
+
+            cbm.round(numeric.cbm_precision, numeric.rounding)
+        }
+    }
+}
+
+fn resolve_volume(
+    field: &str,
+    explicit: f32,
+    dimension: Option<&ProshipDimension>,
+    unit: DimensionUnit,
+    tolerance: f32,
+    warnings: &mut Vec<String>,
+) -> f32 {
+    let derived = dimension
+        .map(|d| round_float(d.volume_cbm(unit), Some(6)) as f32)
+        .unwrap_or(0.0);
+    if derived <= 0.0 {
+        return explicit;
+    }
+    if explicit <= 0.0 {
+        return derived;
     }
 
-    round_float(cbm, Some(3))
+    let diff = (explicit - derived).abs() / derived;
+    if diff > tolerance {
+        warnings.push(format!(
+            "{} {} differs from dimensions ({}) by {:.1}%, using dimensions",
+            field,
+            explicit,
+            derived,
+            diff * 100.0
+        ));
+        return derived;
+    }
+    explicit
 }
 
 pub fn calculate_master_qty(quantity: i32, inner_qty_on_mas: i32) -> i32 {
     (quantity + inner_qty_on_mas - 1) / inner_qty_on_mas
 }
 
+/// Pallet positions needed for `quantity` inner units. Returns 0 when the
+/// inventory has no cartons-per-pallet configured.
+pub fn calculate_pallet_qty(quantity: i32, inner_qty_on_mas: i32, cartons_per_pallet: i32) -> i32 {
+    if cartons_per_pallet <= 0 {
+        return 0;
+    }
+    let master_qty = calculate_master_qty(quantity, inner_qty_on_mas);
+    (master_qty + cartons_per_pallet - 1) / cartons_per_pallet
+}
+
 // =============================================================================
 // Main logic
 // =============================================================================
 
+/// Tuning knobs for process_inventory_history_data.
+pub struct ProcessOptions {
+    /// Relative difference allowed between explicit volumes and dimensions.
+    pub volume_tolerance: f32,
+    /// Arithmetic and rounding for CBM and prices.
+    pub numeric: NumericConfig,
+    pub valuation: ValuationOptions,
+    /// Timezone of the daily rows, as seconds east of UTC; must pass
+    /// is_valid_utc_offset.
+    pub utc_offset_seconds: i32,
+    /// Time the report is produced at, instead of now; later events are ignored.
+    pub as_of: Option<i64>,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        Self {
+            volume_tolerance: 0.05,
+            numeric: NumericConfig::default(),
+            valuation: ValuationOptions::default(),
+            utc_offset_seconds: GMT7_OFFSET_SECONDS,
+            as_of: None,
+        }
+    }
+}
+
 /// Input for process_inventory_history_data - all data fetched upfront.
 pub struct ProcessInventoryHistoryInput<'a> {
     pub inventory: &'a ProshipInventory,
@@ -456,71 +1754,387 @@ pub struct ProcessInventoryHistoryInput<'a> {
     pub goods_receipt: &'a ProshipGoodsReceipt,
     pub inventory_history_list: Vec<ProshipInventoryHistory>,
     pub inventory_ids: HashMap<String, i32>,
-    pub from_date: Option<i32>,
-    pub to_date: Option<i32>,
+    pub from_date: Option<i64>,
+    pub to_date: Option<i64>,
+    pub options: ProcessOptions,
 }
 
 /// Output of process_inventory_history_data - processed data only.
 pub struct ProcessInventoryHistoryOutput {
     pub merged_inventory_history: HashMap<String, (InventoryData, HashSet<String>)>,
     pub total_duration: i32,
+    pub warnings: Vec<String>,
+    pub goods_issue_ledger: HashMap<String, GoodsIssueLedger>,
+    /// Status balances after the last history event.
+    pub closing_balances: StatusBalances,
+}
+
+/// Output of one inventory of a batch, as taken by the report writers.
+pub struct BatchResult<'a> {
+    pub inventory_id: &'a str,
+    pub output: &'a ProcessInventoryHistoryOutput,
 }
 
 /// Pure function: process inventory history data. No async, no I/O.
 pub fn process_inventory_history_data(
     input: ProcessInventoryHistoryInput<'_>,
 ) -> ProcessInventoryHistoryOutput {
-    let ProcessInventoryHistoryInput {
-        inventory,
-        inventory_id,
-        asin_outbound_list,
-        goods_receipt,
-        inventory_history_list,
-        inventory_ids,
-        from_date,
-        to_date,
-    } = input;
-
-    let volume = inventory.get_volume();
-    let master_volume = inventory.get_master_volume();
-
-    let mut merged_inventory_history: HashMap<String, (InventoryData, HashSet<String>)> =
-        HashMap::new();
-    let mut last_stock_qty = 0;
-    let mut on_hand_map: HashMap<String, i32> = HashMap::new();
-    let mut disposal_map: HashMap<String, i32> = HashMap::new();
-    let mut total_disposal_qty: HashMap<i32, i32> = HashMap::new();
-    total_disposal_qty.insert(3, 0);
-    total_disposal_qty.insert(4, 0);
-    total_disposal_qty.insert(5, 0);
-    let mut total_allocated_qty: HashMap<String, i32> = HashMap::new();
-
-    for history in inventory_history_list {
+    InventoryHistoryProcessor::new(input).finish()
+}
+
+/// Like process_inventory_history_data, but consumes the history events from an
+/// iterator (e.g. a streaming JSON reader) after any in the input's list.
+pub fn process_inventory_history_iter<I>(
+    input: ProcessInventoryHistoryInput<'_>,
+    events: I,
+) -> ProcessInventoryHistoryOutput
+where
+    I: IntoIterator<Item = ProshipInventoryHistory>,
+{
+    let mut processor = InventoryHistoryProcessor::new(input);
+    processor.extend(events);
+    processor.finish()
+}
+
+/// Like process_inventory_history_data, but continues from `checkpoint` (the
+/// state after earlier events) and returns the checkpoint after `input`'s events.
+///
+/// Events before the checkpoint's `last_created_at`, and the first
+/// `applied_at_last_created_at` events at it, were applied by an earlier call
+/// and are skipped. The checkpoint only keeps the day of the last
+/// event, so the output holds that day and the later ones: merged by key over
+/// the earlier outputs, it equals the output of a full run.
+pub fn process_inventory_history_incremental(
+    mut input: ProcessInventoryHistoryInput<'_>,
+    checkpoint: Option<ProcessorCheckpoint>,
+) -> (ProcessInventoryHistoryOutput, ProcessorCheckpoint) {
+    let checkpoint = checkpoint.unwrap_or_default();
+    if let Some(last_created_at) = checkpoint.last_created_at {
+        let mut resent = checkpoint.applied_at_last_created_at;
+        input
+            .inventory_history_list
+            .retain(|h| match h.get_created_at().cmp(&last_created_at) {
+                Ordering::Less => false,
+                Ordering::Equal if resent > 0 => {
+                    resent -= 1;
+                    false
+                }
+                _ => true,
+            });
+    }
+    let processor = InventoryHistoryProcessor::resume(input, checkpoint);
+    let mut checkpoint = processor.checkpoint();
+    checkpoint.retain_last_day(processor.from_date, processor.to_date);
+    (processor.finish(), checkpoint)
+}
+
+/// A history event older than the last one a processor applied.
+#[derive(Clone, PartialEq, Debug)]
+pub struct OutOfOrderEvent {
+    pub created_at: i64,
+    pub last_created_at: i64,
+}
+
+impl std::fmt::Display for OutOfOrderEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "createdAt {} is before the previous event's {}, events must be in createdAt order",
+            self.created_at, self.last_created_at
+        )
+    }
+}
+
+impl std::error::Error for OutOfOrderEvent {}
+
+/// Running state between history events: everything process_inventory_history_data
+/// carries from one event to the next, before the end-of-run adjustments.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct ProcessorCheckpoint {
+    pub merged_inventory_history: HashMap<String, (InventoryData, HashSet<String>)>,
+    pub last_stock_qty: i32,
+    /// created_at of the last event applied, to select the events to resume with.
+    pub last_created_at: Option<i64>,
+    /// How many of the events applied have `last_created_at`, so a resumed run
+    /// skips only those of the events sharing that second.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub applied_at_last_created_at: usize,
+    pub on_hand_map: HashMap<String, i32>,
+    pub disposal_map: HashMap<String, i32>,
+    pub total_disposal_qty: HashMap<i32, i32>,
+    pub total_allocated_qty: HashMap<String, i32>,
+    pub goods_issue_ledger: HashMap<String, GoodsIssueLedger>,
+    pub closing_balances: StatusBalances,
+    /// Whether `closing_balances` has its opening stock, see StatusBalances::seed.
+    pub balances_seeded: bool,
+    /// Whether the balances were already reported as differing from stock_qty.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub balances_warned: bool,
+    /// Storage days of the rows dropped from `merged_inventory_history`.
+    pub closed_duration: i32,
+}
+
+/// Whether a row is in the `from_date`..`to_date` window summed into total_duration.
+fn counts_toward_duration(
+    data: &InventoryData,
+    from_date: Option<i64>,
+    to_date: Option<i64>,
+) -> bool {
+    match (from_date, to_date) {
+        (Some(from), Some(to)) => data.get_date() >= from && data.get_date() <= to,
+        _ => true,
+    }
+}
+
+impl ProcessorCheckpoint {
+    /// Drops the state of the days before the last event's: later events only
+    /// change that day's row and add new ones. Their storage days are kept in
+    /// `closed_duration`.
+    fn retain_last_day(&mut self, from_date: Option<i64>, to_date: Option<i64>) {
+        let Some(last_day) = self
+            .merged_inventory_history
+            .iter()
+            .max_by_key(|(_, (data, _))| data.get_date())
+            .map(|(day, _)| day.clone())
+        else {
+            return;
+        };
+        let prefix = format!("{}-", last_day);
+        let closed_duration = &mut self.closed_duration;
+        self.merged_inventory_history.retain(|day, (data, _)| {
+            if *day == last_day {
+                return true;
+            }
+            if counts_toward_duration(data, from_date, to_date) {
+                *closed_duration += data.get_storage_time_days();
+            }
+            false
+        });
+        self.on_hand_map.retain(|key, _| key.starts_with(&prefix));
+        self.disposal_map.retain(|key, _| key.starts_with(&prefix));
+    }
+}
+
+impl Default for ProcessorCheckpoint {
+    fn default() -> Self {
+        let mut total_disposal_qty: HashMap<i32, i32> = HashMap::new();
+        total_disposal_qty.insert(3, 0);
+        total_disposal_qty.insert(4, 0);
+        total_disposal_qty.insert(5, 0);
+        Self {
+            merged_inventory_history: HashMap::new(),
+            last_stock_qty: 0,
+            last_created_at: None,
+            applied_at_last_created_at: 0,
+            on_hand_map: HashMap::new(),
+            disposal_map: HashMap::new(),
+            total_disposal_qty,
+            total_allocated_qty: HashMap::new(),
+            goods_issue_ledger: HashMap::new(),
+            closing_balances: StatusBalances::default(),
+            balances_seeded: false,
+            balances_warned: false,
+            closed_duration: 0,
+        }
+    }
+}
+
+/// Push-based form of process_inventory_history_data: history events are fed one
+/// at a time (in created_at order) so the full list never has to be held in memory.
+/// Events in the input's inventory_history_list are applied first.
+pub struct InventoryHistoryProcessor<'a> {
+    inventory: &'a ProshipInventory,
+    inventory_id: &'a str,
+    asin_outbound_list: &'a [String],
+    goods_receipt: &'a ProshipGoodsReceipt,
+    inventory_ids: HashMap<String, i32>,
+    from_date: Option<i64>,
+    to_date: Option<i64>,
+    options: ProcessOptions,
+    volume: f32,
+    master_volume: f32,
+    warnings: Vec<String>,
+    state: ProcessorCheckpoint,
+}
+
+/// What a processor hands to the next one when a run is split over several
+/// (e.g. one per streamed chunk), see InventoryHistoryProcessor::into_parts.
+#[cfg(feature = "wasm")]
+pub(crate) struct ProcessorParts {
+    pub(crate) checkpoint: ProcessorCheckpoint,
+    /// Warnings so far, those of resolve_volumes included.
+    pub(crate) warnings: Vec<String>,
+    pub(crate) inventory_ids: HashMap<String, i32>,
+    pub(crate) options: ProcessOptions,
+}
+
+impl<'a> InventoryHistoryProcessor<'a> {
+    pub fn new(input: ProcessInventoryHistoryInput<'a>) -> Self {
+        Self::resume(input, ProcessorCheckpoint::default())
+    }
+
+    /// Continues from the state after earlier events, see process_inventory_history_incremental.
+    pub fn resume(
+        input: ProcessInventoryHistoryInput<'a>,
+        checkpoint: ProcessorCheckpoint,
+    ) -> Self {
+        Self::resume_with_warnings(input, checkpoint, None)
+    }
+
+    /// `resume` with the `warnings` of the previous processor of the same run
+    /// (see into_parts), so they are kept and the volume warnings not raised
+    /// again; None starts the warnings afresh.
+    pub(crate) fn resume_with_warnings(
+        input: ProcessInventoryHistoryInput<'a>,
+        checkpoint: ProcessorCheckpoint,
+        warnings: Option<Vec<String>>,
+    ) -> Self {
+        let ProcessInventoryHistoryInput {
+            inventory,
+            inventory_id,
+            asin_outbound_list,
+            goods_receipt,
+            inventory_history_list,
+            inventory_ids,
+            from_date,
+            to_date,
+            options,
+        } = input;
+
+        let (volume, master_volume, volume_warnings) =
+            inventory.resolve_volumes(options.volume_tolerance);
+
+        let mut processor = Self {
+            inventory,
+            inventory_id,
+            asin_outbound_list,
+            goods_receipt,
+            inventory_ids,
+            from_date,
+            to_date,
+            options,
+            volume,
+            master_volume,
+            warnings: warnings.unwrap_or(volume_warnings),
+            state: checkpoint,
+        };
+        processor.extend(inventory_history_list);
+        processor
+    }
+
+    /// State after the events pushed so far.
+    pub fn checkpoint(&self) -> ProcessorCheckpoint {
+        self.state.clone()
+    }
+
+    /// Like checkpoint, without cloning the state.
+    pub fn into_checkpoint(self) -> ProcessorCheckpoint {
+        self.state
+    }
+
+    /// The state, warnings and owned input to continue the run with
+    /// resume_with_warnings.
+    #[cfg(feature = "wasm")]
+    pub(crate) fn into_parts(self) -> ProcessorParts {
+        ProcessorParts {
+            checkpoint: self.state,
+            warnings: self.warnings,
+            inventory_ids: self.inventory_ids,
+            options: self.options,
+        }
+    }
+
+    /// Applies one history event. Events must arrive in created_at order: one
+    /// before the last event applied is rejected and changes nothing.
+    pub fn push(
+        &mut self,
+        history: impl Borrow<ProshipInventoryHistory>,
+    ) -> Result<(), OutOfOrderEvent> {
+        let history = history.borrow();
+        let inventory = self.inventory;
+        let inventory_id = self.inventory_id;
+        let asin_outbound_list = self.asin_outbound_list;
+        let goods_receipt = self.goods_receipt;
+        let inventory_ids = &self.inventory_ids;
+        let from_date = self.from_date;
+        let to_date = self.to_date;
+        let options = &self.options;
+        let volume = self.volume;
+        let master_volume = self.master_volume;
+        let ProcessorCheckpoint {
+            merged_inventory_history,
+            last_stock_qty,
+            last_created_at,
+            applied_at_last_created_at,
+            on_hand_map,
+            disposal_map,
+            total_disposal_qty,
+            total_allocated_qty,
+            goods_issue_ledger,
+            closing_balances,
+            balances_seeded,
+            balances_warned,
+            ..
+        } = &mut self.state;
+
+        if let Some(last_created_at) = *last_created_at {
+            if history.get_created_at() < last_created_at {
+                return Err(OutOfOrderEvent {
+                    created_at: history.get_created_at(),
+                    last_created_at,
+                });
+            }
+        }
+        if options
+            .as_of
+            .is_some_and(|as_of| history.get_created_at() > as_of)
+        {
+            return Ok(());
+        }
+        if *last_created_at == Some(history.get_created_at()) {
+            *applied_at_last_created_at += 1;
+        } else {
+            *last_created_at = Some(history.get_created_at());
+            *applied_at_last_created_at = 1;
+        }
         if history.get_new_status()
             == EnumProshipType_InventoryStatus::PENDING_FOR_IMPORT_INVENTORY_STATUS
         {
-            continue;
+            return Ok(());
         }
 
-        let date = epoch_to_human_gmt7(history.get_created_at() as u64);
+        let date = epoch_to_human(history.get_created_at(), options.utc_offset_seconds);
         let entry = merged_inventory_history
             .entry(date.clone())
             .or_insert_with(|| {
                 let mut data = InventoryData::new();
-                data.set_opening_stock(last_stock_qty);
-                data.set_opening_cbm(calculate_cbm(
-                    last_stock_qty,
+                data.set_opening_stock(*last_stock_qty);
+                data.set_opening_cbm_decimal(calculate_cbm_decimal(
+                    *last_stock_qty,
                     inventory.get_inner_qty_on_mas(),
                     volume,
                     master_volume,
+                    &options.numeric,
                 ));
                 data.set_opening_master_qty(calculate_master_qty(
-                    last_stock_qty,
+                    *last_stock_qty,
+                    inventory.get_inner_qty_on_mas(),
+                ));
+                data.set_opening_pallet_qty(calculate_pallet_qty(
+                    *last_stock_qty,
                     inventory.get_inner_qty_on_mas(),
+                    inventory.get_cartons_per_pallet(),
                 ));
                 data.set_asin(inventory.get_asin().to_string());
                 data.set_asin_outbound(asin_outbound_list.to_vec());
-                data.set_unit_price(inventory.get_unit_price());
+                data.set_unit_price_decimal(
+                    options.numeric.round_price(inventory.get_unit_price()),
+                );
                 data.set_received_date(goods_receipt.get_imported_at());
                 data.set_inner_qty_on_mas(inventory.get_inner_qty_on_mas());
                 data.set_date(history.get_created_at());
@@ -528,39 +2142,88 @@ pub fn process_inventory_history_data(
                 data.set_po_no(inventory.get_po_no().to_string());
                 data.set_master_dimension(inventory.get_master_dimension().clone());
                 data.set_dimension(inventory.get_dimension().clone());
-                if is_same_day_gmt7(
-                    history.get_created_at() as u64,
-                    goods_receipt.get_imported_at() as u64,
+                data.set_pallet_footprint(inventory.get_pallet_footprint());
+                if is_same_day(
+                    history.get_created_at(),
+                    goods_receipt.get_imported_at(),
+                    options.utc_offset_seconds,
                 ) {
                     let inbound_qty = inventory_ids.get(inventory_id).unwrap_or(&0);
                     data.set_inbound_qty(*inbound_qty);
-                    data.set_inbound_cbm(calculate_cbm(
+                    data.set_inbound_cbm_decimal(calculate_cbm_decimal(
                         *inbound_qty,
                         inventory.get_inner_qty_on_mas(),
                         volume,
                         master_volume,
+                        &options.numeric,
                     ));
                     data.set_inbound_master_qty(calculate_master_qty(
                         *inbound_qty,
                         inventory.get_inner_qty_on_mas(),
                     ));
+                    data.set_inbound_pallet_qty(calculate_pallet_qty(
+                        *inbound_qty,
+                        inventory.get_inner_qty_on_mas(),
+                        inventory.get_cartons_per_pallet(),
+                    ));
                 }
                 (data, HashSet::new())
             });
 
         let (data, goods_issue_ids) = entry;
         data.set_closing_stock(history.get_stock_qty());
-        data.set_closing_cbm(calculate_cbm(
+        data.set_closing_cbm_decimal(calculate_cbm_decimal(
             history.get_stock_qty(),
             inventory.get_inner_qty_on_mas(),
             volume,
             master_volume,
+            &options.numeric,
         ));
         data.set_closing_master_qty(calculate_master_qty(
             history.get_stock_qty(),
             inventory.get_inner_qty_on_mas(),
         ));
-        last_stock_qty = history.get_stock_qty();
+        data.set_closing_pallet_qty(calculate_pallet_qty(
+            history.get_stock_qty(),
+            inventory.get_inner_qty_on_mas(),
+            inventory.get_cartons_per_pallet(),
+        ));
+        *last_stock_qty = history.get_stock_qty();
+        if !*balances_seeded {
+            *balances_seeded = true;
+            if StatusBalances::tracks(history.get_old_status()) {
+                closing_balances.seed(
+                    history,
+                    inventory_ids.get(inventory_id).copied().unwrap_or(0),
+                );
+            }
+        }
+        closing_balances.apply(
+            history.get_old_status(),
+            history.get_new_status(),
+            history.get_quantity(),
+        );
+        data.set_closing_balances(*closing_balances);
+        if !*balances_warned && closing_balances.in_stock_qty() != history.get_stock_qty() {
+            *balances_warned = true;
+            self.warnings.push(format!(
+                "Status balances ({}) differ from stockQty ({}) on {}, the history may be incomplete",
+                closing_balances.in_stock_qty(),
+                history.get_stock_qty(),
+                date
+            ));
+        }
+
+        if let Some(movement) =
+            GoodsIssueMovement::from_transition(history.get_old_status(), history.get_new_status())
+        {
+            if !history.get_goods_issue_id().is_empty() {
+                goods_issue_ledger
+                    .entry(history.get_goods_issue_id().to_string())
+                    .or_default()
+                    .record(movement, history.get_quantity(), history.get_created_at());
+            }
+        }
 
         match history.get_old_status() {
             EnumProshipType_InventoryStatus::AVALABLE_INVENTORY_STATUS => {
@@ -581,11 +2244,12 @@ pub fn process_inventory_history_data(
 
                         let allocated_qty = data.get_allocated_qty() + history.get_quantity();
                         data.set_allocated_qty(allocated_qty);
-                        data.set_allocated_cbm(calculate_cbm(
+                        data.set_allocated_cbm_decimal(calculate_cbm_decimal(
                             allocated_qty,
                             inventory.get_inner_qty_on_mas(),
                             volume,
                             master_volume,
+                            &options.numeric,
                         ));
                         data.set_allocated_master_qty(calculate_master_qty(
                             allocated_qty,
@@ -608,11 +2272,12 @@ pub fn process_inventory_history_data(
 
                         let disposal_qty = data.get_disposal_stock() + history.get_quantity();
                         data.set_disposal_stock(disposal_qty);
-                        data.set_disposal_cbm(calculate_cbm(
+                        data.set_disposal_cbm_decimal(calculate_cbm_decimal(
                             disposal_qty,
                             inventory.get_inner_qty_on_mas(),
                             volume,
                             master_volume,
+                            &options.numeric,
                         ));
                         data.set_disposal_master_qty(calculate_master_qty(
                             disposal_qty,
@@ -639,11 +2304,12 @@ pub fn process_inventory_history_data(
                                     let restore_stock_qty =
                                         data.get_restore_stock_qty() + history.get_quantity();
                                     data.set_restore_stock_qty(restore_stock_qty);
-                                    data.set_restore_stock_cbm(calculate_cbm(
+                                    data.set_restore_stock_cbm_decimal(calculate_cbm_decimal(
                                         restore_stock_qty,
                                         inventory.get_inner_qty_on_mas(),
                                         volume,
                                         master_volume,
+                                        &options.numeric,
                                     ));
                                     data.set_restore_master_qty(calculate_master_qty(
                                         restore_stock_qty,
@@ -656,11 +2322,12 @@ pub fn process_inventory_history_data(
                                     let restore_stock_qty =
                                         data.get_restore_stock_qty() + previous_onhand_qty;
                                     data.set_restore_stock_qty(restore_stock_qty);
-                                    data.set_restore_stock_cbm(calculate_cbm(
+                                    data.set_restore_stock_cbm_decimal(calculate_cbm_decimal(
                                         restore_stock_qty,
                                         inventory.get_inner_qty_on_mas(),
                                         volume,
                                         master_volume,
+                                        &options.numeric,
                                     ));
                                     data.set_restore_master_qty(calculate_master_qty(
                                         restore_stock_qty,
@@ -669,11 +2336,12 @@ pub fn process_inventory_history_data(
                                     let allocated_qty = data.get_allocated_qty()
                                         - (history.get_quantity() - previous_onhand_qty);
                                     data.set_allocated_qty(allocated_qty);
-                                    data.set_allocated_cbm(calculate_cbm(
+                                    data.set_allocated_cbm_decimal(calculate_cbm_decimal(
                                         allocated_qty,
                                         inventory.get_inner_qty_on_mas(),
                                         volume,
                                         master_volume,
+                                        &options.numeric,
                                     ));
                                     data.set_allocated_master_qty(calculate_master_qty(
                                         allocated_qty,
@@ -687,11 +2355,12 @@ pub fn process_inventory_history_data(
                             let restore_stock_qty =
                                 data.get_restore_stock_qty() + history.get_quantity();
                             data.set_restore_stock_qty(restore_stock_qty);
-                            data.set_restore_stock_cbm(calculate_cbm(
+                            data.set_restore_stock_cbm_decimal(calculate_cbm_decimal(
                                 restore_stock_qty,
                                 inventory.get_inner_qty_on_mas(),
                                 volume,
                                 master_volume,
+                                &options.numeric,
                             ));
                             data.set_restore_master_qty(calculate_master_qty(
                                 restore_stock_qty,
@@ -711,18 +2380,18 @@ pub fn process_inventory_history_data(
                             history.get_goods_issue_id().to_string(),
                         )) {
                             if (*total_allocated_qty_data - history.get_quantity()) < *onhand_qty {
-                                let previous_onhand_qty =
-                                    *total_allocated_qty_data - *onhand_qty;
+                                let previous_onhand_qty = *total_allocated_qty_data - *onhand_qty;
                                 let onhand_disposal_qty =
                                     history.get_quantity() - previous_onhand_qty;
 
                                 let allocated_qty = data.get_allocated_qty() - onhand_disposal_qty;
                                 data.set_allocated_qty(allocated_qty);
-                                data.set_allocated_cbm(calculate_cbm(
+                                data.set_allocated_cbm_decimal(calculate_cbm_decimal(
                                     allocated_qty,
                                     inventory.get_inner_qty_on_mas(),
                                     volume,
                                     master_volume,
+                                    &options.numeric,
                                 ));
                                 data.set_allocated_master_qty(calculate_master_qty(
                                     allocated_qty,
@@ -732,11 +2401,12 @@ pub fn process_inventory_history_data(
 
                                 let disposal_qty = data.get_disposal_stock() + onhand_disposal_qty;
                                 data.set_disposal_stock(disposal_qty);
-                                data.set_disposal_cbm(calculate_cbm(
+                                data.set_disposal_cbm_decimal(calculate_cbm_decimal(
                                     disposal_qty,
                                     inventory.get_inner_qty_on_mas(),
                                     volume,
                                     master_volume,
+                                    &options.numeric,
                                 ));
                                 data.set_disposal_master_qty(calculate_master_qty(
                                     disposal_qty,
@@ -765,11 +2435,12 @@ pub fn process_inventory_history_data(
                         )) {
                             let allocated_qty = data.get_allocated_qty() - history.get_quantity();
                             data.set_allocated_qty(allocated_qty);
-                            data.set_allocated_cbm(calculate_cbm(
+                            data.set_allocated_cbm_decimal(calculate_cbm_decimal(
                                 allocated_qty,
                                 inventory.get_inner_qty_on_mas(),
                                 volume,
                                 master_volume,
+                                &options.numeric,
                             ));
                             data.set_allocated_master_qty(calculate_master_qty(
                                 allocated_qty,
@@ -781,41 +2452,47 @@ pub fn process_inventory_history_data(
 
                         let outbound_qty = data.get_outbound_qty() + history.get_quantity();
                         data.set_outbound_qty(outbound_qty);
-                        data.set_outbound_cbm(calculate_cbm(
+                        data.set_outbound_cbm_decimal(calculate_cbm_decimal(
                             outbound_qty,
                             inventory.get_inner_qty_on_mas(),
                             volume,
                             master_volume,
+                            &options.numeric,
                         ));
                         data.set_outbound_master_qty(calculate_master_qty(
                             outbound_qty,
                             inventory.get_inner_qty_on_mas(),
                         ));
+                        data.set_outbound_pallet_qty(calculate_pallet_qty(
+                            outbound_qty,
+                            inventory.get_inner_qty_on_mas(),
+                            inventory.get_cartons_per_pallet(),
+                        ));
                         goods_issue_ids.insert(history.get_goods_issue_id().to_string());
                         data.set_storage_time_days(
                             days_between(
                                 from_date.map_or_else(
-                                    || goods_receipt.get_imported_at() as u64,
+                                    || goods_receipt.get_imported_at(),
                                     |fd| {
                                         if fd < goods_receipt.get_imported_at() {
-                                            goods_receipt.get_imported_at() as u64
+                                            goods_receipt.get_imported_at()
                                         } else {
-                                            fd as u64
+                                            fd
                                         }
                                     },
                                 ),
                                 to_date.map_or_else(
-                                    || Some(history.get_created_at() as u64),
+                                    || Some(history.get_created_at()),
                                     |td| {
                                         if td <= history.get_created_at() {
-                                            Some(td as u64)
+                                            Some(td)
                                         } else {
-                                            Some(history.get_created_at() as u64)
+                                            Some(history.get_created_at())
                                         }
                                     },
                                 ),
-                            ) as i32
-                                + 1,
+                                options.utc_offset_seconds,
+                            ) + 1,
                         );
                     }
                     _ => {}
@@ -839,11 +2516,12 @@ pub fn process_inventory_history_data(
                                         let restore_stock_qty =
                                             data.get_restore_stock_qty() + history.get_quantity();
                                         data.set_restore_stock_qty(restore_stock_qty);
-                                        data.set_restore_stock_cbm(calculate_cbm(
+                                        data.set_restore_stock_cbm_decimal(calculate_cbm_decimal(
                                             restore_stock_qty,
                                             inventory.get_inner_qty_on_mas(),
                                             volume,
                                             master_volume,
+                                            &options.numeric,
                                         ));
                                         data.set_restore_master_qty(calculate_master_qty(
                                             restore_stock_qty,
@@ -858,11 +2536,12 @@ pub fn process_inventory_history_data(
                                         let disposal_qty_data =
                                             data.get_disposal_stock() - disposal_available_qty;
                                         data.set_disposal_stock(disposal_qty_data);
-                                        data.set_disposal_cbm(calculate_cbm(
+                                        data.set_disposal_cbm_decimal(calculate_cbm_decimal(
                                             disposal_qty_data,
                                             inventory.get_inner_qty_on_mas(),
                                             volume,
                                             master_volume,
+                                            &options.numeric,
                                         ));
                                         data.set_disposal_master_qty(calculate_master_qty(
                                             disposal_qty_data,
@@ -876,11 +2555,12 @@ pub fn process_inventory_history_data(
                                 let restore_stock_qty =
                                     data.get_restore_stock_qty() + history.get_quantity();
                                 data.set_restore_stock_qty(restore_stock_qty);
-                                data.set_restore_stock_cbm(calculate_cbm(
+                                data.set_restore_stock_cbm_decimal(calculate_cbm_decimal(
                                     restore_stock_qty,
                                     inventory.get_inner_qty_on_mas(),
                                     volume,
                                     master_volume,
+                                    &options.numeric,
                                 ));
                                 data.set_restore_master_qty(calculate_master_qty(
                                     restore_stock_qty,
@@ -904,11 +2584,12 @@ pub fn process_inventory_history_data(
                                         let onhand_qty =
                                             data.get_allocated_qty() + history.get_quantity();
                                         data.set_allocated_qty(onhand_qty);
-                                        data.set_allocated_cbm(calculate_cbm(
+                                        data.set_allocated_cbm_decimal(calculate_cbm_decimal(
                                             onhand_qty,
                                             inventory.get_inner_qty_on_mas(),
                                             volume,
                                             master_volume,
+                                            &options.numeric,
                                         ));
                                         data.set_allocated_master_qty(calculate_master_qty(
                                             onhand_qty,
@@ -923,11 +2604,12 @@ pub fn process_inventory_history_data(
                                         let disposal_qty_value =
                                             data.get_disposal_stock() - disposal_onhand_qty;
                                         data.set_disposal_stock(disposal_qty_value);
-                                        data.set_disposal_cbm(calculate_cbm(
+                                        data.set_disposal_cbm_decimal(calculate_cbm_decimal(
                                             disposal_qty_value,
                                             inventory.get_inner_qty_on_mas(),
                                             volume,
                                             master_volume,
+                                            &options.numeric,
                                         ));
                                         data.set_disposal_master_qty(calculate_master_qty(
                                             disposal_qty_value,
@@ -938,11 +2620,12 @@ pub fn process_inventory_history_data(
                                         let onhand_qty =
                                             data.get_allocated_qty() + disposal_onhand_qty;
                                         data.set_allocated_qty(onhand_qty);
-                                        data.set_allocated_cbm(calculate_cbm(
+                                        data.set_allocated_cbm_decimal(calculate_cbm_decimal(
                                             onhand_qty,
                                             inventory.get_inner_qty_on_mas(),
                                             volume,
                                             master_volume,
+                                            &options.numeric,
                                         ));
                                         data.set_allocated_master_qty(calculate_master_qty(
                                             onhand_qty,
@@ -953,11 +2636,12 @@ pub fn process_inventory_history_data(
                             } else {
                                 let onhand_qty = data.get_allocated_qty() + history.get_quantity();
                                 data.set_allocated_qty(onhand_qty);
-                                data.set_allocated_cbm(calculate_cbm(
+                                data.set_allocated_cbm_decimal(calculate_cbm_decimal(
                                     onhand_qty,
                                     inventory.get_inner_qty_on_mas(),
                                     volume,
                                     master_volume,
+                                    &options.numeric,
                                 ));
                                 data.set_allocated_master_qty(calculate_master_qty(
                                     onhand_qty,
@@ -981,90 +2665,652 @@ pub fn process_inventory_history_data(
             }
             _ => {}
         }
+        Ok(())
     }
 
-    let timestamp = to_date.map_or_else(now_to_epoch, |td| {
-        let now = now_to_epoch();
-        if td as u64 > now {
-            now
-        } else {
-            td as u64
-        }
-    });
-    let now = epoch_to_human_gmt7(timestamp);
-
-    if merged_inventory_history.contains_key(&now) {
-        let (data, _) = merged_inventory_history.get_mut(&now).unwrap();
-        data.set_storage_time_days(
-            days_between(
-                from_date.map_or_else(
-                    || goods_receipt.get_imported_at() as u64,
-                    |fd| {
-                        if fd < goods_receipt.get_imported_at() {
-                            goods_receipt.get_imported_at() as u64
-                        } else {
-                            fd as u64
-                        }
-                    },
-                ),
-                Some(timestamp),
-            ) as i32
-                + 1,
-        );
-    } else if last_stock_qty > 0 {
-        let mut data = InventoryData::new();
-        data.set_opening_stock(last_stock_qty);
-        data.set_opening_cbm(calculate_cbm(
-            last_stock_qty,
-            inventory.get_inner_qty_on_mas(),
+    /// Closes the run: adds the row for today/to_date and the valuation.
+    pub fn finish(self) -> ProcessInventoryHistoryOutput {
+        let Self {
+            inventory,
+            asin_outbound_list,
+            goods_receipt,
+            from_date,
+            to_date,
+            options,
             volume,
             master_volume,
-        ));
-        data.set_opening_master_qty(calculate_master_qty(
+            mut warnings,
+            state,
+            ..
+        } = self;
+        let ProcessorCheckpoint {
+            mut merged_inventory_history,
             last_stock_qty,
-            inventory.get_inner_qty_on_mas(),
-        ));
-        data.set_asin(inventory.get_asin().to_string());
-        data.set_asin_outbound(asin_outbound_list.to_vec());
-        data.set_unit_price(inventory.get_unit_price());
-        data.set_received_date(goods_receipt.get_imported_at());
-        data.set_po_no(inventory.get_po_no().to_string());
-        data.set_inner_qty_on_mas(inventory.get_inner_qty_on_mas());
-        data.set_date(timestamp as i32);
-        data.set_line_in_cd(inventory.get_index_customs_declaration().to_string());
-        data.set_master_dimension(inventory.get_master_dimension().clone());
-        data.set_dimension(inventory.get_dimension().clone());
-        data.set_storage_time_days(
-            days_between(
-                from_date.map_or_else(
-                    || goods_receipt.get_imported_at() as u64,
-                    |fd| {
-                        if fd < goods_receipt.get_imported_at() {
-                            goods_receipt.get_imported_at() as u64
-                        } else {
-                            fd as u64
-                        }
-                    },
-                ),
-                Some(timestamp),
-            ) as i32
-                + 1,
-        );
-        merged_inventory_history.insert(now, (data, HashSet::new()));
+            goods_issue_ledger,
+            closing_balances,
+            closed_duration,
+            ..
+        } = state;
+
+        let now_epoch = || options.as_of.unwrap_or_else(now_to_epoch);
+        let timestamp = to_date.map_or_else(now_epoch, |td| {
+            let now = now_epoch();
+            if td > now {
+                now
+            } else {
+                td
+            }
+        });
+        let now = epoch_to_human(timestamp, options.utc_offset_seconds);
+
+        if let Some((data, _)) = merged_inventory_history.get_mut(&now) {
+            data.set_storage_time_days(
+                days_between(
+                    from_date.map_or_else(
+                        || goods_receipt.get_imported_at(),
+                        |fd| {
+                            if fd < goods_receipt.get_imported_at() {
+                                goods_receipt.get_imported_at()
+                            } else {
+                                fd
+                            }
+                        },
+                    ),
+                    Some(timestamp),
+                    options.utc_offset_seconds,
+                ) + 1,
+            );
+        } else if last_stock_qty > 0 {
+            let mut data = InventoryData::new();
+            data.set_opening_stock(last_stock_qty);
+            data.set_opening_cbm_decimal(calculate_cbm_decimal(
+                last_stock_qty,
+                inventory.get_inner_qty_on_mas(),
+                volume,
+                master_volume,
+                &options.numeric,
+            ));
+            data.set_opening_master_qty(calculate_master_qty(
+                last_stock_qty,
+                inventory.get_inner_qty_on_mas(),
+            ));
+            data.set_opening_pallet_qty(calculate_pallet_qty(
+                last_stock_qty,
+                inventory.get_inner_qty_on_mas(),
+                inventory.get_cartons_per_pallet(),
+            ));
+            data.set_asin(inventory.get_asin().to_string());
+            data.set_asin_outbound(asin_outbound_list.to_vec());
+            data.set_unit_price_decimal(options.numeric.round_price(inventory.get_unit_price()));
+            data.set_received_date(goods_receipt.get_imported_at());
+            data.set_po_no(inventory.get_po_no().to_string());
+            data.set_inner_qty_on_mas(inventory.get_inner_qty_on_mas());
+            data.set_date(timestamp);
+            data.set_line_in_cd(inventory.get_index_customs_declaration().to_string());
+            data.set_master_dimension(inventory.get_master_dimension().clone());
+            data.set_dimension(inventory.get_dimension().clone());
+            data.set_pallet_footprint(inventory.get_pallet_footprint());
+            data.set_closing_balances(closing_balances);
+            data.set_storage_time_days(
+                days_between(
+                    from_date.map_or_else(
+                        || goods_receipt.get_imported_at(),
+                        |fd| {
+                            if fd < goods_receipt.get_imported_at() {
+                                goods_receipt.get_imported_at()
+                            } else {
+                                fd
+                            }
+                        },
+                    ),
+                    Some(timestamp),
+                    options.utc_offset_seconds,
+                ) + 1,
+            );
+            merged_inventory_history.insert(now, (data, HashSet::new()));
+        }
+
+        let unit_price = options.numeric.round_price(inventory.get_unit_price());
+        let currency = options
+            .valuation
+            .currency
+            .clone()
+            .unwrap_or_else(|| inventory.get_currency().to_string());
+        let mut missing_rate = false;
+        for (data, _) in merged_inventory_history.values_mut() {
+            // Without a rate the row stays in the inventory's own currency.
+            let (rate, row_currency) = match options
+                .valuation
+                .rate_at(inventory.get_currency(), data.get_date())
+            {
+                Some(rate) => (rate, &currency),
+                None => {
+                    missing_rate = true;
+                    (1.0, &inventory.currency)
+                }
+            };
+            let value = |qty: i32| {
+                options
+                    .numeric
+                    .value(qty, unit_price, rate, options.valuation.value_precision)
+            };
+            data.set_currency(row_currency.clone());
+            data.set_opening_value(value(data.opening_stock));
+            data.set_inbound_value(value(data.inbound_qty));
+            data.set_outbound_value(value(data.outbound_qty));
+            data.set_disposal_value(value(data.disposal_stock));
+            data.set_closing_value(value(data.closing_stock));
+        }
+        if missing_rate {
+            warnings.push(format!(
+                "no FX rate from {} to {} for some days, their values are in {}",
+                inventory.get_currency(),
+                currency,
+                inventory.get_currency()
+            ));
+        }
+
+        let mut total_duration = closed_duration;
+        for (inventory_data, _) in merged_inventory_history.values() {
+            if counts_toward_duration(inventory_data, from_date, to_date) {
+                total_duration += inventory_data.get_storage_time_days();
+            }
+        }
+
+        ProcessInventoryHistoryOutput {
+            merged_inventory_history,
+            total_duration,
+            warnings,
+            goods_issue_ledger,
+            closing_balances,
+        }
+    }
+}
+
+impl InventoryHistoryProcessor<'_> {
+    /// push, reporting a rejected event in the warnings.
+    fn push_or_warn(&mut self, history: impl Borrow<ProshipInventoryHistory>) {
+        if let Err(e) = self.push(history) {
+            self.warnings.push(format!("Skipped history event: {}", e));
+        }
+    }
+}
+
+impl Extend<ProshipInventoryHistory> for InventoryHistoryProcessor<'_> {
+    fn extend<I: IntoIterator<Item = ProshipInventoryHistory>>(&mut self, iter: I) {
+        for history in iter {
+            self.push_or_warn(history);
+        }
     }
+}
+
+impl<'h> Extend<&'h ProshipInventoryHistory> for InventoryHistoryProcessor<'_> {
+    fn extend<I: IntoIterator<Item = &'h ProshipInventoryHistory>>(&mut self, iter: I) {
+        for history in iter {
+            self.push_or_warn(history);
+        }
+    }
+}
+
+/// Stock held at a point in time.
+#[derive(Clone, PartialEq, Default)]
+pub struct InventorySnapshot {
+    pub at: i64,
+    /// `at` as yyyymmdd in the report timezone.
+    pub date: String,
+    pub stock_qty: i32,
+    pub stock_cbm: Decimal,
+    pub balances: StatusBalances,
+    /// Goods issues with quantity still on hand at `at`.
+    pub allocated_by_goods_issue: HashMap<String, i32>,
+}
+
+/// Replays the history up to and including `at` through
+/// process_inventory_history_data and reports where the stock sits.
+pub fn snapshot_inventory_at(
+    input: ProcessInventoryHistoryInput<'_>,
+    at: i64,
+) -> InventorySnapshot {
+    let mut input = input;
+    input
+        .inventory_history_list
+        .retain(|h| h.get_created_at() <= at);
+    input.to_date = Some(input.to_date.map_or(at, |td| td.min(at)));
+
+    let inventory = input.inventory;
+    let stock_qty = input
+        .inventory_history_list
+        .iter()
+        .rev()
+        .find(|h| {
+            h.get_new_status()
+                != EnumProshipType_InventoryStatus::PENDING_FOR_IMPORT_INVENTORY_STATUS
+        })
+        .map_or(0, |h| h.get_stock_qty());
+    let (volume, master_volume, _) = inventory.resolve_volumes(input.options.volume_tolerance);
+    let stock_cbm = calculate_cbm_decimal(
+        stock_qty,
+        inventory.get_inner_qty_on_mas(),
+        volume,
+        master_volume,
+        &input.options.numeric,
+    );
+
+    let utc_offset_seconds = input.options.utc_offset_seconds;
+    let output = process_inventory_history_data(input);
+
+    InventorySnapshot {
+        at,
+        date: epoch_to_human(at, utc_offset_seconds),
+        stock_qty,
+        stock_cbm,
+        balances: output.closing_balances,
+        allocated_by_goods_issue: output
+            .goods_issue_ledger
+            .into_iter()
+            .filter(|(_, l)| l.get_open_qty() != 0)
+            .map(|(id, l)| (id, l.get_open_qty()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut total_duration = 0;
-    for (_, (inventory_data, _)) in &merged_inventory_history {
-        if let (Some(from), Some(to)) = (from_date, to_date) {
-            if inventory_data.get_date() < from || inventory_data.get_date() > to {
-                continue;
+    use EnumProshipType_InventoryStatus::*;
+
+    const DAY: i64 = 24 * 3600;
+    // 2024-03-01 00:00 at GMT+7.
+    const START: i64 = 1_709_226_000;
+
+    fn event(
+        created_at: i64,
+        stock_qty: i32,
+        old_status: EnumProshipType_InventoryStatus,
+        new_status: EnumProshipType_InventoryStatus,
+        quantity: i32,
+        goods_issue_id: &str,
+    ) -> ProshipInventoryHistory {
+        ProshipInventoryHistory {
+            created_at,
+            stock_qty,
+            old_status,
+            new_status,
+            quantity,
+            goods_issue_id: goods_issue_id.to_string(),
+        }
+    }
+
+    fn history() -> Vec<ProshipInventoryHistory> {
+        vec![
+            event(
+                START + 3600,
+                100,
+                PENDING_FOR_IMPORT_INVENTORY_STATUS,
+                AVALABLE_INVENTORY_STATUS,
+                100,
+                "",
+            ),
+            event(
+                START + 7200,
+                100,
+                AVALABLE_INVENTORY_STATUS,
+                ON_HAND_INVENTORY_STATUS,
+                30,
+                "gi1",
+            ),
+            event(
+                START + DAY + 3600,
+                80,
+                ON_HAND_INVENTORY_STATUS,
+                EXPORTED_INVENTORY_STATUS,
+                20,
+                "gi1",
+            ),
+            event(
+                START + 2 * DAY + 3600,
+                75,
+                AVALABLE_INVENTORY_STATUS,
+                DAMAGED_INVENTORY_STATUS,
+                5,
+                "",
+            ),
+            event(
+                START + 2 * DAY + 7200,
+                75,
+                ON_HAND_INVENTORY_STATUS,
+                AVALABLE_INVENTORY_STATUS,
+                10,
+                "gi1",
+            ),
+            event(
+                START + 3 * DAY + 3600,
+                78,
+                DAMAGED_INVENTORY_STATUS,
+                AVALABLE_INVENTORY_STATUS,
+                3,
+                "",
+            ),
+            event(
+                START + 4 * DAY + 3600,
+                78,
+                AVALABLE_INVENTORY_STATUS,
+                ON_HAND_INVENTORY_STATUS,
+                40,
+                "gi2",
+            ),
+            event(
+                START + 4 * DAY + 7200,
+                38,
+                ON_HAND_INVENTORY_STATUS,
+                EXPORTED_INVENTORY_STATUS,
+                40,
+                "gi2",
+            ),
+        ]
+    }
+
+    fn run(
+        inventory: &ProshipInventory,
+        goods_receipt: &ProshipGoodsReceipt,
+        events: Vec<ProshipInventoryHistory>,
+        checkpoint: Option<ProcessorCheckpoint>,
+    ) -> (ProcessInventoryHistoryOutput, ProcessorCheckpoint) {
+        let inventory_ids = HashMap::from([("i1".to_string(), 100)]);
+        process_inventory_history_incremental(
+            ProcessInventoryHistoryInput {
+                inventory,
+                inventory_id: "i1",
+                asin_outbound_list: &[],
+                goods_receipt,
+                inventory_history_list: events,
+                inventory_ids,
+                from_date: None,
+                to_date: Some(START + 6 * DAY),
+                options: ProcessOptions {
+                    as_of: Some(START + 6 * DAY),
+                    ..Default::default()
+                },
+            },
+            checkpoint,
+        )
+    }
+
+    #[test]
+    fn split_incremental_run_matches_full_run() {
+        let inventory = ProshipInventory {
+            id: "i1".to_string(),
+            inner_qty_on_mas: 10,
+            volume: 0.01,
+            unit_price: 2.5,
+            ..Default::default()
+        };
+        let goods_receipt = ProshipGoodsReceipt {
+            imported_at: START + 3600,
+        };
+        // The last two events of 2024-03-05 in the same second.
+        let mut same_second = history();
+        same_second[6].created_at = same_second[5].created_at;
+
+        // Split in the middle of a day, then between the two events sharing a
+        // second; the second call also gets the events of the first again,
+        // which the checkpoint skips.
+        for (history, split) in [(history(), 4), (same_second, 6)] {
+            let (full, _) = run(&inventory, &goods_receipt, history.clone(), None);
+            let (first, checkpoint) =
+                run(&inventory, &goods_receipt, history[..split].to_vec(), None);
+            assert_eq!(checkpoint.merged_inventory_history.len(), 1);
+            assert_eq!(checkpoint.applied_at_last_created_at, 1);
+            let (second, _) = run(&inventory, &goods_receipt, history, Some(checkpoint));
+
+            let mut merged = first.merged_inventory_history;
+            merged.extend(second.merged_inventory_history);
+            assert_eq!(merged.len(), full.merged_inventory_history.len());
+            for (day, entry) in &full.merged_inventory_history {
+                assert!(merged.get(day) == Some(entry), "day {} differs", day);
             }
+            assert_eq!(second.total_duration, full.total_duration);
+            assert!(second.goods_issue_ledger == full.goods_issue_ledger);
+            assert!(second.closing_balances == full.closing_balances);
         }
-        total_duration += inventory_data.get_storage_time_days();
     }
 
-    ProcessInventoryHistoryOutput {
-        merged_inventory_history,
-        total_duration,
+    #[test]
+    fn processor_rejects_events_out_of_order() {
+        let inventory = ProshipInventory {
+            id: "i1".to_string(),
+            inner_qty_on_mas: 10,
+            volume: 0.01,
+            ..Default::default()
+        };
+        let goods_receipt = ProshipGoodsReceipt {
+            imported_at: START + 3600,
+        };
+        let history = history();
+        let mut processor = InventoryHistoryProcessor::new(ProcessInventoryHistoryInput {
+            inventory: &inventory,
+            inventory_id: "i1",
+            asin_outbound_list: &[],
+            goods_receipt: &goods_receipt,
+            inventory_history_list: history[..3].to_vec(),
+            inventory_ids: HashMap::from([("i1".to_string(), 100)]),
+            from_date: None,
+            to_date: Some(START + 6 * DAY),
+            options: ProcessOptions {
+                as_of: Some(START + 6 * DAY),
+                ..Default::default()
+            },
+        });
+        let before = processor.checkpoint();
+        assert_eq!(
+            processor.push(history[1].clone()),
+            Err(OutOfOrderEvent {
+                created_at: history[1].created_at,
+                last_created_at: history[2].created_at,
+            })
+        );
+        assert!(processor.checkpoint() == before);
+        // An event in the same second as the last one is in order.
+        assert_eq!(processor.push(history[2].clone()), Ok(()));
+        assert_eq!(processor.checkpoint().applied_at_last_created_at, 2);
+
+        processor.extend([history[0].clone()]);
+        let output = processor.finish();
+        let skipped: Vec<&String> = output
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Skipped history event: createdAt"))
+            .collect();
+        assert_eq!(skipped.len(), 1, "{:?}", output.warnings);
+    }
+
+    fn dimension(length: f64, width: f64, height: f64) -> Option<ProshipDimension> {
+        Some(ProshipDimension {
+            length,
+            width,
+            height,
+        })
+    }
+
+    #[test]
+    fn dimension_units_convert_to_cubic_meters() {
+        let cm = dimension(100.0, 50.0, 20.0).unwrap();
+        assert!((cm.volume_cbm(DimensionUnit::Centimeter) - 0.1).abs() < 1e-12);
+        let mm = dimension(1000.0, 500.0, 200.0).unwrap();
+        assert!((mm.volume_cbm(DimensionUnit::Millimeter) - 0.1).abs() < 1e-12);
+        let inch = dimension(10.0, 10.0, 10.0).unwrap();
+        assert!((inch.volume_cbm(DimensionUnit::Inch) - 0.016_387_064).abs() < 1e-12);
+
+        assert_eq!(
+            DimensionUnit::from_name(" MM "),
+            Some(DimensionUnit::Millimeter)
+        );
+        assert_eq!(
+            DimensionUnit::from_name("inches"),
+            Some(DimensionUnit::Inch)
+        );
+        assert_eq!(DimensionUnit::from_name("ft"), None);
+    }
+
+    #[test]
+    fn resolve_volumes_keeps_explicit_volume_within_tolerance() {
+        let inventory = ProshipInventory {
+            dimension: dimension(1000.0, 500.0, 200.0),
+            dimension_unit: Some(DimensionUnit::Millimeter),
+            volume: 0.104,
+            master_dimension: dimension(1000.0, 1000.0, 200.0),
+            master_volume: 0.4,
+            ..Default::default()
+        };
+        let (volume, master_volume, warnings) = inventory.resolve_volumes(0.05);
+        assert_eq!(volume, 0.104);
+        assert_eq!(master_volume, 0.2);
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].starts_with("masterVolume 0.4 differs"),
+            "{}",
+            warnings[0]
+        );
+    }
+
+    #[test]
+    fn unknown_dimension_unit_falls_back_to_centimeters_with_a_warning() {
+        let inventory = ProshipInventory {
+            dimension: dimension(100.0, 50.0, 20.0),
+            unknown_dimension_unit: "ft".to_string(),
+            ..Default::default()
+        };
+        let (volume, _, warnings) = inventory.resolve_volumes(0.05);
+        assert_eq!(volume, 0.1);
+        assert_eq!(
+            warnings,
+            ["Unknown dimensionUnit \"ft\", using centimeters"]
+        );
+    }
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::try_parse(s).unwrap()
+    }
+
+    #[test]
+    fn decimal_parses_and_formats_exactly() {
+        assert_eq!(dec("-12.345").to_string(), "-12.345");
+        assert_eq!(dec("+0.10").to_string(), "0.10");
+        assert_eq!(dec(".5").to_string(), "0.5");
+        assert_eq!(Decimal::from_f32(0.1).to_string(), "0.1");
+        assert_eq!(Decimal::from_f32(1.1).to_string(), "1.1");
+        assert_eq!(Decimal::from_f64(f64::INFINITY), Decimal::MAX);
+        assert!(Decimal::try_parse("1e3").is_none());
+        assert!(Decimal::try_parse("").is_none());
+        assert_eq!(Decimal::parse("abc"), Decimal::default());
+        // Places beyond MAX_SCALE are dropped.
+        assert_eq!(dec("0.12345678901234567890123456789").to_string().len(), 30);
+    }
+
+    #[test]
+    fn decimal_arithmetic_is_exact() {
+        assert_eq!(dec("0.1") + dec("0.2"), dec("0.3"));
+        assert_eq!(dec("1.005") - dec("2"), dec("-0.995"));
+        assert_eq!(dec("1.5") * dec("-0.25"), dec("-0.375"));
+        assert_eq!(
+            [dec("0.001"); 1000].into_iter().sum::<Decimal>(),
+            Decimal::from_i64(1)
+        );
+        assert_eq!(
+            dec("1").checked_div(dec("3"), 4, RoundingMode::HalfUp),
+            Some(dec("0.3333"))
+        );
+        assert_eq!(
+            dec("2").checked_div(dec("-3"), 2, RoundingMode::HalfUp),
+            Some(dec("-0.67"))
+        );
+        assert!(dec("1")
+            .checked_div(Decimal::default(), 2, RoundingMode::HalfUp)
+            .is_none());
+        assert_eq!(Decimal::MAX + Decimal::from_i64(1), Decimal::MAX);
+        assert_eq!(Decimal::MIN - Decimal::from_i64(1), Decimal::MIN);
+        assert_eq!(Decimal::MAX * Decimal::from_i64(-2), Decimal::MIN);
+        assert!(Decimal::MAX.checked_add(Decimal::from_i64(1)).is_none());
+    }
+
+    #[test]
+    fn decimal_equality_ignores_scale_but_not_overflow() {
+        assert_eq!(dec("1.50"), dec("1.5"));
+        assert_ne!(dec("1.5"), dec("1.51"));
+        // `big` does not fit at 28 places, so it equals no 28-place value.
+        let big = Decimal::new(i128::MAX / 10, 0);
+        let fine = Decimal::new(1, Decimal::MAX_SCALE);
+        assert!(big.rescale(Decimal::MAX_SCALE).is_none());
+        assert_ne!(big, fine);
+        assert_ne!(fine, big);
+        assert_eq!(big, big);
+    }
+
+    #[test]
+    fn decimal_rounding_modes() {
+        let round = |s: &str, precision, mode| dec(s).round(precision, mode).to_string();
+        assert_eq!(round("2.345", 2, RoundingMode::HalfUp), "2.35");
+        assert_eq!(round("2.345", 2, RoundingMode::HalfEven), "2.34");
+        assert_eq!(round("2.355", 2, RoundingMode::HalfEven), "2.36");
+        assert_eq!(round("2.349", 2, RoundingMode::Truncate), "2.34");
+        assert_eq!(round("-2.345", 2, RoundingMode::HalfUp), "-2.35");
+        assert_eq!(round("-2.345", 2, RoundingMode::HalfEven), "-2.34");
+        assert_eq!(round("-2.349", 2, RoundingMode::Truncate), "-2.34");
+        // Rounding to more places pads with zeros.
+        assert_eq!(round("2.5", 3, RoundingMode::HalfUp), "2.500");
+        assert_eq!(round("0.5", 0, RoundingMode::HalfEven), "0");
+        assert_eq!(round("1.5", 0, RoundingMode::HalfEven), "2");
+        assert_eq!(
+            dec("5").checked_div(dec("8"), 2, RoundingMode::HalfEven),
+            Some(dec("0.62"))
+        );
+        assert_eq!(
+            dec("5").checked_div(dec("8"), 2, RoundingMode::Truncate),
+            Some(dec("0.62"))
+        );
+        assert_eq!(
+            dec("7").checked_div(dec("8"), 2, RoundingMode::HalfUp),
+            Some(dec("0.88"))
+        );
+    }
+
+    #[test]
+    fn numeric_config_applies_precision_in_both_modes() {
+        let config = |mode, rounding, price_precision| NumericConfig {
+            mode,
+            cbm_precision: 3,
+            price_precision,
+            rounding,
+        };
+        let float = config(NumericMode::Float, RoundingMode::HalfUp, Some(2));
+        let decimal = config(NumericMode::Decimal, RoundingMode::HalfEven, Some(2));
+        assert_eq!(float.round_price(1.005).to_string(), "1");
+        assert_eq!(decimal.round_price(1.005).to_string(), "1.00");
+        assert_eq!(decimal.round_price(1.015).to_string(), "1.02");
+        assert_eq!(
+            config(NumericMode::Decimal, RoundingMode::HalfUp, None)
+                .round_price(1.005)
+                .to_string(),
+            "1.005"
+        );
+        assert_eq!(decimal.per_unit(dec("10"), 3, 4), dec("3.3333"));
+        assert_eq!(decimal.per_unit(dec("10"), 0, 4), Decimal::default());
+        assert_eq!(decimal.value(3, dec("0.1"), 1.0, 2), dec("0.3"));
+        assert_eq!(float.round(0.0015, 3), 0.002);
+        assert_eq!(decimal.round(0.0025, 3), 0.002);
+    }
+
+    #[test]
+    fn inventory_data_keeps_f32_fields_and_exact_decimals() {
+        let mut data = InventoryData::new();
+        data.set_closing_cbm_decimal(dec("123456.789"));
+        assert_eq!(data.closing_cbm, 123456.79);
+        assert_eq!(data.get_closing_cbm_decimal(), dec("123456.789"));
+        // Assigning the f32 field directly wins over the stale exact value.
+        data.closing_cbm = 2.5;
+        assert_eq!(data.get_closing_cbm_decimal(), dec("2.5"));
+        data.set_unit_price(1.1);
+        assert_eq!(data.get_unit_price_decimal(), dec("1.1"));
     }
 }
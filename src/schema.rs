@@ -0,0 +1,78 @@
+//! JSON Schema for the JSON contract of the bindings (`schema` feature), so
+//! other services can produce and check input without the Rust structs.
+
+use crate::wasm::{
+    ErrorPayloadSerializable, HandleInventoryListInputSerializable,
+    ProcessedInventoryHistoryResultSerializable, ValidationIssueSerializable,
+};
+use jsonschema::JSONSchema;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{Schema, SchemaObject, SubschemaValidation};
+use schemars::schema_for;
+use std::sync::OnceLock;
+
+fn schema_value(schema: schemars::schema::RootSchema) -> serde_json::Value {
+    serde_json::to_value(schema).expect("Failed to serialize schema")
+}
+
+/// Schema of the process_inventory_history_wasm input.
+pub fn input_schema() -> serde_json::Value {
+    schema_value(schema_for!(HandleInventoryListInputSerializable))
+}
+
+/// Schema of the process_inventory_history_wasm output.
+pub fn output_schema() -> serde_json::Value {
+    schema_value(schema_for!(ProcessedInventoryHistoryResultSerializable))
+}
+
+/// Schema of the error payload for rejected input.
+pub fn error_schema() -> serde_json::Value {
+    schema_value(schema_for!(ErrorPayloadSerializable))
+}
+
+/// Checks `input` against input_schema, reporting every violation with the
+/// JSON pointer of the offending value.
+pub fn validate_input(input: &serde_json::Value) -> Result<(), ErrorPayloadSerializable> {
+    static SCHEMA: OnceLock<JSONSchema> = OnceLock::new();
+    let schema =
+        SCHEMA.get_or_init(|| JSONSchema::compile(&input_schema()).expect("Invalid input schema"));
+
+    schema
+        .validate(input)
+        .map_err(|errors| ErrorPayloadSerializable {
+            error: "Invalid input JSON".to_string(),
+            issues: errors
+                .map(|e| ValidationIssueSerializable {
+                    path: e.instance_path.to_string(),
+                    message: e.to_string(),
+                })
+                .collect(),
+        })
+}
+
+fn any_of(schemas: Vec<Schema>) -> Schema {
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            any_of: Some(schemas),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Timestamps: epoch seconds or an ISO-8601 / numeric string.
+pub(crate) fn timestamp_schema(gen: &mut SchemaGenerator) -> Schema {
+    any_of(vec![
+        gen.subschema_for::<i64>(),
+        gen.subschema_for::<String>(),
+    ])
+}
+
+pub(crate) fn optional_timestamp_schema(gen: &mut SchemaGenerator) -> Schema {
+    any_of(vec![
+        gen.subschema_for::<i64>(),
+        gen.subschema_for::<String>(),
+        gen.subschema_for::<()>(),
+    ])
+}
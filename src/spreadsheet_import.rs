@@ -0,0 +1,696 @@
+//! Reads inventory history and inventories from CSV / XLSX sheets, e.g. as
+//! sent by 3PL partners, into the processor's input types.
+
+use crate::process_inventory_list::{
+    fixed_offset, parse_timestamp_with_offset, DimensionUnit, EnumProshipType_InventoryStatus,
+    ProshipDimension, ProshipInventory, ProshipInventoryHistory, GMT7_OFFSET_SECONDS,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+
+/// Sheet cells as text: the first row holds the headers.
+#[derive(Clone, PartialEq, Default)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Line (1-based) of the header row in the file, so errors name the line
+    /// a row came from; data rows follow it.
+    pub header_line: usize,
+}
+
+impl Table {
+    /// Parses CSV text (RFC 4180 quoting, LF or CRLF line ends, optional UTF-8 BOM).
+    pub fn from_csv(text: &str, delimiter: char) -> Result<Self, String> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if quoted {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' => quoted = false,
+                    _ => field.push(c),
+                }
+            } else if c == '"' && field.is_empty() {
+                quoted = true;
+            } else if c == delimiter {
+                record.push(std::mem::take(&mut field));
+            } else if c == '\n' || c == '\r' {
+                if c == '\r' && chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            } else {
+                field.push(c);
+            }
+        }
+        if quoted {
+            return Err("unterminated quoted field".to_string());
+        }
+        if !field.is_empty() || !record.is_empty() {
+            record.push(field);
+            records.push(record);
+        }
+
+        let mut records = records.into_iter();
+        let headers = records.next().ok_or("missing header row")?;
+        Ok(Self {
+            headers,
+            rows: records.collect(),
+            header_line: 1,
+        })
+    }
+
+    /// Reads a sheet (the first one when `sheet` is `None`) of an XLSX, XLS or
+    /// ODS file. Date cells become `yyyy-mm-dd hh:mm:ss` text.
+    #[cfg(feature = "xlsx")]
+    pub fn from_xlsx(bytes: &[u8], sheet: Option<&str>) -> Result<Self, String> {
+        use calamine::{open_workbook_auto_from_rs, Data, Reader};
+
+        let mut workbook =
+            open_workbook_auto_from_rs(std::io::Cursor::new(bytes)).map_err(|e| e.to_string())?;
+        let range = match sheet {
+            Some(name) => workbook.worksheet_range(name),
+            None => workbook
+                .worksheet_range_at(0)
+                .ok_or("workbook has no sheets")?,
+        }
+        .map_err(|e| e.to_string())?;
+
+        let cell_text = |cell: &Data| match cell {
+            Data::Int(v) => v.to_string(),
+            Data::Float(v) if v.fract() == 0.0 && v.abs() < 1e15 => (*v as i64).to_string(),
+            Data::Float(v) => v.to_string(),
+            Data::String(v) | Data::DateTimeIso(v) | Data::DurationIso(v) => v.clone(),
+            Data::Bool(v) => v.to_string(),
+            Data::DateTime(v) => excel_serial_to_text(v.as_f64()),
+            Data::Error(_) | Data::Empty => String::new(),
+        };
+        // The range starts at the first used row, which need not be row 1.
+        let first_row = range.start().map_or(0, |(row, _)| row as usize);
+        let mut rows = range
+            .rows()
+            .map(|row| row.iter().map(cell_text).collect::<Vec<_>>())
+            .enumerate()
+            .skip_while(|(_, row)| row.iter().all(|c| c.trim().is_empty()));
+        let (header_index, headers) = rows.next().ok_or("missing header row")?;
+        Ok(Self {
+            headers,
+            rows: rows.map(|(_, row)| row).collect(),
+            header_line: first_row + header_index + 1,
+        })
+    }
+}
+
+/// Excel serial date (days since 1899-12-30) as `yyyy-mm-dd hh:mm:ss`.
+#[cfg(feature = "xlsx")]
+fn excel_serial_to_text(serial: f64) -> String {
+    use chrono::{Datelike, Timelike};
+
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .expect("Invalid Excel epoch");
+    let seconds = (serial * 86400.0).round() as i64;
+    let dt = epoch + chrono::Duration::seconds(seconds);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        dt.year(),
+        dt.month(),
+        dt.day(),
+        dt.hour(),
+        dt.minute(),
+        dt.second()
+    )
+}
+
+/// Lowercase without spaces, `_`, `-` and `.`, so headers match loosely.
+fn normalize(v: &str) -> String {
+    v.trim().to_lowercase().replace([' ', '_', '-', '.'], "")
+}
+
+/// Header of the column each field is read from. Fields are the camelCase
+/// JSON input names (`createdAt`, `stockQty`, ...; dimensions flattened as
+/// `dimensionLength`, `masterDimensionWidth`); unmapped fields are read from
+/// the column headed by their own name.
+#[derive(Clone, PartialEq, Default)]
+pub struct ColumnMapping {
+    headers: HashMap<String, String>,
+}
+
+impl ColumnMapping {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn set(&mut self, field: &str, header: &str) {
+        self.headers.insert(normalize(field), header.to_string());
+    }
+
+    pub fn header<'a>(&'a self, field: &'a str) -> &'a str {
+        self.headers
+            .get(&normalize(field))
+            .map_or(field, |h| h.as_str())
+    }
+}
+
+/// Status cell text to status. Besides the numeric codes it knows the enum
+/// names and the English and Vietnamese status names; partners' own names
+/// can be added with `set`.
+#[derive(Clone, PartialEq)]
+pub struct StatusMapping {
+    names: HashMap<String, EnumProshipType_InventoryStatus>,
+}
+
+impl Default for StatusMapping {
+    fn default() -> Self {
+        use EnumProshipType_InventoryStatus::*;
+        let mut mapping = Self {
+            names: HashMap::new(),
+        };
+        let names: [(EnumProshipType_InventoryStatus, &[&str]); 8] = [
+            (OTHER_INVENTORY_STATUS, &["other", "khác"]),
+            (
+                AVALABLE_INVENTORY_STATUS,
+                &["available", "avalable", "khả dụng"],
+            ),
+            (
+                ON_HAND_INVENTORY_STATUS,
+                &["on hand", "allocated", "đang giữ"],
+            ),
+            (DAMAGED_INVENTORY_STATUS, &["damaged", "hư hỏng"]),
+            (RETURN_INVENTORY_STATUS, &["return", "returned", "trả về"]),
+            (LIQUIDATION_INVENTORY_STATUS, &["liquidation", "thanh lý"]),
+            (
+                EXPORTED_INVENTORY_STATUS,
+                &["exported", "shipped", "đã xuất"],
+            ),
+            (
+                PENDING_FOR_IMPORT_INVENTORY_STATUS,
+                &["pending for import", "pending", "chờ nhập"],
+            ),
+        ];
+        for (status, aliases) in names {
+            mapping.set(&(status as u8).to_string(), status);
+            for alias in aliases {
+                mapping.set(alias, status);
+                mapping.set(&format!("{}_INVENTORY_STATUS", alias), status);
+            }
+        }
+        mapping
+    }
+}
+
+impl StatusMapping {
+    pub fn set(&mut self, name: &str, status: EnumProshipType_InventoryStatus) {
+        self.names.insert(normalize(name), status);
+    }
+
+    pub fn status(&self, name: &str) -> Option<EnumProshipType_InventoryStatus> {
+        self.names.get(&normalize(name)).copied()
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ImportOptions {
+    pub columns: ColumnMapping,
+    pub statuses: StatusMapping,
+    /// Timezone of date cells without an offset; must pass is_valid_utc_offset.
+    pub utc_offset_seconds: i32,
+    /// `,` for sheets written with decimal commas.
+    pub decimal_separator: char,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            columns: ColumnMapping::default(),
+            statuses: StatusMapping::default(),
+            utc_offset_seconds: GMT7_OFFSET_SECONDS,
+            decimal_separator: '.',
+        }
+    }
+}
+
+/// Column index of each field present in the table.
+struct FieldColumns<'a> {
+    options: &'a ImportOptions,
+    indexes: HashMap<&'static str, usize>,
+}
+
+impl<'a> FieldColumns<'a> {
+    fn new(
+        table: &Table,
+        options: &'a ImportOptions,
+        fields: &[&'static str],
+        required: &[&'static str],
+    ) -> Result<Self, String> {
+        let headers: Vec<String> = table.headers.iter().map(|h| normalize(h)).collect();
+        let mut indexes = HashMap::new();
+        for field in fields {
+            let header = normalize(options.columns.header(field));
+            if let Some(i) = headers.iter().position(|h| *h == header) {
+                indexes.insert(*field, i);
+            }
+        }
+        for field in required {
+            if !indexes.contains_key(field) {
+                return Err(format!(
+                    "missing column {:?}",
+                    options.columns.header(field)
+                ));
+            }
+        }
+        Ok(Self { options, indexes })
+    }
+
+    fn has(&self, field: &str) -> bool {
+        self.indexes.contains_key(field)
+    }
+
+    /// Rows with any non-empty cell, with their line number in the sheet.
+    fn rows<'t>(&'t self, table: &'t Table) -> impl Iterator<Item = Row<'t>> {
+        table
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, cells)| cells.iter().any(|c| !c.trim().is_empty()))
+            .map(move |(i, cells)| Row {
+                columns: self,
+                cells,
+                line: table.header_line + i + 1,
+            })
+    }
+}
+
+struct Row<'a> {
+    columns: &'a FieldColumns<'a>,
+    cells: &'a [String],
+    line: usize,
+}
+
+impl Row<'_> {
+    fn cell(&self, field: &str) -> Option<&str> {
+        self.columns
+            .indexes
+            .get(field)
+            .and_then(|i| self.cells.get(*i))
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+    }
+
+    fn error(&self, field: &str, message: String) -> String {
+        format!(
+            "row {}, column {:?}: {}",
+            self.line,
+            self.columns.options.columns.header(field),
+            message
+        )
+    }
+
+    fn text(&self, field: &str) -> String {
+        self.cell(field).unwrap_or_default().to_string()
+    }
+
+    fn float(&self, field: &str) -> Result<Option<f64>, String> {
+        self.cell(field)
+            .map(|v| {
+                let separator = self.columns.options.decimal_separator;
+                let number = if separator == '.' {
+                    v.to_string()
+                } else {
+                    v.replace('.', "").replace(separator, ".")
+                };
+                number
+                    .parse::<f64>()
+                    .map_err(|_| self.error(field, format!("invalid number {:?}", v)))
+            })
+            .transpose()
+    }
+
+    /// Whole number cell, checked against the range of the field's type.
+    fn int<T: TryFrom<i64>>(&self, field: &str) -> Result<Option<T>, String> {
+        let cell = || self.cell(field).unwrap_or_default();
+        match self.float(field)? {
+            None => Ok(None),
+            Some(v) if v.fract() != 0.0 => {
+                Err(self.error(field, format!("invalid integer {:?}", cell())))
+            }
+            Some(v) => {
+                let in_range = v.abs() < i64::MAX as f64;
+                in_range
+                    .then(|| T::try_from(v as i64).ok())
+                    .flatten()
+                    .map(Some)
+                    .ok_or_else(|| self.error(field, format!("{} is out of range", cell())))
+            }
+        }
+    }
+
+    fn timestamp(&self, field: &str) -> Result<Option<i64>, String> {
+        let offset = self.columns.options.utc_offset_seconds;
+        self.cell(field)
+            .map(|v| {
+                parse_timestamp_with_offset(v, offset)
+                    .or_else(|| parse_day_first_date(v, offset))
+                    .ok_or_else(|| self.error(field, format!("invalid date {:?}", v)))
+            })
+            .transpose()
+    }
+
+    fn status(&self, field: &str) -> Result<Option<EnumProshipType_InventoryStatus>, String> {
+        self.cell(field)
+            .map(|v| {
+                self.columns
+                    .options
+                    .statuses
+                    .status(v)
+                    .ok_or_else(|| self.error(field, format!("unknown status {:?}", v)))
+            })
+            .transpose()
+    }
+
+    fn dimension_unit(&self, field: &str) -> Result<Option<DimensionUnit>, String> {
+        self.cell(field)
+            .map(|v| {
+                DimensionUnit::from_name(v)
+                    .ok_or_else(|| self.error(field, format!("unknown unit {:?}", v)))
+            })
+            .transpose()
+    }
+
+    fn required<T>(&self, field: &str, value: Option<T>) -> Result<T, String> {
+        value.ok_or_else(|| self.error(field, "missing value".to_string()))
+    }
+}
+
+/// `dd/mm/yyyy[ hh:mm[:ss]]`, as Vietnamese spreadsheets write dates.
+fn parse_day_first_date(value: &str, utc_offset_seconds: i32) -> Option<i64> {
+    let naive = NaiveDateTime::parse_from_str(value, "%d/%m/%Y %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%d/%m/%Y %H:%M"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%d/%m/%Y")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })?;
+    naive
+        .and_local_timezone(fixed_offset(utc_offset_seconds))
+        .single()
+        .map(|dt| dt.timestamp())
+}
+
+const HISTORY_FIELDS: [&str; 7] = [
+    "inventoryId",
+    "createdAt",
+    "stockQty",
+    "oldStatus",
+    "newStatus",
+    "quantity",
+    "goodsIssueId",
+];
+
+const HISTORY_REQUIRED_FIELDS: [&str; 5] = [
+    "createdAt",
+    "stockQty",
+    "oldStatus",
+    "newStatus",
+    "quantity",
+];
+
+/// Events with their `inventoryId` cell, which must be filled in every row
+/// when `by_inventory` is set.
+fn history_rows(
+    table: &Table,
+    options: &ImportOptions,
+    by_inventory: bool,
+) -> Result<Vec<(String, ProshipInventoryHistory)>, String> {
+    let mut required = HISTORY_REQUIRED_FIELDS.to_vec();
+    if by_inventory {
+        required.push("inventoryId");
+    }
+    let columns = FieldColumns::new(table, options, &HISTORY_FIELDS, &required)?;
+    columns
+        .rows(table)
+        .map(|row| {
+            let history = ProshipInventoryHistory {
+                created_at: row.required("createdAt", row.timestamp("createdAt")?)?,
+                stock_qty: row.required("stockQty", row.int("stockQty")?)?,
+                old_status: row.required("oldStatus", row.status("oldStatus")?)?,
+                new_status: row.required("newStatus", row.status("newStatus")?)?,
+                quantity: row.required("quantity", row.int("quantity")?)?,
+                goods_issue_id: row.text("goodsIssueId"),
+            };
+            let inventory_id = if by_inventory {
+                row.required("inventoryId", row.cell("inventoryId"))?
+                    .to_string()
+            } else {
+                row.text("inventoryId")
+            };
+            Ok((inventory_id, history))
+        })
+        .collect()
+}
+
+/// History events of the sheet, sorted by `created_at` as the processor expects.
+pub fn history_from_table(
+    table: &Table,
+    options: &ImportOptions,
+) -> Result<Vec<ProshipInventoryHistory>, String> {
+    let mut history: Vec<_> = history_rows(table, options, false)?
+        .into_iter()
+        .map(|(_, h)| h)
+        .collect();
+    history.sort_by_key(|h| h.created_at);
+    Ok(history)
+}
+
+/// History events of a sheet covering several inventories, grouped by its
+/// `inventoryId` column and sorted by `created_at`. Rows without an
+/// `inventoryId` are an error.
+pub fn history_by_inventory_from_table(
+    table: &Table,
+    options: &ImportOptions,
+) -> Result<HashMap<String, Vec<ProshipInventoryHistory>>, String> {
+    let mut grouped: HashMap<String, Vec<ProshipInventoryHistory>> = HashMap::new();
+    for (inventory_id, history) in history_rows(table, options, true)? {
+        grouped.entry(inventory_id).or_default().push(history);
+    }
+    for history in grouped.values_mut() {
+        history.sort_by_key(|h| h.created_at);
+    }
+    Ok(grouped)
+}
+
+/// Whether the sheet has the (mapped) `inventoryId` column.
+pub fn table_has_inventory_ids(table: &Table, options: &ImportOptions) -> bool {
+    FieldColumns::new(table, options, &["inventoryId"], &[])
+        .map(|columns| columns.has("inventoryId"))
+        .unwrap_or(false)
+}
+
+const INVENTORY_FIELDS: [&str; 35] = [
+    "id",
+    "creatorId",
+    "createdAt",
+    "updatedAt",
+    "status",
+    "shelfCode",
+    "customerId",
+    "stockQty",
+    "stockCbm",
+    "goodsReceiptId",
+    "goodsIssueId",
+    "goodsId",
+    "duration",
+    "exportAt",
+    "asin",
+    "supplierId",
+    "asinOutbound",
+    "indexCustomsDeclaration",
+    "unitPrice",
+    "innerQtyOnMas",
+    "poNo",
+    "masterDimensionLength",
+    "masterDimensionWidth",
+    "masterDimensionHeight",
+    "dimensionLength",
+    "dimensionWidth",
+    "dimensionHeight",
+    "volume",
+    "masterVolume",
+    "masterQty",
+    "doNo",
+    "dimensionUnit",
+    "cartonsPerPallet",
+    "palletFootprint",
+    "currency",
+];
+
+fn dimension(row: &Row<'_>, prefix: &str) -> Result<Option<ProshipDimension>, String> {
+    let length = row.float(&format!("{}Length", prefix))?;
+    let width = row.float(&format!("{}Width", prefix))?;
+    let height = row.float(&format!("{}Height", prefix))?;
+    if length.is_none() && width.is_none() && height.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(ProshipDimension {
+        length: length.unwrap_or_default(),
+        width: width.unwrap_or_default(),
+        height: height.unwrap_or_default(),
+    }))
+}
+
+/// One inventory per row; only the `id` column is required and missing cells
+/// take the same defaults as absent JSON fields.
+pub fn inventories_from_table(
+    table: &Table,
+    options: &ImportOptions,
+) -> Result<Vec<ProshipInventory>, String> {
+    let columns = FieldColumns::new(table, options, &INVENTORY_FIELDS, &["id"])?;
+    columns
+        .rows(table)
+        .map(|row| {
+            let id = |field| row.int::<i64>(field).map(Option::unwrap_or_default);
+            let int = |field| row.int::<i32>(field).map(Option::unwrap_or_default);
+            let float = |field| row.float(field).map(Option::unwrap_or_default);
+            let timestamp = |field| row.timestamp(field).map(Option::unwrap_or_default);
+            Ok(ProshipInventory {
+                id: row.required("id", row.cell("id"))?.to_string(),
+                creator_id: id("creatorId")?,
+                created_at: timestamp("createdAt")?,
+                updated_at: timestamp("updatedAt")?,
+                status: row.status("status")?.unwrap_or_default(),
+                shelf_code: row.text("shelfCode"),
+                customer_id: id("customerId")?,
+                stock_qty: int("stockQty")?,
+                stock_cbm: float("stockCbm")? as f32,
+                goods_receipt_id: row.text("goodsReceiptId"),
+                goods_issue_id: row.text("goodsIssueId"),
+                goods_id: row.text("goodsId"),
+                duration: int("duration")?,
+                export_at: timestamp("exportAt")?,
+                asin: row.text("asin"),
+                supplier_id: row.text("supplierId"),
+                asin_outbound: row.text("asinOutbound"),
+                index_customs_declaration: row.text("indexCustomsDeclaration"),
+                unit_price: float("unitPrice")? as f32,
+                inner_qty_on_mas: int("innerQtyOnMas")?.max(1),
+                po_no: row.text("poNo"),
+                master_dimension: dimension(&row, "masterDimension")?,
+                dimension: dimension(&row, "dimension")?,
+                volume: float("volume")? as f32,
+                master_volume: float("masterVolume")? as f32,
+                master_qty: int("masterQty")?,
+                do_no: row.text("doNo"),
+                dimension_unit: row.dimension_unit("dimensionUnit")?,
+                unknown_dimension_unit: String::new(),
+                cartons_per_pallet: int("cartonsPerPallet")?,
+                pallet_footprint: float("palletFootprint")? as f32,
+                currency: row.text("currency"),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EnumProshipType_InventoryStatus::*;
+
+    const HEADER: &str = "createdAt,stockQty,oldStatus,newStatus,quantity,goodsIssueId";
+
+    #[test]
+    fn csv_quoting_and_line_ends() {
+        let text = "\u{feff}a,b,c\r\n\"x,y\",\"say \"\"hi\"\"\",\"two\nlines\"\r\nplain,,last\n";
+        let table = Table::from_csv(text, ',').unwrap();
+        assert_eq!(table.headers, ["a", "b", "c"]);
+        assert_eq!(
+            table.rows,
+            [
+                vec!["x,y", "say \"hi\"", "two\nlines"],
+                vec!["plain", "", "last"],
+            ]
+        );
+
+        let table = Table::from_csv("a;b\n\"1;2\";3", ';').unwrap();
+        assert_eq!(table.rows, [vec!["1;2", "3"]]);
+        assert!(Table::from_csv("a\n\"open", ',').is_err());
+        assert!(Table::from_csv("", ',').is_err());
+    }
+
+    #[test]
+    fn history_dates_numbers_and_statuses() {
+        let text = format!(
+            "{}\n02/03/2024 08:30,\"1.234,5\",available,on hand,\"1,0\",gi1\n\
+             2024-03-01 10:00:00,1234,1,2,10,\n",
+            HEADER
+        );
+        let table = Table::from_csv(&text, ',').unwrap();
+        let options = ImportOptions {
+            decimal_separator: ',',
+            ..Default::default()
+        };
+        // A fractional stock is rejected, with the line it is on.
+        assert_eq!(
+            history_from_table(&table, &options).err().unwrap(),
+            "row 2, column \"stockQty\": invalid integer \"1.234,5\""
+        );
+
+        let text = text.replace("1.234,5", "1.234");
+        let table = Table::from_csv(&text, ',').unwrap();
+        let history = history_from_table(&table, &options).unwrap();
+        // Sorted by createdAt; dd/mm/yyyy at GMT+7.
+        assert_eq!(history[0].created_at, 1_709_262_000);
+        assert_eq!(history[1].created_at, 1_709_343_000);
+        assert_eq!(history[1].stock_qty, 1234);
+        assert_eq!(history[1].quantity, 1);
+        assert!(history[1].old_status == AVALABLE_INVENTORY_STATUS);
+        assert!(history[1].new_status == ON_HAND_INVENTORY_STATUS);
+        assert_eq!(history[1].goods_issue_id, "gi1");
+    }
+
+    #[test]
+    fn out_of_range_integers_name_their_row() {
+        let text = format!(
+            "{}\n\n2024-03-01,1,1,2,1,\n2024-03-01,3000000000,1,2,1,\n",
+            HEADER
+        );
+        let table = Table::from_csv(&text, ',').unwrap();
+        // Line 4: the header, a blank line and one good row come before it.
+        assert_eq!(
+            history_from_table(&table, &ImportOptions::default())
+                .err()
+                .unwrap(),
+            "row 4, column \"stockQty\": 3000000000 is out of range"
+        );
+    }
+
+    #[test]
+    fn grouped_history_needs_every_inventory_id() {
+        let text = format!(
+            "inventoryId,{}\nA,2024-03-02,1,1,2,1,\nB,2024-03-01,1,1,2,1,\nA,2024-03-01,1,1,2,1,\n",
+            HEADER
+        );
+        let table = Table::from_csv(&text, ',').unwrap();
+        let grouped = history_by_inventory_from_table(&table, &ImportOptions::default()).unwrap();
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped["A"].len(), 2);
+        assert!(grouped["A"][0].created_at < grouped["A"][1].created_at);
+
+        let table = Table::from_csv(&text.replace("\nB,", "\n ,"), ',').unwrap();
+        assert_eq!(
+            history_by_inventory_from_table(&table, &ImportOptions::default())
+                .err()
+                .unwrap(),
+            "row 3, column \"inventoryId\": missing value"
+        );
+        assert!(history_from_table(&table, &ImportOptions::default()).is_ok());
+    }
+}
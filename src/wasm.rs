@@ -1,179 +1,727 @@
 //! WASM bindings for inventory history processor.
 
+use crate::aging::{
+    aging_report, daily_aging_report, default_aging_buckets, AgingBucket, AgingLot,
+};
+use crate::costing::{calculate_issue_costs, CostedIssue, CostingLot, CostingMethod};
+use crate::csv_export::{write_inventory_csv, CsvColumn, CsvLanguage, CsvOptions};
+#[cfg(feature = "xlsx")]
+use crate::process_inventory_list::BatchResult;
 use crate::process_inventory_list::{
-    EnumProshipType_InventoryStatus, InventoryData, ProcessInventoryHistoryInput,
-    ProcessInventoryHistoryOutput, ProshipDimension, ProshipGoodsReceipt, ProshipInventory,
-    ProshipInventoryHistory,
+    is_valid_timestamp, now_to_epoch, parse_timestamp, process_inventory_history_incremental,
+    process_inventory_history_iter, snapshot_inventory_at, Decimal, DimensionUnit,
+    EnumProshipType_InventoryStatus, FxRate, HistoryColumns, HistoryColumnsError, InventoryData,
+    InventoryHistoryProcessor, NumericConfig, NumericMode, ProcessInventoryHistoryInput,
+    ProcessInventoryHistoryOutput, ProcessOptions, ProcessorCheckpoint, ProshipDimension,
+    ProshipGoodsReceipt, ProshipInventory, ProshipInventoryHistory, RoundingMode, StatusBalances,
+    ValuationOptions,
 };
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "xlsx")]
+use crate::xlsx_export::{write_inventory_xlsx, XlsxOptions};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_path_to_error::Segment;
 use std::collections::HashMap;
+use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
-#[derive(Deserialize)]
+/// TypeScript declarations of the JSON input and output types, the same ones
+/// wasm-pack puts in the pkg `.d.ts`. The napi addon ships them as `types.d.ts`.
+pub fn typescript_declarations() -> String {
+    [
+        HandleInventoryListInputSerializable::DECL,
+        InventoryItem::DECL,
+        DimensionItem::DECL,
+        GoodsReceiptItem::DECL,
+        FxRateItem::DECL,
+        InventoryHistoryItem::DECL,
+        ProcessedInventoryHistoryResultSerializable::DECL,
+        GoodsIssueLedgerSerializable::DECL,
+        ProcessedEntry::DECL,
+        InventoryDataSerializable::DECL,
+        DimensionSerializable::DECL,
+        StatusBalancesSerializable::DECL,
+        AgingReportInputSerializable::DECL,
+        AgingBucketItem::DECL,
+        AgingReportSerializable::DECL,
+        AgingBucketTotalSerializable::DECL,
+        CostingInputSerializable::DECL,
+        CostingReportSerializable::DECL,
+        CostedIssueSerializable::DECL,
+        CostLayerConsumptionSerializable::DECL,
+        CostLayerSerializable::DECL,
+        GoodsIssueCostSerializable::DECL,
+        SnapshotInputSerializable::DECL,
+        InventorySnapshotSerializable::DECL,
+        IncrementalInputSerializable::DECL,
+        IncrementalResultSerializable::DECL,
+        PROCESSOR_CHECKPOINT_DECL,
+        CsvInputSerializable::DECL,
+        CsvOptionsItem::DECL,
+        #[cfg(feature = "xlsx")]
+        XlsxInputSerializable::DECL,
+        #[cfg(feature = "xlsx")]
+        XlsxOptionsItem::DECL,
+        NdjsonInputLine::DECL,
+        NdjsonOutputLine::DECL,
+        ErrorPayloadSerializable::DECL,
+        ValidationIssueSerializable::DECL,
+    ]
+    .join("\n\n")
+        + "\n"
+}
+
+#[derive(Deserialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
-struct HandleInventoryListInputSerializable {
-    inventory: serde_json::Value,
-    inventory_list: Vec<serde_json::Value>,
+pub(crate) struct HandleInventoryListInputSerializable {
+    inventory: InventoryItem,
+    #[tsify(type = "unknown[]")]
+    _inventory_list: Vec<serde_json::Value>,
     asin_outbound_list: Vec<String>,
-    goods: serde_json::Value,
-    goods_receipt: serde_json::Value,
+    #[tsify(type = "unknown")]
+    _goods: serde_json::Value,
+    goods_receipt: GoodsReceiptItem,
+    #[tsify(type = "unknown")]
     _supplier: serde_json::Value,
+    #[tsify(type = "unknown")]
     _customer: serde_json::Value,
+    #[serde(default)]
+    #[tsify(optional)]
     inventory_history_list: Vec<InventoryHistoryItem>,
+    #[tsify(type = "unknown[]")]
     _receipt_orders: Vec<serde_json::Value>,
     inventory_id: String,
     inventory_ids_map: HashMap<String, i32>,
-    from_date: Option<i32>,
-    to_date: Option<i32>,
+    #[serde(default, deserialize_with = "de_opt_timestamp")]
+    #[tsify(type = "number | string | null", optional)]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::schema::optional_timestamp_schema")
+    )]
+    from_date: Option<i64>,
+    #[serde(default, deserialize_with = "de_opt_timestamp")]
+    #[tsify(type = "number | string | null", optional)]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::schema::optional_timestamp_schema")
+    )]
+    to_date: Option<i64>,
+    #[tsify(optional)]
+    volume_tolerance: Option<f32>,
+    #[serde(default, deserialize_with = "de_opt_numeric_mode")]
+    #[tsify(type = "string", optional)]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    numeric_mode: Option<NumericMode>,
+    #[tsify(optional)]
+    precision: Option<u32>,
+    #[tsify(optional)]
+    price_precision: Option<u32>,
+    #[serde(default, deserialize_with = "de_opt_rounding_mode")]
+    #[tsify(type = "string", optional)]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    rounding_mode: Option<RoundingMode>,
+    #[tsify(optional)]
+    currency: Option<String>,
+    #[serde(default)]
+    #[tsify(optional)]
+    fx_rates: Vec<FxRateItem>,
+    #[tsify(optional)]
+    value_precision: Option<u32>,
+}
+
+/// `ProshipInventory` as sent by TS; missing or null fields take the default.
+#[derive(Deserialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+struct InventoryItem {
+    #[tsify(optional)]
+    id: Option<String>,
+    #[tsify(optional)]
+    creator_id: Option<i64>,
+    #[serde(default, deserialize_with = "de_opt_timestamp")]
+    #[tsify(type = "number | string | null", optional)]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::schema::optional_timestamp_schema")
+    )]
+    created_at: Option<i64>,
+    #[serde(default, deserialize_with = "de_opt_timestamp")]
+    #[tsify(type = "number | string | null", optional)]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::schema::optional_timestamp_schema")
+    )]
+    updated_at: Option<i64>,
+    #[tsify(optional)]
+    status: Option<i32>,
+    #[tsify(optional)]
+    shelf_code: Option<String>,
+    #[tsify(optional)]
+    customer_id: Option<i64>,
+    #[tsify(optional)]
+    stock_qty: Option<i32>,
+    #[tsify(optional)]
+    stock_cbm: Option<f32>,
+    #[tsify(optional)]
+    goods_receipt_id: Option<String>,
+    #[tsify(optional)]
+    goods_issue_id: Option<String>,
+    #[tsify(optional)]
+    goods_id: Option<String>,
+    #[tsify(optional)]
+    duration: Option<i32>,
+    #[serde(default, deserialize_with = "de_opt_timestamp")]
+    #[tsify(type = "number | string | null", optional)]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::schema::optional_timestamp_schema")
+    )]
+    export_at: Option<i64>,
+    #[tsify(optional)]
+    asin: Option<String>,
+    #[tsify(optional)]
+    supplier_id: Option<String>,
+    #[tsify(optional)]
+    asin_outbound: Option<String>,
+    #[tsify(optional)]
+    index_customs_declaration: Option<String>,
+    #[tsify(optional)]
+    unit_price: Option<f32>,
+    #[tsify(optional)]
+    inner_qty_on_mas: Option<i32>,
+    #[tsify(optional)]
+    po_no: Option<String>,
+    #[tsify(optional)]
+    master_dimension: Option<DimensionItem>,
+    #[tsify(optional)]
+    dimension: Option<DimensionItem>,
+    #[tsify(optional)]
+    volume: Option<f32>,
+    #[tsify(optional)]
+    master_volume: Option<f32>,
+    #[tsify(optional)]
+    master_qty: Option<i32>,
+    #[tsify(optional)]
+    do_no: Option<String>,
+    #[tsify(optional)]
+    dimension_unit: Option<String>,
+    #[tsify(optional)]
+    cartons_per_pallet: Option<i32>,
+    #[tsify(optional)]
+    pallet_footprint: Option<f32>,
+    #[tsify(optional)]
+    currency: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct DimensionItem {
+    #[tsify(optional)]
+    length: Option<f64>,
+    #[tsify(optional)]
+    width: Option<f64>,
+    #[tsify(optional)]
+    height: Option<f64>,
+}
+
+#[derive(Deserialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+struct GoodsReceiptItem {
+    #[serde(default, deserialize_with = "de_opt_timestamp")]
+    #[tsify(type = "number | string | null", optional)]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::schema::optional_timestamp_schema")
+    )]
+    imported_at: Option<i64>,
+}
+
+#[derive(Deserialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+struct FxRateItem {
+    currency: String,
+    rate: f64,
+    #[serde(default, deserialize_with = "de_timestamp")]
+    #[tsify(type = "number | string", optional)]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::schema::timestamp_schema")
+    )]
+    effective_at: i64,
+}
+
+#[derive(Deserialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 struct InventoryHistoryItem {
-    id: String,
-    created_at: i32,
+    _id: String,
+    #[serde(deserialize_with = "de_timestamp")]
+    #[tsify(type = "number | string")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(schema_with = "crate::schema::timestamp_schema")
+    )]
+    created_at: i64,
     stock_qty: i32,
     old_status: i32,
     new_status: i32,
     quantity: i32,
+    #[tsify(optional)]
     goods_issue_id: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
-struct ProcessedInventoryHistoryResultSerializable {
+pub(crate) struct ProcessedInventoryHistoryResultSerializable {
     entries: HashMap<String, ProcessedEntry>,
     total_duration: i32,
+    warnings: Vec<String>,
+    goods_issue_ledger: HashMap<String, GoodsIssueLedgerSerializable>,
+}
+
+#[derive(Serialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+struct GoodsIssueLedgerSerializable {
+    allocated_qty: i32,
+    released_qty: i32,
+    disposed_qty: i32,
+    shipped_qty: i32,
+    open_qty: i32,
+    allocated_at: Option<i64>,
+    released_at: Option<i64>,
+    disposed_at: Option<i64>,
+    shipped_at: Option<i64>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 struct ProcessedEntry {
-    data: serde_json::Value,
+    data: InventoryDataSerializable,
     goods_issue_ids: Vec<String>,
 }
 
-fn json_f64(v: &serde_json::Value, key: &str) -> f64 {
-    v.get(key).and_then(|x| x.as_f64()).unwrap_or(0.0)
+/// Error payload for rejected input: the panic message (JSON) of the string
+/// entry points and the value thrown by process_inventory_history_object.
+#[derive(Serialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorPayloadSerializable {
+    pub error: String,
+    pub issues: Vec<ValidationIssueSerializable>,
 }
 
-fn json_f32(v: &serde_json::Value, key: &str) -> f32 {
-    json_f64(v, key) as f32
+#[derive(Serialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationIssueSerializable {
+    /// JSON pointer to the offending value, e.g. `/inventoryHistoryList/3/createdAt`;
+    /// empty when unknown.
+    pub path: String,
+    pub message: String,
 }
 
-fn json_i32(v: &serde_json::Value, key: &str) -> i32 {
-    v.get(key).and_then(|x| x.as_i64()).unwrap_or(0) as i32
+fn invalid_input(message: String) -> ErrorPayloadSerializable {
+    ErrorPayloadSerializable {
+        error: "Invalid input JSON".to_string(),
+        issues: vec![ValidationIssueSerializable {
+            path: String::new(),
+            message,
+        }],
+    }
 }
 
-fn json_i64(v: &serde_json::Value, key: &str) -> i64 {
-    v.get(key).and_then(|x| x.as_i64()).unwrap_or(0)
+/// JSON pointer of a serde_path_to_error path, e.g. `/inventoryHistoryList/3/createdAt`.
+fn json_pointer(path: &serde_path_to_error::Path) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            Segment::Seq { index } => format!("/{}", index),
+            Segment::Map { key } => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+            Segment::Enum { variant } => format!("/{}", variant),
+            Segment::Unknown => String::new(),
+        })
+        .collect()
 }
 
-fn json_str(v: &serde_json::Value, key: &str) -> String {
-    v.get(key)
-        .and_then(|x| x.as_str())
-        .unwrap_or("")
-        .to_string()
+/// Deserializes from `deserializer`, reporting a failure with the pointer of
+/// the offending value below `path`. This also covers what the input schema
+/// doesn't: wrapper fields such as `asOf` or `checkpoint` and custom checks.
+fn deserialize_at<'de, T, D>(deserializer: D, path: &str) -> Result<T, ErrorPayloadSerializable>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    serde_path_to_error::deserialize(deserializer).map_err(|e| ErrorPayloadSerializable {
+        error: "Invalid input JSON".to_string(),
+        issues: vec![ValidationIssueSerializable {
+            path: format!("{}{}", path, json_pointer(e.path())),
+            message: e.inner().to_string(),
+        }],
+    })
 }
 
-fn parse_dimension(v: &serde_json::Value) -> Option<ProshipDimension> {
-    let obj = v.as_object()?;
-    Some(ProshipDimension {
-        length: obj.get("length").and_then(|x| x.as_f64()).unwrap_or(0.0),
-        width: obj.get("width").and_then(|x| x.as_f64()).unwrap_or(0.0),
-        height: obj.get("height").and_then(|x| x.as_f64()).unwrap_or(0.0),
-    })
+/// Parses `input_json` without the schema check, see deserialize_at.
+fn parse_json<T: DeserializeOwned>(input_json: &str) -> Result<T, ErrorPayloadSerializable> {
+    let mut deserializer = serde_json::Deserializer::from_str(input_json);
+    let value = deserialize_at(&mut deserializer, "")?;
+    deserializer
+        .end()
+        .map_err(|e| invalid_input(e.to_string()))?;
+    Ok(value)
 }
 
-fn inventory_from_json(v: &serde_json::Value) -> ProshipInventory {
-    let status_num = json_i32(v, "status");
-    let status = EnumProshipType_InventoryStatus::from_i32(status_num);
-    let master_dim = v.get("masterDimension").and_then(parse_dimension);
-    let dim = v.get("dimension").and_then(parse_dimension);
+/// With the `schema` feature the input is checked against the input schema
+/// first, so every problem is reported with its path.
+fn input_from_value<T: DeserializeOwned>(
+    value: serde_json::Value,
+) -> Result<T, ErrorPayloadSerializable> {
+    #[cfg(feature = "schema")]
+    crate::schema::validate_input(&value)?;
+    deserialize_at(value, "")
+}
+
+#[cfg(feature = "schema")]
+fn parse_input<T: DeserializeOwned>(input_json: &str) -> Result<T, ErrorPayloadSerializable> {
+    let value = serde_json::from_str(input_json).map_err(|e| invalid_input(e.to_string()))?;
+    input_from_value(value)
+}
+
+#[cfg(not(feature = "schema"))]
+fn parse_input<T: DeserializeOwned>(input_json: &str) -> Result<T, ErrorPayloadSerializable> {
+    parse_json(input_json)
+}
+
+#[cfg(feature = "schema")]
+fn input_from_js<T: DeserializeOwned>(input: JsValue) -> Result<T, ErrorPayloadSerializable> {
+    let value = serde_wasm_bindgen::from_value(input).map_err(|e| invalid_input(e.to_string()))?;
+    input_from_value(value)
+}
+
+#[cfg(not(feature = "schema"))]
+fn input_from_js<T: DeserializeOwned>(input: JsValue) -> Result<T, ErrorPayloadSerializable> {
+    deserialize_at(serde_wasm_bindgen::Deserializer::from(input), "")
+}
+
+fn panic_with_error(error: &ErrorPayloadSerializable) -> ! {
+    panic!(
+        "{}",
+        serde_json::to_string(error).expect("Failed to serialize error")
+    )
+}
+
+/// Parses the input or panics with the error payload as JSON.
+fn expect_input<T: DeserializeOwned>(input_json: &str) -> T {
+    parse_input(input_json).unwrap_or_else(|e| panic_with_error(&e))
+}
+
+/// Checks each of several inputs against the input schema, reporting paths
+/// below `path`/index.
+#[cfg(feature = "schema")]
+fn validate_inputs(
+    values: &[serde_json::Value],
+    path: &str,
+) -> Result<(), ErrorPayloadSerializable> {
+    let issues: Vec<_> = values
+        .iter()
+        .enumerate()
+        .filter_map(|(i, value)| crate::schema::validate_input(value).err().map(|e| (i, e)))
+        .flat_map(|(i, e)| {
+            e.issues
+                .into_iter()
+                .map(move |issue| ValidationIssueSerializable {
+                    path: format!("{}/{}{}", path, i, issue.path),
+                    message: issue.message,
+                })
+        })
+        .collect();
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(ErrorPayloadSerializable {
+            error: "Invalid input JSON".to_string(),
+            issues,
+        })
+    }
+}
+
+#[cfg(not(feature = "schema"))]
+fn validate_inputs(
+    _values: &[serde_json::Value],
+    _path: &str,
+) -> Result<(), ErrorPayloadSerializable> {
+    Ok(())
+}
+
+/// Several inputs at `path` of `value`; see validate_inputs.
+fn inputs_from_values(
+    values: Vec<serde_json::Value>,
+    path: &str,
+) -> Result<Vec<HandleInventoryListInputSerializable>, ErrorPayloadSerializable> {
+    validate_inputs(&values, path)?;
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| deserialize_at(value, &format!("{}/{}", path, i)))
+        .collect()
+}
+
+fn error_to_js(error: &ErrorPayloadSerializable) -> JsValue {
+    error
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap_or_else(|e| e.into())
+}
+
+/// Epoch seconds from a JSON number or an ISO-8601 / numeric string, within
+/// the years 1 to 9999.
+fn timestamp_from_value(v: &serde_json::Value) -> Option<i64> {
+    match v {
+        serde_json::Value::Number(n) => n
+            .as_i64()
+            .or_else(|| n.as_f64().map(|f| f as i64))
+            .filter(|&ts| is_valid_timestamp(ts)),
+        serde_json::Value::String(s) => parse_timestamp(s),
+        _ => None,
+    }
+}
+
+fn invalid_timestamp<E: serde::de::Error>(v: &serde_json::Value) -> E {
+    E::custom(format!(
+        "invalid timestamp: {} (expected epoch seconds or an ISO-8601 date in the years 1 to 9999)",
+        v
+    ))
+}
+
+fn de_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    let v = serde_json::Value::deserialize(deserializer)?;
+    timestamp_from_value(&v).ok_or_else(|| invalid_timestamp(&v))
+}
+
+fn de_opt_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    let v = serde_json::Value::deserialize(deserializer)?;
+    if v.is_null() {
+        return Ok(None);
+    }
+    timestamp_from_value(&v)
+        .map(Some)
+        .ok_or_else(|| invalid_timestamp(&v))
+}
+
+/// Optional name read with `from_name`; unknown names are rejected.
+fn de_opt_name<'de, D: Deserializer<'de>, T>(
+    deserializer: D,
+    kind: &str,
+    from_name: fn(&str) -> Option<T>,
+) -> Result<Option<T>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(name) => from_name(&name)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown {}: {:?}", kind, name))),
+        None => Ok(None),
+    }
+}
+
+fn de_opt_numeric_mode<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NumericMode>, D::Error> {
+    de_opt_name(deserializer, "numericMode", NumericMode::from_name)
+}
+
+fn de_opt_rounding_mode<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<RoundingMode>, D::Error> {
+    de_opt_name(deserializer, "roundingMode", RoundingMode::from_name)
+}
+
+fn dimension_from_item(v: &DimensionItem) -> ProshipDimension {
+    ProshipDimension {
+        length: v.length.unwrap_or_default(),
+        width: v.width.unwrap_or_default(),
+        height: v.height.unwrap_or_default(),
+    }
+}
+
+fn inventory_from_item(v: &InventoryItem) -> ProshipInventory {
+    let unit_name = v.dimension_unit.as_deref().unwrap_or_default().trim();
+    let dimension_unit = DimensionUnit::from_name(unit_name);
     ProshipInventory {
-        id: json_str(v, "id"),
-        creator_id: json_i64(v, "creatorId"),
-        created_at: json_i32(v, "createdAt"),
-        updated_at: json_i32(v, "updatedAt"),
-        status,
-        shelf_code: json_str(v, "shelfCode"),
-        customer_id: json_i64(v, "customerId"),
-        stock_qty: json_i32(v, "stockQty"),
-        stock_cbm: json_f32(v, "stockCbm"),
-        goods_receipt_id: json_str(v, "goodsReceiptId"),
-        goods_issue_id: json_str(v, "goodsIssueId"),
-        goods_id: json_str(v, "goodsId"),
-        duration: json_i32(v, "duration"),
-        export_at: json_i32(v, "exportAt"),
-        asin: json_str(v, "asin"),
-        supplier_id: json_str(v, "supplierId"),
-        asin_outbound: json_str(v, "asinOutbound"),
-        index_customs_declaration: json_str(v, "indexCustomsDeclaration"),
-        unit_price: json_f32(v, "unitPrice"),
-        inner_qty_on_mas: json_i32(v, "innerQtyOnMas").max(1),
-        po_no: json_str(v, "poNo"),
-        master_dimension: master_dim,
-        dimension: dim,
-        volume: json_f32(v, "volume"),
-        master_volume: json_f32(v, "masterVolume"),
-        master_qty: json_i32(v, "masterQty"),
-        do_no: json_str(v, "doNo"),
-    }
-}
-
-fn goods_receipt_from_json(v: &serde_json::Value) -> ProshipGoodsReceipt {
+        id: v.id.clone().unwrap_or_default(),
+        creator_id: v.creator_id.unwrap_or_default(),
+        created_at: v.created_at.unwrap_or_default(),
+        updated_at: v.updated_at.unwrap_or_default(),
+        status: EnumProshipType_InventoryStatus::from_i32(v.status.unwrap_or_default()),
+        shelf_code: v.shelf_code.clone().unwrap_or_default(),
+        customer_id: v.customer_id.unwrap_or_default(),
+        stock_qty: v.stock_qty.unwrap_or_default(),
+        stock_cbm: v.stock_cbm.unwrap_or_default(),
+        goods_receipt_id: v.goods_receipt_id.clone().unwrap_or_default(),
+        goods_issue_id: v.goods_issue_id.clone().unwrap_or_default(),
+        goods_id: v.goods_id.clone().unwrap_or_default(),
+        duration: v.duration.unwrap_or_default(),
+        export_at: v.export_at.unwrap_or_default(),
+        asin: v.asin.clone().unwrap_or_default(),
+        supplier_id: v.supplier_id.clone().unwrap_or_default(),
+        asin_outbound: v.asin_outbound.clone().unwrap_or_default(),
+        index_customs_declaration: v.index_customs_declaration.clone().unwrap_or_default(),
+        unit_price: v.unit_price.unwrap_or_default(),
+        inner_qty_on_mas: v.inner_qty_on_mas.unwrap_or_default().max(1),
+        po_no: v.po_no.clone().unwrap_or_default(),
+        master_dimension: v.master_dimension.as_ref().map(dimension_from_item),
+        dimension: v.dimension.as_ref().map(dimension_from_item),
+        volume: v.volume.unwrap_or_default(),
+        master_volume: v.master_volume.unwrap_or_default(),
+        master_qty: v.master_qty.unwrap_or_default(),
+        do_no: v.do_no.clone().unwrap_or_default(),
+        dimension_unit,
+        unknown_dimension_unit: match dimension_unit {
+            Some(_) => String::new(),
+            None => unit_name.to_string(),
+        },
+        cartons_per_pallet: v.cartons_per_pallet.unwrap_or_default(),
+        pallet_footprint: v.pallet_footprint.unwrap_or_default(),
+        currency: v.currency.clone().unwrap_or_default(),
+    }
+}
+
+fn goods_receipt_from_item(v: &GoodsReceiptItem) -> ProshipGoodsReceipt {
     ProshipGoodsReceipt {
-        imported_at: json_i32(v, "importedAt"),
-    }
-}
-
-fn inventory_data_to_json(d: &InventoryData) -> serde_json::Value {
-    serde_json::json!({
-        "openingStock": d.opening_stock,
-        "openingCbm": d.opening_cbm,
-        "openingMasterQty": d.opening_master_qty,
-        "asin": d.asin,
-        "asinOutbound": d.asin_outbound,
-        "unitPrice": d.unit_price,
-        "receivedDate": d.received_date,
-        "innerQtyOnMas": d.inner_qty_on_mas,
-        "date": d.date,
-        "lineInCd": d.line_in_cd,
-        "poNo": d.po_no,
-        "masterDimension": {"length": d.master_dimension.length, "width": d.master_dimension.width, "height": d.master_dimension.height},
-        "dimension": {"length": d.dimension.length, "width": d.dimension.width, "height": d.dimension.height},
-        "inboundQty": d.inbound_qty,
-        "inboundCbm": d.inbound_cbm,
-        "inboundMasterQty": d.inbound_master_qty,
-        "closingStock": d.closing_stock,
-        "closingCbm": d.closing_cbm,
-        "closingMasterQty": d.closing_master_qty,
-        "allocatedQty": d.allocated_qty,
-        "allocatedCbm": d.allocated_cbm,
-        "allocatedMasterQty": d.allocated_master_qty,
-        "disposalStock": d.disposal_stock,
-        "disposalCbm": d.disposal_cbm,
-        "disposalMasterQty": d.disposal_master_qty,
-        "restoreStockQty": d.restore_stock_qty,
-        "restoreStockCbm": d.restore_stock_cbm,
-        "restoreMasterQty": d.restore_master_qty,
-        "outboundQty": d.outbound_qty,
-        "outboundCbm": d.outbound_cbm,
-        "outboundMasterQty": d.outbound_master_qty,
-        "storageTimeDays": d.storage_time_days,
-    })
+        imported_at: v.imported_at.unwrap_or_default(),
+    }
 }
 
-#[wasm_bindgen]
-pub fn process_inventory_history_wasm(input_json: &str) -> String {
-    let input: HandleInventoryListInputSerializable =
-        serde_json::from_str(input_json).expect("Invalid input JSON");
+#[derive(Serialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+struct InventoryDataSerializable {
+    opening_stock: i32,
+    opening_cbm: f64,
+    opening_master_qty: i32,
+    opening_pallet_qty: i32,
+    asin: String,
+    asin_outbound: Vec<String>,
+    unit_price: f64,
+    received_date: i64,
+    inner_qty_on_mas: i32,
+    date: i64,
+    line_in_cd: String,
+    po_no: String,
+    master_dimension: DimensionSerializable,
+    dimension: DimensionSerializable,
+    inbound_qty: i32,
+    inbound_cbm: f64,
+    inbound_master_qty: i32,
+    inbound_pallet_qty: i32,
+    closing_stock: i32,
+    closing_cbm: f64,
+    closing_master_qty: i32,
+    closing_pallet_qty: i32,
+    allocated_qty: i32,
+    allocated_cbm: f64,
+    allocated_master_qty: i32,
+    disposal_stock: i32,
+    disposal_cbm: f64,
+    disposal_master_qty: i32,
+    restore_stock_qty: i32,
+    restore_stock_cbm: f64,
+    restore_master_qty: i32,
+    outbound_qty: i32,
+    outbound_cbm: f64,
+    outbound_master_qty: i32,
+    outbound_pallet_qty: i32,
+    pallet_footprint: f64,
+    storage_time_days: i32,
+    currency: String,
+    opening_value: f64,
+    inbound_value: f64,
+    outbound_value: f64,
+    disposal_value: f64,
+    closing_value: f64,
+    closing_balances: StatusBalancesSerializable,
+}
 
-    let inventory = inventory_from_json(&input.inventory);
-    let goods_receipt = goods_receipt_from_json(&input.goods_receipt);
+#[derive(Serialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct DimensionSerializable {
+    length: f64,
+    width: f64,
+    height: f64,
+}
 
-    let inventory_history_list: Vec<ProshipInventoryHistory> = input
-        .inventory_history_list
+impl From<&ProshipDimension> for DimensionSerializable {
+    fn from(d: &ProshipDimension) -> Self {
+        Self {
+            length: d.length,
+            width: d.width,
+            height: d.height,
+        }
+    }
+}
+
+/// Decimal mode emits the exact rounded decimal; float mode the widened f32
+/// the value was computed as.
+fn json_num(v: Decimal, exact: bool) -> f64 {
+    if exact {
+        v.to_f64()
+    } else {
+        v.to_f32() as f64
+    }
+}
+
+fn inventory_data_to_serializable(d: &InventoryData, exact: bool) -> InventoryDataSerializable {
+    InventoryDataSerializable {
+        opening_stock: d.opening_stock,
+        opening_cbm: json_num(d.get_opening_cbm_decimal(), exact),
+        opening_master_qty: d.opening_master_qty,
+        opening_pallet_qty: d.opening_pallet_qty,
+        asin: d.asin.clone(),
+        asin_outbound: d.asin_outbound.clone(),
+        unit_price: json_num(d.get_unit_price_decimal(), exact),
+        received_date: d.received_date,
+        inner_qty_on_mas: d.inner_qty_on_mas,
+        date: d.date,
+        line_in_cd: d.line_in_cd.clone(),
+        po_no: d.po_no.clone(),
+        master_dimension: (&d.master_dimension).into(),
+        dimension: (&d.dimension).into(),
+        inbound_qty: d.inbound_qty,
+        inbound_cbm: json_num(d.get_inbound_cbm_decimal(), exact),
+        inbound_master_qty: d.inbound_master_qty,
+        inbound_pallet_qty: d.inbound_pallet_qty,
+        closing_stock: d.closing_stock,
+        closing_cbm: json_num(d.get_closing_cbm_decimal(), exact),
+        closing_master_qty: d.closing_master_qty,
+        closing_pallet_qty: d.closing_pallet_qty,
+        allocated_qty: d.allocated_qty,
+        allocated_cbm: json_num(d.get_allocated_cbm_decimal(), exact),
+        allocated_master_qty: d.allocated_master_qty,
+        disposal_stock: d.disposal_stock,
+        disposal_cbm: json_num(d.get_disposal_cbm_decimal(), exact),
+        disposal_master_qty: d.disposal_master_qty,
+        restore_stock_qty: d.restore_stock_qty,
+        restore_stock_cbm: json_num(d.get_restore_stock_cbm_decimal(), exact),
+        restore_master_qty: d.restore_master_qty,
+        outbound_qty: d.outbound_qty,
+        outbound_cbm: json_num(d.get_outbound_cbm_decimal(), exact),
+        outbound_master_qty: d.outbound_master_qty,
+        outbound_pallet_qty: d.outbound_pallet_qty,
+        pallet_footprint: json_num(Decimal::from_f32(d.pallet_footprint), exact),
+        storage_time_days: d.storage_time_days,
+        currency: d.currency.clone(),
+        opening_value: d.opening_value.to_f64(),
+        inbound_value: d.inbound_value.to_f64(),
+        outbound_value: d.outbound_value.to_f64(),
+        disposal_value: d.disposal_value.to_f64(),
+        closing_value: d.closing_value.to_f64(),
+        closing_balances: d.closing_balances.into(),
+    }
+}
+
+fn history_from_items(items: Vec<InventoryHistoryItem>) -> Vec<ProshipInventoryHistory> {
+    items
         .into_iter()
         .map(|h| ProshipInventoryHistory {
             created_at: h.created_at,
@@ -183,7 +731,95 @@ pub fn process_inventory_history_wasm(input_json: &str) -> String {
             quantity: h.quantity,
             goods_issue_id: h.goods_issue_id.unwrap_or_default(),
         })
-        .collect();
+        .collect()
+}
+
+fn options_from_input(input: &HandleInventoryListInputSerializable) -> ProcessOptions {
+    let numeric = NumericConfig {
+        mode: input.numeric_mode.unwrap_or_default(),
+        cbm_precision: input
+            .precision
+            .unwrap_or(NumericConfig::default().cbm_precision),
+        price_precision: input.price_precision,
+        rounding: input.rounding_mode.unwrap_or_default(),
+    };
+    ProcessOptions {
+        volume_tolerance: input
+            .volume_tolerance
+            .unwrap_or(ProcessOptions::default().volume_tolerance),
+        numeric,
+        valuation: ValuationOptions::new(
+            input.currency.clone(),
+            input
+                .fx_rates
+                .iter()
+                .map(|r| FxRate {
+                    currency: r.currency.clone(),
+                    rate: r.rate,
+                    effective_at: r.effective_at,
+                })
+                .collect(),
+            input
+                .value_precision
+                .unwrap_or(ValuationOptions::default().value_precision),
+        ),
+        ..ProcessOptions::default()
+    }
+}
+
+fn result_from_output(
+    output: ProcessInventoryHistoryOutput,
+    exact: bool,
+) -> ProcessedInventoryHistoryResultSerializable {
+    let mut entries: HashMap<String, ProcessedEntry> = HashMap::new();
+    for (k, (data, goods_issue_ids)) in output.merged_inventory_history {
+        entries.insert(
+            k,
+            ProcessedEntry {
+                data: inventory_data_to_serializable(&data, exact),
+                goods_issue_ids: goods_issue_ids.into_iter().collect::<Vec<String>>(),
+            },
+        );
+    }
+
+    ProcessedInventoryHistoryResultSerializable {
+        entries,
+        total_duration: output.total_duration,
+        warnings: output.warnings,
+        goods_issue_ledger: output
+            .goods_issue_ledger
+            .into_iter()
+            .map(|(id, l)| {
+                (
+                    id,
+                    GoodsIssueLedgerSerializable {
+                        allocated_qty: l.allocated_qty,
+                        released_qty: l.released_qty,
+                        disposed_qty: l.disposed_qty,
+                        shipped_qty: l.shipped_qty,
+                        open_qty: l.get_open_qty(),
+                        allocated_at: l.allocated_at,
+                        released_at: l.released_at,
+                        disposed_at: l.disposed_at,
+                        shipped_at: l.shipped_at,
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Processes `input`; `feed` pushes any further events after its list.
+fn process_input(
+    input: HandleInventoryListInputSerializable,
+    feed: impl FnOnce(&mut InventoryHistoryProcessor<'_>),
+) -> ProcessedInventoryHistoryResultSerializable {
+    let inventory = inventory_from_item(&input.inventory);
+    let goods_receipt = goods_receipt_from_item(&input.goods_receipt);
+
+    let options = options_from_input(&input);
+    let exact = options.numeric.mode == NumericMode::Decimal;
+    let inventory_history_list = history_from_items(input.inventory_history_list);
 
     let process_input = ProcessInventoryHistoryInput {
         inventory: &inventory,
@@ -194,26 +830,1427 @@ pub fn process_inventory_history_wasm(input_json: &str) -> String {
         inventory_ids: input.inventory_ids_map,
         from_date: input.from_date,
         to_date: input.to_date,
+        options,
     };
 
-    let output: ProcessInventoryHistoryOutput =
-        crate::process_inventory_list::process_inventory_history_data(process_input);
+    let mut processor = InventoryHistoryProcessor::new(process_input);
+    feed(&mut processor);
 
-    let mut entries: HashMap<String, ProcessedEntry> = HashMap::new();
-    for (k, (data, goods_issue_ids)) in output.merged_inventory_history {
-        entries.insert(
-            k,
-            ProcessedEntry {
-                data: inventory_data_to_json(&data),
-                goods_issue_ids: goods_issue_ids.into_iter().collect::<Vec<String>>(),
+    result_from_output(processor.finish(), exact)
+}
+
+/// Input of the JSON contract parsed for use from Rust, e.g. by the
+/// command-line tool.
+pub struct JsonInput {
+    input: HandleInventoryListInputSerializable,
+    /// Replaces `input.inventory`, see set_inventory.
+    inventory: Option<ProshipInventory>,
+}
+
+impl JsonInput {
+    fn new(input: HandleInventoryListInputSerializable) -> Self {
+        Self {
+            input,
+            inventory: None,
+        }
+    }
+
+    pub fn parse(input_json: &str) -> Result<Self, ErrorPayloadSerializable> {
+        parse_input(input_json).map(Self::new)
+    }
+
+    /// Parses a JSON array of inputs, or a single input as a batch of one.
+    pub fn parse_batch(input_json: &str) -> Result<Vec<Self>, ErrorPayloadSerializable> {
+        let value: serde_json::Value =
+            serde_json::from_str(input_json).map_err(|e| invalid_input(e.to_string()))?;
+        match value {
+            serde_json::Value::Array(values) => Ok(inputs_from_values(values, "")?
+                .into_iter()
+                .map(Self::new)
+                .collect()),
+            value => Ok(vec![Self::new(input_from_value(value)?)]),
+        }
+    }
+
+    pub fn inventory_id(&self) -> &str {
+        &self.input.inventory_id
+    }
+
+    /// Replaces the `inventory` of the input, e.g. with a row read by
+    /// inventories_from_table.
+    pub fn set_inventory(&mut self, inventory: ProshipInventory) {
+        self.inventory = Some(inventory);
+    }
+
+    fn into_parts(self) -> (HandleInventoryListInputSerializable, ProshipInventory) {
+        let inventory = self
+            .inventory
+            .unwrap_or_else(|| inventory_from_item(&self.input.inventory));
+        (self.input, inventory)
+    }
+
+    /// Runs the input like process_inventory_history_wasm; `configure` may
+    /// adjust the dates and options first.
+    pub fn process(
+        self,
+        configure: impl FnOnce(&mut ProcessInventoryHistoryInput<'_>),
+    ) -> JsonOutput {
+        let (input, inventory) = self.into_parts();
+        let goods_receipt = goods_receipt_from_item(&input.goods_receipt);
+        let options = options_from_input(&input);
+
+        let mut process_input = ProcessInventoryHistoryInput {
+            inventory: &inventory,
+            inventory_id: &input.inventory_id,
+            asin_outbound_list: &input.asin_outbound_list,
+            goods_receipt: &goods_receipt,
+            inventory_history_list: history_from_items(input.inventory_history_list),
+            inventory_ids: input.inventory_ids_map,
+            from_date: input.from_date,
+            to_date: input.to_date,
+            options,
+        };
+        configure(&mut process_input);
+        let numeric = process_input.options.numeric;
+        let value_precision = process_input.options.valuation.value_precision;
+
+        JsonOutput {
+            output: process_inventory_history_iter(process_input, std::iter::empty()),
+            exact: numeric.mode == NumericMode::Decimal,
+            numeric,
+            value_precision,
+        }
+    }
+}
+
+/// Replaces the inventory of each input whose `inventoryId` is the `id` of one
+/// of `inventories`, e.g. read by inventories_from_table; inputs without one
+/// keep their own. Fails on an inventory that matches no input.
+pub fn apply_inventories(
+    inputs: &mut [JsonInput],
+    inventories: Vec<ProshipInventory>,
+) -> Result<(), String> {
+    for inventory in inventories {
+        let input = inputs
+            .iter_mut()
+            .find(|input| input.inventory_id() == inventory.id)
+            .ok_or_else(|| format!("inventory {:?} matches no input", inventory.id))?;
+        input.set_inventory(inventory);
+    }
+    Ok(())
+}
+
+/// Output of JsonInput::process.
+pub struct JsonOutput {
+    pub output: ProcessInventoryHistoryOutput,
+    /// Decimal mode: numbers are the exact rounded decimals, not widened f32.
+    pub exact: bool,
+    /// Numeric settings the input was processed with.
+    pub numeric: NumericConfig,
+    /// Decimal places of the value columns.
+    pub value_precision: u32,
+}
+
+impl JsonOutput {
+    /// The process_inventory_history_wasm result object.
+    pub fn into_json(self) -> serde_json::Value {
+        serde_json::to_value(result_from_output(self.output, self.exact))
+            .expect("Failed to serialize result")
+    }
+
+    /// The daily entries as flat objects sorted by key: `key`, the fields of
+    /// `data` and `goodsIssueIds`.
+    pub fn into_rows(self) -> Vec<serde_json::Value> {
+        let mut result = self.into_json();
+        let serde_json::Value::Object(mut entries) = result["entries"].take() else {
+            return Vec::new();
+        };
+        let mut keys: Vec<String> = entries.keys().cloned().collect();
+        keys.sort();
+        keys.into_iter()
+            .map(|key| {
+                let mut entry = entries.remove(&key).unwrap_or_default();
+                let mut row = serde_json::Map::new();
+                row.insert("key".to_string(), serde_json::Value::from(key));
+                if let serde_json::Value::Object(data) = entry["data"].take() {
+                    row.extend(data);
+                }
+                row.insert("goodsIssueIds".to_string(), entry["goodsIssueIds"].take());
+                serde_json::Value::Object(row)
+            })
+            .collect()
+    }
+}
+
+#[wasm_bindgen]
+pub fn process_inventory_history_wasm(input_json: &str) -> String {
+    try_process_inventory_history_json(input_json).unwrap_or_else(|e| panic_with_error(&e))
+}
+
+/// process_inventory_history_wasm returning invalid input as the error payload
+/// instead of panicking, for the C API.
+pub(crate) fn try_process_inventory_history_json(
+    input_json: &str,
+) -> Result<String, ErrorPayloadSerializable> {
+    let input: HandleInventoryListInputSerializable = parse_input(input_json)?;
+
+    let result = process_input(input, |_| {});
+
+    Ok(serde_json::to_string(&result).expect("Failed to serialize result"))
+}
+
+/// Same as process_inventory_history_wasm, but takes and returns plain JS
+/// objects instead of JSON strings. Invalid input throws instead of aborting.
+#[wasm_bindgen(unchecked_return_type = "ProcessedInventoryHistoryResultSerializable")]
+pub fn process_inventory_history_object(
+    #[wasm_bindgen(unchecked_param_type = "HandleInventoryListInputSerializable")] input: JsValue,
+) -> Result<JsValue, JsValue> {
+    let input: HandleInventoryListInputSerializable =
+        input_from_js(input).map_err(|e| error_to_js(&e))?;
+
+    let result = process_input(input, |_| {});
+
+    Ok(result.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Like process_inventory_history_wasm, with the history events as typed arrays
+/// (`createdAt` a Float64Array of epoch seconds, the rest Int32Array) applied
+/// after any `inventoryHistoryList` in `input_json`. `goodsIssueIndex` points
+/// into `goodsIssueIds` (-1 for none) and may be empty. Invalid input or
+/// columns throw the error payload as JSON, with column paths such as
+/// `/stockQty` or `/createdAt/3` naming the arguments.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn process_inventory_history_columnar(
+    input_json: &str,
+    created_at: &[f64],
+    stock_qty: &[i32],
+    old_status: &[i32],
+    new_status: &[i32],
+    quantity: &[i32],
+    goods_issue_index: &[i32],
+    goods_issue_ids: Vec<String>,
+) -> Result<String, String> {
+    let error_json =
+        |e: ErrorPayloadSerializable| serde_json::to_string(&e).expect("Failed to serialize error");
+    let input: HandleInventoryListInputSerializable =
+        parse_input(input_json).map_err(error_json)?;
+
+    // The cast below would saturate NaN and infinities and drop fractions.
+    if let Some(i) = created_at
+        .iter()
+        .position(|t| !t.is_finite() || t.fract() != 0.0)
+    {
+        return Err(error_json(columns_error(&HistoryColumnsError {
+            column: "created_at",
+            index: Some(i),
+            message: format!("{} is not a whole number of seconds", created_at[i]),
+        })));
+    }
+    let created_at: Vec<i64> = created_at.iter().map(|&t| t as i64).collect();
+    let columns = HistoryColumns {
+        created_at: &created_at,
+        stock_qty,
+        old_status,
+        new_status,
+        quantity,
+        goods_issue_index,
+        goods_issue_ids: &goods_issue_ids,
+    };
+    columns
+        .validate()
+        .map_err(|e| error_json(columns_error(&e)))?;
+
+    let result = process_input(input, |processor| {
+        columns.for_each(|event| processor.extend([event]))
+    });
+
+    Ok(serde_json::to_string(&result).expect("Failed to serialize result"))
+}
+
+/// Maps a column problem to the error payload, naming the column by its JS
+/// argument.
+fn columns_error(e: &HistoryColumnsError) -> ErrorPayloadSerializable {
+    let column = match e.column {
+        "created_at" => "createdAt",
+        "stock_qty" => "stockQty",
+        "old_status" => "oldStatus",
+        "new_status" => "newStatus",
+        "goods_issue_index" => "goodsIssueIndex",
+        other => other,
+    };
+    let path = match e.index {
+        Some(i) => format!("/{}/{}", column, i),
+        None => format!("/{}", column),
+    };
+    ErrorPayloadSerializable {
+        error: "Invalid history columns".to_string(),
+        issues: vec![ValidationIssueSerializable {
+            path,
+            message: e.message.clone(),
+        }],
+    }
+}
+
+#[derive(Deserialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct AgingReportInputSerializable {
+    /// Lots aged together, each from its own goods receipt date.
+    #[tsify(type = "HandleInventoryListInputSerializable[]")]
+    inventories: Vec<serde_json::Value>,
+    #[serde(default, deserialize_with = "de_opt_timestamp")]
+    #[tsify(type = "number | string | null", optional)]
+    as_of: Option<i64>,
+    #[serde(default, deserialize_with = "de_opt_timestamp")]
+    #[tsify(type = "number | string | null", optional)]
+    from_date: Option<i64>,
+    #[serde(default, deserialize_with = "de_opt_timestamp")]
+    #[tsify(type = "number | string | null", optional)]
+    to_date: Option<i64>,
+    #[tsify(optional)]
+    aging_buckets: Option<Vec<AgingBucketItem>>,
+}
+
+#[derive(Deserialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct AgingBucketItem {
+    label: String,
+    min_days: i32,
+    #[tsify(optional)]
+    max_days: Option<i32>,
+}
+
+#[derive(Serialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct AgingReportSerializable {
+    as_of: i64,
+    date: String,
+    buckets: Vec<AgingBucketTotalSerializable>,
+    total_qty: i32,
+    total_cbm: f64,
+}
+
+#[derive(Serialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct AgingBucketTotalSerializable {
+    label: String,
+    quantity: i32,
+    cbm: f64,
+}
+
+/// Aging buckets of `inventories` at `asOf`, or at the end of each day from
+/// `fromDate` (default: the earliest receipt date) to `toDate` (or now) when
+/// `asOf` is absent, for at most MAX_AGING_REPORT_DAYS days. Options come from
+/// the first lot.
+#[wasm_bindgen]
+pub fn aging_report_wasm(input_json: &str) -> String {
+    let parse = || -> Result<_, ErrorPayloadSerializable> {
+        let input: AgingReportInputSerializable = parse_json(input_json)?;
+        let lots = inputs_from_values(input.inventories, "/inventories")?;
+        Ok((
+            lots,
+            input.as_of,
+            input.from_date,
+            input.to_date,
+            input.aging_buckets,
+        ))
+    };
+    let (inputs, as_of, from_date, to_date, aging_buckets) =
+        parse().unwrap_or_else(|e| panic_with_error(&e));
+
+    let options = inputs.first().map(options_from_input).unwrap_or_default();
+    let exact = options.numeric.mode == NumericMode::Decimal;
+    let buckets = aging_buckets.map_or_else(default_aging_buckets, |items| {
+        items
+            .into_iter()
+            .map(|b| AgingBucket {
+                label: b.label,
+                min_days: b.min_days,
+                max_days: b.max_days,
+            })
+            .collect()
+    });
+    let owned: Vec<_> = inputs
+        .into_iter()
+        .map(|input| {
+            (
+                inventory_from_item(&input.inventory),
+                goods_receipt_from_item(&input.goods_receipt),
+                history_from_items(input.inventory_history_list),
+            )
+        })
+        .collect();
+    let lots: Vec<AgingLot<'_>> = owned
+        .iter()
+        .map(|(inventory, goods_receipt, history)| AgingLot {
+            inventory,
+            goods_receipt,
+            inventory_history_list: history,
+        })
+        .collect();
+
+    let reports = match as_of {
+        Some(as_of) => vec![aging_report(&lots, &buckets, as_of, &options)],
+        None => {
+            // A missing receipt date (0) would otherwise start the daily
+            // reports in 1970.
+            let first_receipt = lots
+                .iter()
+                .map(|lot| lot.goods_receipt.get_imported_at())
+                .filter(|&imported_at| imported_at > 0)
+                .min();
+            let from_date = match (from_date, first_receipt) {
+                (Some(from_date), Some(first_receipt)) => from_date.max(first_receipt),
+                (Some(date), None) | (None, Some(date)) => date,
+                (None, None) => panic_with_error(&ErrorPayloadSerializable {
+                    error: "Invalid input JSON".to_string(),
+                    issues: vec![ValidationIssueSerializable {
+                        path: "/fromDate".to_string(),
+                        message:
+                            "required with asOf absent when no lot has goodsReceipt.importedAt"
+                                .to_string(),
+                    }],
+                }),
+            };
+            daily_aging_report(
+                &lots,
+                &buckets,
+                from_date,
+                to_date.unwrap_or_else(now_to_epoch),
+                &options,
+            )
+            .unwrap_or_else(|e| {
+                panic_with_error(&ErrorPayloadSerializable {
+                    error: "Invalid input JSON".to_string(),
+                    issues: vec![ValidationIssueSerializable {
+                        path: "/fromDate".to_string(),
+                        message: format!("{}; narrow fromDate/toDate or pass asOf", e),
+                    }],
+                })
+            })
+        }
+    };
+
+    let result: Vec<AgingReportSerializable> = reports
+        .into_iter()
+        .map(|r| AgingReportSerializable {
+            as_of: r.as_of,
+            date: r.date,
+            buckets: r
+                .buckets
+                .into_iter()
+                .map(|b| AgingBucketTotalSerializable {
+                    label: b.label,
+                    quantity: b.quantity,
+                    cbm: json_num(b.cbm, exact),
+                })
+                .collect(),
+            total_qty: r.total_qty,
+            total_cbm: json_num(r.total_cbm, exact),
+        })
+        .collect();
+
+    serde_json::to_string(&result).expect("Failed to serialize result")
+}
+
+#[derive(Deserialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct CostingInputSerializable {
+    /// Lots of the same goods, each costed from its own receipt and history.
+    #[tsify(type = "HandleInventoryListInputSerializable[]")]
+    inventories: Vec<serde_json::Value>,
+    #[serde(default, deserialize_with = "de_opt_costing_method")]
+    #[tsify(type = "'fifo' | 'lifo' | 'weightedAverage'", optional)]
+    method: Option<CostingMethod>,
+}
+
+#[derive(Serialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct CostingReportSerializable {
+    issues: Vec<CostedIssueSerializable>,
+    restores: Vec<CostedIssueSerializable>,
+    goods_issue_costs: HashMap<String, GoodsIssueCostSerializable>,
+    remaining_layers: Vec<CostLayerSerializable>,
+    cost_of_goods_issued: f64,
+    disposal_cost: f64,
+    remaining_value: f64,
+}
+
+#[derive(Serialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct CostedIssueSerializable {
+    inventory_id: String,
+    goods_issue_id: String,
+    created_at: i64,
+    old_status: i32,
+    new_status: i32,
+    quantity: i32,
+    cost: f64,
+    uncosted_qty: i32,
+    layers: Vec<CostLayerConsumptionSerializable>,
+}
+
+#[derive(Serialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct CostLayerConsumptionSerializable {
+    inventory_id: String,
+    received_at: i64,
+    quantity: i32,
+    unit_cost: f64,
+    cost: f64,
+}
+
+#[derive(Serialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct CostLayerSerializable {
+    inventory_id: String,
+    received_at: i64,
+    unit_cost: f64,
+    remaining_qty: i32,
+}
+
+#[derive(Serialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct GoodsIssueCostSerializable {
+    quantity: i32,
+    cost: f64,
+}
+
+fn de_opt_costing_method<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<CostingMethod>, D::Error> {
+    de_opt_name(deserializer, "method", CostingMethod::from_name)
+}
+
+impl From<CostedIssue> for CostedIssueSerializable {
+    fn from(i: CostedIssue) -> Self {
+        Self {
+            inventory_id: i.inventory_id,
+            goods_issue_id: i.goods_issue_id,
+            created_at: i.created_at,
+            old_status: i.old_status as i32,
+            new_status: i.new_status as i32,
+            quantity: i.quantity,
+            cost: i.cost.to_f64(),
+            uncosted_qty: i.uncosted_qty,
+            layers: i
+                .layers
+                .into_iter()
+                .map(|l| CostLayerConsumptionSerializable {
+                    inventory_id: l.inventory_id,
+                    received_at: l.received_at,
+                    quantity: l.quantity,
+                    unit_cost: l.unit_cost.to_f64(),
+                    cost: l.cost.to_f64(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Issue costs across `inputs`, lots of the same goods, as the
+/// costing_report_wasm result. `configure` may adjust each input first, as in
+/// JsonInput::process; events after `options.as_of` are left out. Numeric and
+/// value precision options come from the first input.
+pub fn costing_report_json(
+    inputs: Vec<JsonInput>,
+    method: CostingMethod,
+    mut configure: impl FnMut(&mut ProcessInventoryHistoryInput<'_>),
+) -> serde_json::Value {
+    let mut options: Option<ProcessOptions> = None;
+    let lots: Vec<_> = inputs
+        .into_iter()
+        .map(|input| {
+            let (input, inventory) = input.into_parts();
+            let goods_receipt = goods_receipt_from_item(&input.goods_receipt);
+            let input_options = options_from_input(&input);
+            let mut process_input = ProcessInventoryHistoryInput {
+                inventory: &inventory,
+                inventory_id: &input.inventory_id,
+                asin_outbound_list: &input.asin_outbound_list,
+                goods_receipt: &goods_receipt,
+                inventory_history_list: history_from_items(input.inventory_history_list),
+                inventory_ids: input.inventory_ids_map,
+                from_date: input.from_date,
+                to_date: input.to_date,
+                options: input_options,
+            };
+            configure(&mut process_input);
+            let received_qty = process_input
+                .inventory_ids
+                .get(process_input.inventory_id)
+                .copied()
+                .unwrap_or_default();
+            let as_of = process_input.options.as_of;
+            let mut history = process_input.inventory_history_list;
+            if let Some(as_of) = as_of {
+                history.retain(|h| h.get_created_at() <= as_of);
+            }
+            options.get_or_insert(process_input.options);
+            (inventory, goods_receipt, received_qty, history)
+        })
+        .collect();
+    let options = options.unwrap_or_default();
+    let costing_lots: Vec<CostingLot<'_>> = lots
+        .iter()
+        .map(
+            |(inventory, goods_receipt, received_qty, history)| CostingLot {
+                inventory,
+                goods_receipt,
+                received_qty: *received_qty,
+                inventory_history_list: history,
             },
-        );
+        )
+        .collect();
+
+    let output = calculate_issue_costs(
+        &costing_lots,
+        method,
+        &options.numeric,
+        options.valuation.value_precision,
+    );
+
+    let result = CostingReportSerializable {
+        issues: output.issues.into_iter().map(Into::into).collect(),
+        restores: output.restores.into_iter().map(Into::into).collect(),
+        goods_issue_costs: output
+            .goods_issue_costs
+            .into_iter()
+            .map(|(id, c)| {
+                (
+                    id,
+                    GoodsIssueCostSerializable {
+                        quantity: c.quantity,
+                        cost: c.cost.to_f64(),
+                    },
+                )
+            })
+            .collect(),
+        remaining_layers: output
+            .remaining_layers
+            .into_iter()
+            .map(|l| CostLayerSerializable {
+                inventory_id: l.inventory_id,
+                received_at: l.received_at,
+                unit_cost: l.unit_cost.to_f64(),
+                remaining_qty: l.remaining_qty,
+            })
+            .collect(),
+        cost_of_goods_issued: output.cost_of_goods_issued.to_f64(),
+        disposal_cost: output.disposal_cost.to_f64(),
+        remaining_value: output.remaining_value.to_f64(),
+    };
+    serde_json::to_value(result).expect("Failed to serialize result")
+}
+
+/// Cost of each shipment and disposal of `inventories`, lots of the same
+/// goods, by `method` (FIFO by default); disposed stock moved back to
+/// available returns to the layers it came from.
+#[wasm_bindgen]
+pub fn costing_report_wasm(input_json: &str) -> String {
+    let parse = || -> Result<_, ErrorPayloadSerializable> {
+        let CostingInputSerializable {
+            inventories,
+            method,
+        } = parse_json(input_json)?;
+        Ok((inputs_from_values(inventories, "/inventories")?, method))
+    };
+    let (inputs, method) = parse().unwrap_or_else(|e| panic_with_error(&e));
+
+    let inputs = inputs.into_iter().map(JsonInput::new).collect();
+    let result = costing_report_json(inputs, method.unwrap_or_default(), |_| {});
+
+    serde_json::to_string(&result).expect("Failed to serialize result")
+}
+
+/// Same as costing_report_wasm, for the napi addon.
+pub fn costing_report_native(input_json: &str) -> String {
+    costing_report_wasm(input_json)
+}
+
+#[derive(Deserialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotInputSerializable {
+    #[serde(flatten)]
+    input: HandleInventoryListInputSerializable,
+    #[serde(deserialize_with = "de_timestamp")]
+    #[tsify(type = "number | string")]
+    at: i64,
+}
+
+#[derive(Serialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct InventorySnapshotSerializable {
+    at: i64,
+    date: String,
+    stock_qty: i32,
+    stock_cbm: f64,
+    #[serde(flatten)]
+    balances: StatusBalancesSerializable,
+    allocated_by_goods_issue: HashMap<String, i32>,
+}
+
+#[derive(Serialize, Tsify)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+struct StatusBalancesSerializable {
+    available_qty: i32,
+    on_hand_qty: i32,
+    damaged_qty: i32,
+    return_qty: i32,
+    liquidation_qty: i32,
+    exported_qty: i32,
+}
+
+impl From<StatusBalances> for StatusBalancesSerializable {
+    fn from(b: StatusBalances) -> Self {
+        Self {
+            available_qty: b.available_qty,
+            on_hand_qty: b.on_hand_qty,
+            damaged_qty: b.damaged_qty,
+            return_qty: b.return_qty,
+            liquidation_qty: b.liquidation_qty,
+            exported_qty: b.exported_qty,
+        }
     }
+}
 
-    let result = ProcessedInventoryHistoryResultSerializable {
-        entries,
-        total_duration: output.total_duration,
+/// Stock per status as of `at` (epoch seconds or ISO-8601).
+#[wasm_bindgen]
+pub fn snapshot_inventory_wasm(input_json: &str) -> String {
+    let SnapshotInputSerializable { input, at } = expect_input(input_json);
+
+    let inventory = inventory_from_item(&input.inventory);
+    let goods_receipt = goods_receipt_from_item(&input.goods_receipt);
+    let options = options_from_input(&input);
+    let exact = options.numeric.mode == NumericMode::Decimal;
+
+    let snapshot = snapshot_inventory_at(
+        ProcessInventoryHistoryInput {
+            inventory: &inventory,
+            inventory_id: &input.inventory_id,
+            asin_outbound_list: &input.asin_outbound_list,
+            goods_receipt: &goods_receipt,
+            inventory_history_list: history_from_items(input.inventory_history_list),
+            inventory_ids: input.inventory_ids_map,
+            from_date: input.from_date,
+            to_date: input.to_date,
+            options,
+        },
+        at,
+    );
+
+    let result = InventorySnapshotSerializable {
+        at: snapshot.at,
+        date: snapshot.date,
+        stock_qty: snapshot.stock_qty,
+        stock_cbm: json_num(snapshot.stock_cbm, exact),
+        balances: snapshot.balances.into(),
+        allocated_by_goods_issue: snapshot.allocated_by_goods_issue,
+    };
+
+    serde_json::to_string(&result).expect("Failed to serialize result")
+}
+
+#[derive(Deserialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct IncrementalInputSerializable {
+    #[serde(flatten)]
+    input: HandleInventoryListInputSerializable,
+    #[tsify(type = "ProcessorCheckpoint", optional)]
+    checkpoint: Option<ProcessorCheckpoint>,
+}
+
+#[derive(Serialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct IncrementalResultSerializable {
+    #[serde(flatten)]
+    result: ProcessedInventoryHistoryResultSerializable,
+    #[tsify(type = "ProcessorCheckpoint")]
+    checkpoint: ProcessorCheckpoint,
+}
+
+/// The checkpoint is opaque to JS: a named alias instead of its internal
+/// fields, so callers store and pass it back without depending on its shape.
+const PROCESSOR_CHECKPOINT_DECL: &str = r#"/**
+ * Processor state returned by the incremental API; store it unchanged and
+ * pass it back with the next call.
+ */
+export type ProcessorCheckpoint = Record<string, unknown>;"#;
+
+#[wasm_bindgen(typescript_custom_section)]
+const PROCESSOR_CHECKPOINT_TS: &str = PROCESSOR_CHECKPOINT_DECL;
+
+/// Processes only the new `inventoryHistoryList` entries on top of the
+/// `checkpoint` returned by the previous call. The returned `checkpoint` is
+/// opaque and should be stored as-is for the next call; `entries` start at
+/// the day of the previous call's last event.
+#[wasm_bindgen]
+pub fn process_inventory_history_incremental_wasm(input_json: &str) -> String {
+    let IncrementalInputSerializable { input, checkpoint } = expect_input(input_json);
+
+    let inventory = inventory_from_item(&input.inventory);
+    let goods_receipt = goods_receipt_from_item(&input.goods_receipt);
+    let options = options_from_input(&input);
+    let exact = options.numeric.mode == NumericMode::Decimal;
+
+    let (output, checkpoint) = process_inventory_history_incremental(
+        ProcessInventoryHistoryInput {
+            inventory: &inventory,
+            inventory_id: &input.inventory_id,
+            asin_outbound_list: &input.asin_outbound_list,
+            goods_receipt: &goods_receipt,
+            inventory_history_list: history_from_items(input.inventory_history_list),
+            inventory_ids: input.inventory_ids_map,
+            from_date: input.from_date,
+            to_date: input.to_date,
+            options,
+        },
+        checkpoint,
+    );
+
+    let result = IncrementalResultSerializable {
+        result: result_from_output(output, exact),
+        checkpoint,
     };
 
     serde_json::to_string(&result).expect("Failed to serialize result")
 }
+
+/// Same as process_inventory_history_wasm, for the napi addon.
+pub fn process_inventory_history_native(input_json: &str) -> String {
+    process_inventory_history_wasm(input_json)
+}
+
+#[derive(Deserialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct CsvInputSerializable {
+    #[serde(flatten)]
+    input: HandleInventoryListInputSerializable,
+    #[tsify(optional)]
+    csv_options: Option<CsvOptionsItem>,
+}
+
+/// Options of write_inventory_csv; absent fields keep the defaults.
+#[derive(Deserialize, Tsify, Default)]
+#[serde(rename_all = "camelCase")]
+struct CsvOptionsItem {
+    /// Field names of the JSON row, nested ones flattened (`dimensionLength`,
+    /// `availableQty`), in output order.
+    #[tsify(optional)]
+    columns: Option<Vec<String>>,
+    #[tsify(type = "'en' | 'vi'", optional)]
+    language: Option<String>,
+    #[tsify(type = "string", optional)]
+    delimiter: Option<char>,
+    #[tsify(type = "string", optional)]
+    decimal_separator: Option<char>,
+    #[tsify(optional)]
+    precision: Option<usize>,
+    #[tsify(optional)]
+    include_header: Option<bool>,
+    #[tsify(optional)]
+    byte_order_mark: Option<bool>,
+}
+
+/// Columns named in `names`, an error per unknown name at `path`/index.
+fn columns_from_names(
+    names: &[String],
+    path: &str,
+) -> Result<Vec<CsvColumn>, ErrorPayloadSerializable> {
+    let mut columns = Vec::with_capacity(names.len());
+    let mut issues = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        match CsvColumn::from_name(name) {
+            Some(column) => columns.push(column),
+            None => issues.push(ValidationIssueSerializable {
+                path: format!("{}/{}", path, i),
+                message: format!("unknown column {:?}", name),
+            }),
+        }
+    }
+    if issues.is_empty() {
+        Ok(columns)
+    } else {
+        Err(ErrorPayloadSerializable {
+            error: "Invalid input JSON".to_string(),
+            issues,
+        })
+    }
+}
+
+fn language_from_name(name: &str, path: &str) -> Result<CsvLanguage, ErrorPayloadSerializable> {
+    CsvLanguage::from_name(name).ok_or_else(|| ErrorPayloadSerializable {
+        error: "Invalid input JSON".to_string(),
+        issues: vec![ValidationIssueSerializable {
+            path: path.to_string(),
+            message: format!("unknown language {:?}", name),
+        }],
+    })
+}
+
+fn csv_options_from_item(item: CsvOptionsItem) -> Result<CsvOptions, ErrorPayloadSerializable> {
+    let defaults = CsvOptions::default();
+    let columns = match item.columns {
+        Some(names) => columns_from_names(&names, "/csvOptions/columns")?,
+        None => defaults.columns,
+    };
+    Ok(CsvOptions {
+        columns,
+        language: item
+            .language
+            .as_deref()
+            .map(|name| language_from_name(name, "/csvOptions/language"))
+            .transpose()?
+            .unwrap_or(defaults.language),
+        delimiter: item.delimiter.unwrap_or(defaults.delimiter),
+        decimal_separator: item.decimal_separator.unwrap_or(defaults.decimal_separator),
+        precision: item.precision.or(defaults.precision),
+        include_header: item.include_header.unwrap_or(defaults.include_header),
+        byte_order_mark: item.byte_order_mark.unwrap_or(defaults.byte_order_mark),
+    })
+}
+
+/// Processes the input like process_inventory_history_wasm and returns the
+/// daily rows as CSV, formatted by `csvOptions`.
+#[wasm_bindgen]
+pub fn process_inventory_history_csv_wasm(input_json: &str) -> String {
+    let CsvInputSerializable { input, csv_options } = expect_input(input_json);
+    let options = csv_options_from_item(csv_options.unwrap_or_default())
+        .unwrap_or_else(|e| panic_with_error(&e));
+
+    let output = JsonInput::new(input).process(|_| {});
+    write_inventory_csv(&output.output, &options)
+}
+
+/// Same as process_inventory_history_csv_wasm, for the napi addon.
+pub fn process_inventory_history_csv_native(input_json: &str) -> String {
+    process_inventory_history_csv_wasm(input_json)
+}
+
+#[cfg(feature = "xlsx")]
+#[derive(Deserialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+struct XlsxInputSerializable {
+    #[tsify(type = "HandleInventoryListInputSerializable[]")]
+    inventories: Vec<serde_json::Value>,
+    #[tsify(optional)]
+    xlsx_options: Option<XlsxOptionsItem>,
+}
+
+/// Options of write_inventory_xlsx; absent fields keep the defaults.
+#[cfg(feature = "xlsx")]
+#[derive(Deserialize, Tsify, Default)]
+#[serde(rename_all = "camelCase")]
+struct XlsxOptionsItem {
+    /// Columns of the daily sheet, as in `csvOptions`.
+    #[tsify(optional)]
+    columns: Option<Vec<String>>,
+    #[tsify(type = "'en' | 'vi'", optional)]
+    language: Option<String>,
+}
+
+/// Processes each of `inventories` like process_inventory_history_wasm and
+/// returns an XLSX workbook with summary, daily, goods issue and totals sheets.
+#[cfg(feature = "xlsx")]
+#[wasm_bindgen]
+pub fn process_inventory_history_xlsx_wasm(input_json: &str) -> Vec<u8> {
+    let parse = || -> Result<_, ErrorPayloadSerializable> {
+        let XlsxInputSerializable {
+            inventories,
+            xlsx_options,
+        } = parse_json(input_json)?;
+        let item = xlsx_options.unwrap_or_default();
+        let defaults = XlsxOptions::default();
+        let options = XlsxOptions {
+            columns: match item.columns {
+                Some(names) => columns_from_names(&names, "/xlsxOptions/columns")?,
+                None => defaults.columns,
+            },
+            language: item
+                .language
+                .as_deref()
+                .map(|name| language_from_name(name, "/xlsxOptions/language"))
+                .transpose()?
+                .unwrap_or(defaults.language),
+        };
+        Ok((inputs_from_values(inventories, "/inventories")?, options))
+    };
+    let (inputs, options) = parse().unwrap_or_else(|e| panic_with_error(&e));
+
+    let results: Vec<(String, ProcessInventoryHistoryOutput)> = inputs
+        .into_iter()
+        .map(|input| {
+            let inventory_id = input.inventory_id.clone();
+            (inventory_id, JsonInput::new(input).process(|_| {}).output)
+        })
+        .collect();
+    let items: Vec<BatchResult<'_>> = results
+        .iter()
+        .map(|(inventory_id, output)| BatchResult {
+            inventory_id,
+            output,
+        })
+        .collect();
+    write_inventory_xlsx(&items, &options).unwrap_or_else(|e| {
+        panic_with_error(&ErrorPayloadSerializable {
+            error: "Failed to write XLSX workbook".to_string(),
+            issues: vec![ValidationIssueSerializable {
+                path: String::new(),
+                message: e.to_string(),
+            }],
+        })
+    })
+}
+
+/// Same as process_inventory_history_xlsx_wasm, for the napi addon.
+#[cfg(feature = "xlsx")]
+pub fn process_inventory_history_xlsx_native(input_json: &str) -> Vec<u8> {
+    process_inventory_history_xlsx_wasm(input_json)
+}
+
+/// One NDJSON input line: a header starting the next inventory (same fields as
+/// the JSON input, `inventoryHistoryList` optional) or a history event for it.
+#[derive(Deserialize, Tsify)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum NdjsonInputLine {
+    Header(Box<HandleInventoryListInputSerializable>),
+    Event(InventoryHistoryItem),
+}
+
+#[derive(Serialize, Tsify)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum NdjsonOutputLine<'a> {
+    #[serde(rename_all = "camelCase")]
+    Entry {
+        inventory_id: &'a str,
+        key: String,
+        #[serde(flatten)]
+        entry: Box<ProcessedEntry>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Summary {
+        inventory_id: &'a str,
+        total_duration: i32,
+        warnings: Vec<String>,
+        goods_issue_ledger: HashMap<String, GoodsIssueLedgerSerializable>,
+    },
+}
+
+/// Inventory whose events are being read; only the running state is kept.
+struct NdjsonLot {
+    header: HandleInventoryListInputSerializable,
+    inventory: ProshipInventory,
+    goods_receipt: ProshipGoodsReceipt,
+    /// Built once from the header and lent to the processor of each chunk.
+    inventory_ids: HashMap<String, i32>,
+    options: ProcessOptions,
+    checkpoint: ProcessorCheckpoint,
+    /// Warnings of the chunks applied so far; None before the first one.
+    warnings: Option<Vec<String>>,
+}
+
+impl NdjsonLot {
+    fn new(mut header: HandleInventoryListInputSerializable) -> Self {
+        let history = history_from_items(std::mem::take(&mut header.inventory_history_list));
+        let mut lot = Self {
+            inventory: inventory_from_item(&header.inventory),
+            goods_receipt: goods_receipt_from_item(&header.goods_receipt),
+            options: options_from_input(&header),
+            inventory_ids: std::mem::take(&mut header.inventory_ids_map),
+            header,
+            checkpoint: ProcessorCheckpoint::default(),
+            warnings: None,
+        };
+        lot.apply(history);
+        lot
+    }
+
+    fn processor(&mut self, events: Vec<ProshipInventoryHistory>) -> InventoryHistoryProcessor<'_> {
+        InventoryHistoryProcessor::resume_with_warnings(
+            ProcessInventoryHistoryInput {
+                inventory: &self.inventory,
+                inventory_id: &self.header.inventory_id,
+                asin_outbound_list: &self.header.asin_outbound_list,
+                goods_receipt: &self.goods_receipt,
+                inventory_history_list: events,
+                inventory_ids: std::mem::take(&mut self.inventory_ids),
+                from_date: self.header.from_date,
+                to_date: self.header.to_date,
+                options: std::mem::take(&mut self.options),
+            },
+            std::mem::take(&mut self.checkpoint),
+            self.warnings.take(),
+        )
+    }
+
+    fn apply(&mut self, events: Vec<ProshipInventoryHistory>) {
+        if !events.is_empty() {
+            let parts = self.processor(events).into_parts();
+            self.checkpoint = parts.checkpoint;
+            self.warnings = Some(parts.warnings);
+            self.inventory_ids = parts.inventory_ids;
+            self.options = parts.options;
+        }
+    }
+
+    fn finish(mut self, out: &mut String) {
+        let exact = self.options.numeric.mode == NumericMode::Decimal;
+        let output = self.processor(Vec::new()).finish();
+        let result = result_from_output(output, exact);
+        let inventory_id = self.header.inventory_id.as_str();
+
+        let mut entries: Vec<(String, ProcessedEntry)> = result.entries.into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        for (key, entry) in entries {
+            write_ndjson_line(
+                out,
+                &NdjsonOutputLine::Entry {
+                    inventory_id,
+                    key,
+                    entry: Box::new(entry),
+                },
+            );
+        }
+        write_ndjson_line(
+            out,
+            &NdjsonOutputLine::Summary {
+                inventory_id,
+                total_duration: result.total_duration,
+                warnings: result.warnings,
+                goods_issue_ledger: result.goods_issue_ledger,
+            },
+        );
+    }
+}
+
+fn write_ndjson_line(out: &mut String, line: &NdjsonOutputLine<'_>) {
+    out.push_str(&serde_json::to_string(line).expect("Failed to serialize result"));
+    out.push('\n');
+}
+
+/// Streaming NDJSON processor. Feed input chunks (split anywhere) with `push`;
+/// result lines for an inventory are returned once its next header arrives or
+/// on `finish`. Per inventory: one `entry` line per day, then a `summary` line.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct NdjsonInventoryProcessor {
+    partial: String,
+    line_no: usize,
+    lot: Option<NdjsonLot>,
+    events: Vec<ProshipInventoryHistory>,
+}
+
+#[wasm_bindgen]
+impl NdjsonInventoryProcessor {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consumes the complete lines in `chunk` and returns finished result lines.
+    /// An invalid line rejects the whole chunk and leaves the state as it was,
+    /// so the stream can go on with a corrected chunk.
+    pub fn push(&mut self, chunk: &str) -> Result<String, String> {
+        let Some(end) = chunk.rfind('\n') else {
+            self.partial.push_str(chunk);
+            return Ok(String::new());
+        };
+        let mut text = std::mem::take(&mut self.partial);
+        text.push_str(&chunk[..=end]);
+        let lines = match self.parse_lines(&text) {
+            Ok(lines) => lines,
+            Err(e) => {
+                text.truncate(text.len() - end - 1);
+                self.partial = text;
+                return Err(e);
+            }
+        };
+        self.partial = chunk[end + 1..].to_string();
+        let mut out = String::new();
+        self.apply_lines(lines, &mut out);
+        self.flush_events();
+        Ok(out)
+    }
+
+    /// Ends the input and returns the remaining result lines. The processor can
+    /// then be reused for a new stream.
+    pub fn finish(&mut self) -> Result<String, String> {
+        let lines = self.parse_lines(&self.partial)?;
+        self.partial.clear();
+        let mut out = String::new();
+        self.apply_lines(lines, &mut out);
+        self.finish_lot(&mut out);
+        self.line_no = 0;
+        Ok(out)
+    }
+}
+
+impl NdjsonInventoryProcessor {
+    /// Parses every line of `text` (`None` for blank ones) without touching
+    /// the state.
+    fn parse_lines(&self, text: &str) -> Result<Vec<Option<NdjsonInputLine>>, String> {
+        let mut has_header = self.lot.is_some();
+        text.lines()
+            .enumerate()
+            .map(|(i, line)| {
+                let line_no = self.line_no + i + 1;
+                let line = line.trim();
+                if line.is_empty() {
+                    return Ok(None);
+                }
+                let parsed: NdjsonInputLine = serde_json::from_str(line)
+                    .map_err(|e| format!("Invalid NDJSON line {}: {}", line_no, e))?;
+                match parsed {
+                    NdjsonInputLine::Header(_) => has_header = true,
+                    NdjsonInputLine::Event(_) if !has_header => {
+                        return Err(format!("NDJSON line {}: event before any header", line_no));
+                    }
+                    NdjsonInputLine::Event(_) => {}
+                }
+                Ok(Some(parsed))
+            })
+            .collect()
+    }
+
+    fn apply_lines(&mut self, lines: Vec<Option<NdjsonInputLine>>, out: &mut String) {
+        self.line_no += lines.len();
+        for line in lines.into_iter().flatten() {
+            match line {
+                NdjsonInputLine::Header(header) => {
+                    self.finish_lot(out);
+                    self.lot = Some(NdjsonLot::new(*header));
+                }
+                NdjsonInputLine::Event(item) => {
+                    self.events.extend(history_from_items(vec![item]));
+                }
+            }
+        }
+    }
+
+    fn flush_events(&mut self) {
+        let events = std::mem::take(&mut self.events);
+        if let Some(lot) = self.lot.as_mut() {
+            lot.apply(events);
+        }
+    }
+
+    fn finish_lot(&mut self, out: &mut String) {
+        self.flush_events();
+        if let Some(lot) = self.lot.take() {
+            lot.finish(out);
+        }
+    }
+}
+
+/// Processes a whole NDJSON input (see NdjsonInventoryProcessor) at once.
+#[wasm_bindgen]
+pub fn process_inventory_history_ndjson(input: &str) -> Result<String, String> {
+    let mut processor = NdjsonInventoryProcessor::new();
+    let mut out = processor.push(input)?;
+    out.push_str(&processor.finish()?);
+    Ok(out)
+}
+
+/// JSON Schema of the process_inventory_history_wasm input (`schema` feature).
+#[cfg(feature = "schema")]
+#[wasm_bindgen]
+pub fn input_schema_wasm() -> String {
+    crate::schema::input_schema().to_string()
+}
+
+/// JSON Schema of the process_inventory_history_wasm output (`schema` feature).
+#[cfg(feature = "schema")]
+#[wasm_bindgen]
+pub fn output_schema_wasm() -> String {
+    crate::schema::output_schema().to_string()
+}
+
+/// JSON Schema of the error payload (`schema` feature).
+#[cfg(feature = "schema")]
+#[wasm_bindgen]
+pub fn error_schema_wasm() -> String {
+    crate::schema::error_schema().to_string()
+}
+
+/// Validates an input JSON string against the input schema (`schema` feature).
+/// Returns `null`, or the error payload listing every problem with its path.
+#[cfg(feature = "schema")]
+#[wasm_bindgen]
+pub fn validate_input_wasm(input_json: &str) -> String {
+    let error = parse_input::<HandleInventoryListInputSerializable>(input_json).err();
+    serde_json::to_string(&error).expect("Failed to serialize error")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::{json, Value};
+
+    fn header() -> Value {
+        json!({
+            "inventory": {
+                "id": "inv1",
+                "stockQty": 100,
+                "innerQtyOnMas": 10,
+                "volume": 0.01,
+                "masterVolume": 0.5,
+                // 0.001 m³, off from `volume`: a volume warning.
+                "dimension": { "length": 10, "width": 10, "height": 10 },
+                "unitPrice": 2
+            },
+            "inventoryList": [],
+            "asinOutboundList": [],
+            "goods": null,
+            "goodsReceipt": { "importedAt": "2026-10-01T08:00:00+07:00" },
+            "supplier": null,
+            "customer": null,
+            "receiptOrders": [],
+            "inventoryId": "inv1",
+            "inventoryIdsMap": { "inv1": 1 },
+            "toDate": "2026-10-10"
+        })
+    }
+
+    fn events() -> Vec<Value> {
+        let event = |created_at: &str, stock_qty, old_status, new_status, quantity, gi: &str| {
+            json!({
+                "id": created_at,
+                "createdAt": created_at,
+                "stockQty": stock_qty,
+                "oldStatus": old_status,
+                "newStatus": new_status,
+                "quantity": quantity,
+                "goodsIssueId": gi
+            })
+        };
+        vec![
+            event("2026-10-01T08:00:00+07:00", 100, 7, 1, 100, ""),
+            event("2026-10-02T08:00:00+07:00", 100, 1, 2, 30, "gi1"),
+            // Balances say 70 in stock: warned once.
+            event("2026-10-03T08:00:00+07:00", 75, 2, 6, 30, "gi1"),
+            // Out of order: skipped with a warning.
+            event("2026-10-02T09:00:00+07:00", 75, 1, 3, 5, ""),
+            event("2026-10-05T08:00:00+07:00", 65, 1, 3, 5, ""),
+            event("2026-10-05T08:00:00+07:00", 65, 3, 1, 5, ""),
+        ]
+    }
+
+    #[test]
+    fn ndjson_output_matches_json_output_across_chunks() {
+        let mut input = header();
+        input["inventoryHistoryList"] = Value::Array(events());
+        let json: Value =
+            serde_json::from_str(&process_inventory_history_native(&input.to_string())).unwrap();
+        assert_eq!(
+            json["warnings"].as_array().map(Vec::len),
+            Some(3),
+            "{}",
+            json
+        );
+
+        let mut header = header();
+        header["type"] = json!("header");
+        let mut ndjson = format!("{}\n", header);
+        for mut event in events() {
+            event["type"] = json!("event");
+            ndjson.push_str(&format!("{}\n", event));
+        }
+        // Chunks split inside lines, so every event lands in its own chunk.
+        let mut processor = NdjsonInventoryProcessor::new();
+        let mut out = String::new();
+        for chunk in ndjson.as_bytes().chunks(97) {
+            out.push_str(&processor.push(std::str::from_utf8(chunk).unwrap()).unwrap());
+        }
+        out.push_str(&processor.finish().unwrap());
+
+        let lines: Vec<Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let (summary, entries) = lines.split_last().unwrap();
+        assert_eq!(summary["type"], "summary");
+        assert_eq!(summary["warnings"], json["warnings"]);
+        assert_eq!(summary["totalDuration"], json["totalDuration"]);
+        assert_eq!(summary["goodsIssueLedger"], json["goodsIssueLedger"]);
+        assert_eq!(entries.len(), json["entries"].as_object().unwrap().len());
+        for entry in entries {
+            let key = entry["key"].as_str().unwrap();
+            let mut expected = json["entries"][key].clone();
+            expected["type"] = json!("entry");
+            expected["inventoryId"] = json!("inv1");
+            expected["key"] = json!(key);
+            assert_eq!(*entry, expected, "day {}", key);
+        }
+    }
+
+    fn columnar(created_at: &[f64], goods_issue_index: &[i32]) -> Result<String, String> {
+        let n = created_at.len();
+        let input = header().to_string();
+        let stock_qty: Vec<i32> = (0..n as i32).map(|i| 100 - 10 * i).collect();
+        let mut old_status = vec![1; n];
+        let mut new_status = vec![2; n];
+        old_status[0] = 7;
+        new_status[0] = 1;
+        process_inventory_history_columnar(
+            &input,
+            created_at,
+            &stock_qty,
+            &old_status,
+            &new_status,
+            &vec![10; n],
+            goods_issue_index,
+            vec!["gi1".to_string(), "gi2".to_string()],
+        )
+    }
+
+    #[test]
+    fn columnar_created_at_must_be_whole_seconds() {
+        let day = 86_400.0;
+        let start = 1_790_816_400.0;
+        for (bad, message) in [
+            (f64::NAN, "NaN is not a whole number of seconds"),
+            (f64::INFINITY, "inf is not a whole number of seconds"),
+            (start + 0.5, "1790816400.5 is not a whole number of seconds"),
+        ] {
+            let error: Value = serde_json::from_str(
+                &columnar(&[start, bad, start + 2.0 * day], &[-1, 0, 1]).unwrap_err(),
+            )
+            .unwrap();
+            assert_eq!(error["issues"][0]["path"], "/createdAt/1");
+            assert_eq!(error["issues"][0]["message"], message);
+        }
+        assert!(columnar(&[start, start + day, start + 2.0 * day], &[-1, 0, 1]).is_ok());
+    }
+
+    #[test]
+    fn columnar_events_match_json_events() {
+        let start = 1_790_816_400_i64;
+        let created_at = [start, start + 86_400, start + 2 * 86_400];
+        let mut input = header();
+        let event = |id: &str, created_at, stock_qty, old_status, new_status, gi: &str| {
+            json!({
+                "id": id,
+                "createdAt": created_at,
+                "stockQty": stock_qty,
+                "oldStatus": old_status,
+                "newStatus": new_status,
+                "quantity": 10,
+                "goodsIssueId": gi
+            })
+        };
+        input["inventoryHistoryList"] = json!([
+            event("a", created_at[0], 100, 7, 1, ""),
+            event("b", created_at[1], 90, 1, 2, "gi2"),
+            event("c", created_at[2], 80, 1, 2, "gi1"),
+        ]);
+        let json: Value =
+            serde_json::from_str(&process_inventory_history_native(&input.to_string())).unwrap();
+
+        let created_at: Vec<f64> = created_at.iter().map(|&t| t as f64).collect();
+        let columnar: Value =
+            serde_json::from_str(&columnar(&created_at, &[-1, 1, 0]).unwrap()).unwrap();
+        assert_eq!(columnar, json);
+    }
+}
@@ -0,0 +1,507 @@
+//! XLSX statement workbook: summary, daily movements, goods issues and totals
+//! sheets built from the results of several inventories.
+
+use crate::csv_export::{CsvColumn, CsvLanguage, CsvValue};
+use crate::process_inventory_list::{
+    BatchResult, Decimal, InventoryData, ProcessInventoryHistoryOutput,
+};
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, Worksheet, XlsxError};
+use std::collections::BTreeMap;
+
+#[derive(Clone, PartialEq)]
+pub struct XlsxOptions {
+    /// Columns of the daily sheet, after the inventory id.
+    pub columns: Vec<CsvColumn>,
+    /// Language of sheet names and headers.
+    pub language: CsvLanguage,
+}
+
+impl Default for XlsxOptions {
+    fn default() -> Self {
+        Self {
+            columns: CsvColumn::default_columns(),
+            language: CsvLanguage::English,
+        }
+    }
+}
+
+/// Daily rows of one output sorted by key, with their sorted goods issue ids.
+fn sorted_rows(
+    output: &ProcessInventoryHistoryOutput,
+) -> Vec<(&String, &InventoryData, Vec<&String>)> {
+    let mut rows: Vec<_> = output
+        .merged_inventory_history
+        .iter()
+        .map(|(key, (data, goods_issue_ids))| {
+            let mut goods_issue_ids: Vec<&String> = goods_issue_ids.iter().collect();
+            goods_issue_ids.sort();
+            (key, data, goods_issue_ids)
+        })
+        .collect();
+    rows.sort_by(|a, b| a.0.cmp(b.0));
+    rows
+}
+
+/// Movement totals of one inventory over its daily rows.
+#[derive(Default)]
+struct InventoryTotals {
+    opening_stock: i64,
+    inbound_qty: i64,
+    outbound_qty: i64,
+    disposal_stock: i64,
+    restore_stock_qty: i64,
+    closing_stock: i64,
+    closing_cbm: Decimal,
+}
+
+/// Stock and CBM at the end of a row. The row added for today (or `toDate`)
+/// when no event falls on it carries the stock in its opening figures and
+/// leaves the closing ones at zero.
+fn closing_figures(d: &InventoryData) -> (i32, Decimal) {
+    let carried_forward = d.closing_stock == 0
+        && d.inbound_qty == 0
+        && d.outbound_qty == 0
+        && d.disposal_stock == 0
+        && d.restore_stock_qty == 0
+        && d.allocated_qty == 0;
+    if carried_forward {
+        (d.opening_stock, d.get_opening_cbm_decimal())
+    } else {
+        (d.closing_stock, d.get_closing_cbm_decimal())
+    }
+}
+
+impl InventoryTotals {
+    fn from_rows(rows: &[(&String, &InventoryData, Vec<&String>)]) -> Self {
+        let (closing_stock, closing_cbm) = rows
+            .last()
+            .map_or((0, Decimal::default()), |r| closing_figures(r.1));
+        let mut totals = Self {
+            opening_stock: rows.first().map_or(0, |r| r.1.opening_stock as i64),
+            closing_stock: closing_stock as i64,
+            closing_cbm,
+            ..Default::default()
+        };
+        for (_, d, _) in rows {
+            totals.inbound_qty += d.inbound_qty as i64;
+            totals.outbound_qty += d.outbound_qty as i64;
+            totals.disposal_stock += d.disposal_stock as i64;
+            totals.restore_stock_qty += d.restore_stock_qty as i64;
+        }
+        totals
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.opening_stock += other.opening_stock;
+        self.inbound_qty += other.inbound_qty;
+        self.outbound_qty += other.outbound_qty;
+        self.disposal_stock += other.disposal_stock;
+        self.restore_stock_qty += other.restore_stock_qty;
+        self.closing_stock += other.closing_stock;
+        self.closing_cbm = self.closing_cbm + other.closing_cbm;
+    }
+}
+
+struct Formats {
+    header: Format,
+    date: Format,
+    cbm: Format,
+}
+
+fn label(language: CsvLanguage, english: &'static str, vietnamese: &'static str) -> &'static str {
+    match language {
+        CsvLanguage::English => english,
+        CsvLanguage::Vietnamese => vietnamese,
+    }
+}
+
+/// yyyymmdd row key as an Excel date.
+fn key_date(key: &str) -> Option<ExcelDateTime> {
+    if key.len() != 8 {
+        return None;
+    }
+    let year = key[0..4].parse().ok()?;
+    let month = key[4..6].parse().ok()?;
+    let day = key[6..8].parse().ok()?;
+    ExcelDateTime::from_ymd(year, month, day).ok()
+}
+
+fn write_key(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    key: &str,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    match key_date(key) {
+        Some(date) => sheet.write_datetime_with_format(row, col, &date, &formats.date)?,
+        None => sheet.write_string(row, col, key)?,
+    };
+    Ok(())
+}
+
+fn write_header(
+    sheet: &mut Worksheet,
+    headers: &[&str],
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    for (col, header) in headers.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, &formats.header)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    Ok(())
+}
+
+fn write_summary_sheet(
+    sheet: &mut Worksheet,
+    items: &[BatchResult<'_>],
+    language: CsvLanguage,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    let l = |english, vietnamese| label(language, english, vietnamese);
+    sheet.set_name(l("Summary", "Tổng hợp"))?;
+    write_header(
+        sheet,
+        &[
+            l("Inventory ID", "Mã tồn kho"),
+            "ASIN",
+            l("From", "Từ ngày"),
+            l("To", "Đến ngày"),
+            l("Days", "Số ngày"),
+            l("Opening stock", "Tồn đầu kỳ"),
+            l("Inbound qty", "SL nhập"),
+            l("Outbound qty", "SL xuất"),
+            l("Disposal qty", "SL hủy"),
+            l("Restored qty", "SL hoàn lại"),
+            l("Closing stock", "Tồn cuối kỳ"),
+            l("Closing CBM", "CBM cuối kỳ"),
+            l("Storage days", "Số ngày lưu kho"),
+            l("Goods issues", "Số phiếu xuất"),
+            l("Warnings", "Cảnh báo"),
+        ],
+        formats,
+    )?;
+
+    for (i, item) in items.iter().enumerate() {
+        let row = i as u32 + 1;
+        let rows = sorted_rows(item.output);
+        let totals = InventoryTotals::from_rows(&rows);
+        let asin = rows
+            .iter()
+            .map(|r| r.1.asin.as_str())
+            .find(|asin| !asin.is_empty())
+            .unwrap_or("");
+
+        sheet.write_string(row, 0, item.inventory_id)?;
+        sheet.write_string(row, 1, asin)?;
+        if let (Some(first), Some(last)) = (rows.first(), rows.last()) {
+            write_key(sheet, row, 2, first.0, formats)?;
+            write_key(sheet, row, 3, last.0, formats)?;
+        }
+        sheet.write_number(row, 4, rows.len() as f64)?;
+        sheet.write_number(row, 5, totals.opening_stock as f64)?;
+        sheet.write_number(row, 6, totals.inbound_qty as f64)?;
+        sheet.write_number(row, 7, totals.outbound_qty as f64)?;
+        sheet.write_number(row, 8, totals.disposal_stock as f64)?;
+        sheet.write_number(row, 9, totals.restore_stock_qty as f64)?;
+        sheet.write_number(row, 10, totals.closing_stock as f64)?;
+        sheet.write_number_with_format(row, 11, totals.closing_cbm.to_f64(), &formats.cbm)?;
+        sheet.write_number(row, 12, item.output.total_duration as f64)?;
+        sheet.write_number(row, 13, goods_issue_days(item.output).len() as f64)?;
+        sheet.write_string(row, 14, item.output.warnings.join("\n"))?;
+    }
+    sheet.autofit();
+    Ok(())
+}
+
+fn write_daily_sheet(
+    sheet: &mut Worksheet,
+    items: &[BatchResult<'_>],
+    options: &XlsxOptions,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    let language = options.language;
+    sheet.set_name(label(language, "Daily", "Chi tiết ngày"))?;
+    let mut headers = vec![label(language, "Inventory ID", "Mã tồn kho")];
+    headers.extend(options.columns.iter().map(|c| c.header(language)));
+    write_header(sheet, &headers, formats)?;
+
+    let mut row = 1;
+    for item in items {
+        for (key, data, goods_issue_ids) in sorted_rows(item.output) {
+            sheet.write_string(row, 0, item.inventory_id)?;
+            for (i, column) in options.columns.iter().enumerate() {
+                let col = i as u16 + 1;
+                if *column == CsvColumn::Key {
+                    write_key(sheet, row, col, key, formats)?;
+                    continue;
+                }
+                match column.value(key, data, &goods_issue_ids) {
+                    CsvValue::Int(v) => sheet.write_number(row, col, v as f64)?,
+                    CsvValue::F32(v) => sheet.write_number(row, col, v as f64)?,
+                    CsvValue::F64(v) => sheet.write_number(row, col, v)?,
+                    CsvValue::Decimal(v) => sheet.write_number(row, col, v.to_f64())?,
+                    CsvValue::Text(v) => sheet.write_string(row, col, v)?,
+                };
+            }
+            row += 1;
+        }
+    }
+    sheet.autofit();
+    Ok(())
+}
+
+/// Days on which each goods issue appears in the daily rows, from their
+/// `goods_issue_ids` sets.
+fn goods_issue_days(output: &ProcessInventoryHistoryOutput) -> BTreeMap<&String, Vec<&String>> {
+    let mut days: BTreeMap<&String, Vec<&String>> = BTreeMap::new();
+    for (key, (_, goods_issue_ids)) in &output.merged_inventory_history {
+        for id in goods_issue_ids {
+            days.entry(id).or_default().push(key);
+        }
+    }
+    for keys in days.values_mut() {
+        keys.sort();
+    }
+    days
+}
+
+fn write_goods_issue_sheet(
+    sheet: &mut Worksheet,
+    items: &[BatchResult<'_>],
+    language: CsvLanguage,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    let l = |english, vietnamese| label(language, english, vietnamese);
+    sheet.set_name(l("Goods issues", "Phiếu xuất"))?;
+    write_header(
+        sheet,
+        &[
+            l("Inventory ID", "Mã tồn kho"),
+            l("Goods issue ID", "Mã phiếu xuất"),
+            l("First day", "Ngày đầu"),
+            l("Last day", "Ngày cuối"),
+            l("Days", "Số ngày"),
+            l("Allocated qty", "SL đã phân bổ"),
+            l("Released qty", "SL đã nhả"),
+            l("Shipped qty", "SL đã giao"),
+            l("Disposed qty", "SL đã hủy"),
+            l("Open qty", "SL còn lại"),
+        ],
+        formats,
+    )?;
+
+    let mut row = 1;
+    for item in items {
+        for (id, keys) in goods_issue_days(item.output) {
+            sheet.write_string(row, 0, item.inventory_id)?;
+            sheet.write_string(row, 1, id)?;
+            if let (Some(first), Some(last)) = (keys.first(), keys.last()) {
+                write_key(sheet, row, 2, first, formats)?;
+                write_key(sheet, row, 3, last, formats)?;
+            }
+            sheet.write_number(row, 4, keys.len() as f64)?;
+            if let Some(ledger) = item.output.goods_issue_ledger.get(id) {
+                sheet.write_number(row, 5, ledger.allocated_qty as f64)?;
+                sheet.write_number(row, 6, ledger.released_qty as f64)?;
+                sheet.write_number(row, 7, ledger.shipped_qty as f64)?;
+                sheet.write_number(row, 8, ledger.disposed_qty as f64)?;
+                sheet.write_number(row, 9, ledger.get_open_qty() as f64)?;
+            }
+            row += 1;
+        }
+    }
+    sheet.autofit();
+    Ok(())
+}
+
+fn write_totals_sheet(
+    sheet: &mut Worksheet,
+    items: &[BatchResult<'_>],
+    language: CsvLanguage,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    let l = |english, vietnamese| label(language, english, vietnamese);
+    sheet.set_name(l("Totals", "Tổng cộng"))?;
+    write_header(sheet, &[l("Item", "Mục"), l("Value", "Giá trị")], formats)?;
+
+    let mut totals = InventoryTotals::default();
+    let mut total_duration = 0i64;
+    let mut goods_issues = 0usize;
+    let mut warnings = 0usize;
+    for item in items {
+        totals.add(&InventoryTotals::from_rows(&sorted_rows(item.output)));
+        total_duration += item.output.total_duration as i64;
+        goods_issues += goods_issue_days(item.output).len();
+        warnings += item.output.warnings.len();
+    }
+
+    let lines = [
+        (l("Inventories", "Số tồn kho"), items.len() as f64),
+        (
+            l("Opening stock", "Tồn đầu kỳ"),
+            totals.opening_stock as f64,
+        ),
+        (l("Inbound qty", "SL nhập"), totals.inbound_qty as f64),
+        (l("Outbound qty", "SL xuất"), totals.outbound_qty as f64),
+        (l("Disposal qty", "SL hủy"), totals.disposal_stock as f64),
+        (
+            l("Restored qty", "SL hoàn lại"),
+            totals.restore_stock_qty as f64,
+        ),
+        (
+            l("Closing stock", "Tồn cuối kỳ"),
+            totals.closing_stock as f64,
+        ),
+        (l("Closing CBM", "CBM cuối kỳ"), totals.closing_cbm.to_f64()),
+        (
+            l("Total storage days", "Tổng số ngày lưu kho"),
+            total_duration as f64,
+        ),
+        (l("Goods issues", "Số phiếu xuất"), goods_issues as f64),
+        (l("Warnings", "Cảnh báo"), warnings as f64),
+    ];
+    for (i, (name, value)) in lines.into_iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_string(row, 0, name)?;
+        sheet.write_number(row, 1, value)?;
+    }
+    sheet.autofit();
+    Ok(())
+}
+
+/// XLSX workbook (file bytes) with a summary row per inventory, its daily
+/// rows, the goods issues seen in the rows and the totals of all inventories.
+/// Fails when the rows don't fit in a sheet.
+pub fn write_inventory_xlsx(
+    items: &[BatchResult<'_>],
+    options: &XlsxOptions,
+) -> Result<Vec<u8>, XlsxError> {
+    let formats = Formats {
+        header: Format::new().set_bold(),
+        date: Format::new().set_num_format("yyyy-mm-dd"),
+        cbm: Format::new().set_num_format("0.000"),
+    };
+    let mut workbook = Workbook::new();
+    write_summary_sheet(workbook.add_worksheet(), items, options.language, &formats)?;
+    write_daily_sheet(workbook.add_worksheet(), items, options, &formats)?;
+    write_goods_issue_sheet(workbook.add_worksheet(), items, options.language, &formats)?;
+    write_totals_sheet(workbook.add_worksheet(), items, options.language, &formats)?;
+    workbook.save_to_buffer()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_inventory_list::{
+        process_inventory_history_data, EnumProshipType_InventoryStatus::*,
+        ProcessInventoryHistoryInput, ProcessOptions, ProshipGoodsReceipt, ProshipInventory,
+        ProshipInventoryHistory,
+    };
+    use calamine::{Data, Reader, Xlsx};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    const DAY: i64 = 24 * 3600;
+    // 2024-03-01 00:00 at GMT+7.
+    const START: i64 = 1_709_226_000;
+
+    fn output(to_date: i64) -> ProcessInventoryHistoryOutput {
+        let inventory = ProshipInventory {
+            id: "i1".to_string(),
+            inner_qty_on_mas: 10,
+            volume: 0.01,
+            ..Default::default()
+        };
+        let event =
+            |created_at, stock_qty, old_status, new_status, quantity| ProshipInventoryHistory {
+                created_at,
+                stock_qty,
+                old_status,
+                new_status,
+                quantity,
+                goods_issue_id: "gi1".to_string(),
+            };
+        process_inventory_history_data(ProcessInventoryHistoryInput {
+            inventory: &inventory,
+            inventory_id: "i1",
+            asin_outbound_list: &[],
+            goods_receipt: &ProshipGoodsReceipt {
+                imported_at: START + 3600,
+            },
+            inventory_history_list: vec![
+                event(
+                    START + 3600,
+                    100,
+                    PENDING_FOR_IMPORT_INVENTORY_STATUS,
+                    AVALABLE_INVENTORY_STATUS,
+                    100,
+                ),
+                event(
+                    START + DAY + 3600,
+                    100,
+                    AVALABLE_INVENTORY_STATUS,
+                    ON_HAND_INVENTORY_STATUS,
+                    30,
+                ),
+                event(
+                    START + 2 * DAY + 3600,
+                    70,
+                    ON_HAND_INVENTORY_STATUS,
+                    EXPORTED_INVENTORY_STATUS,
+                    30,
+                ),
+            ],
+            inventory_ids: HashMap::from([("i1".to_string(), 100)]),
+            from_date: None,
+            to_date: Some(to_date),
+            options: ProcessOptions {
+                as_of: Some(START + 10 * DAY),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Values of the Totals sheet by item name.
+    fn totals(outputs: &[ProcessInventoryHistoryOutput]) -> HashMap<String, f64> {
+        let items: Vec<BatchResult<'_>> = outputs
+            .iter()
+            .map(|output| BatchResult {
+                inventory_id: "i1",
+                output,
+            })
+            .collect();
+        let bytes = write_inventory_xlsx(&items, &XlsxOptions::default()).unwrap();
+        let mut workbook: Xlsx<_> = Xlsx::new(Cursor::new(bytes)).unwrap();
+        let range = workbook.worksheet_range("Totals").unwrap();
+        range
+            .rows()
+            .skip(1)
+            .map(|row| match (&row[0], &row[1]) {
+                (Data::String(name), Data::Float(value)) => (name.clone(), *value),
+                other => panic!("unexpected totals row {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn totals_close_with_the_stock_carried_to_the_last_day() {
+        // The last row is the one added for toDate, after the last event.
+        let carried = output(START + 5 * DAY);
+        let last_day = carried
+            .merged_inventory_history
+            .values()
+            .map(|(d, _)| d)
+            .max_by_key(|d| d.date)
+            .unwrap();
+        assert_eq!(last_day.closing_stock, 0);
+
+        let totals = totals(&[carried, output(START + 2 * DAY + 7200)]);
+        assert_eq!(totals["Inventories"], 2.0);
+        assert_eq!(totals["Opening stock"], 0.0);
+        assert_eq!(totals["Inbound qty"], 200.0);
+        assert_eq!(totals["Outbound qty"], 60.0);
+        assert_eq!(totals["Closing stock"], 140.0);
+        assert_eq!(totals["Closing CBM"], 1.4);
+    }
+}
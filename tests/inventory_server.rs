@@ -0,0 +1,138 @@
+//! Runs the inventory-server binary on a free port and talks HTTP to it.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+const MAX_BODY_BYTES: usize = 4096;
+const MAX_BATCH: usize = 2;
+
+/// The running server, killed when dropped.
+struct Server {
+    child: Child,
+    address: String,
+}
+
+impl Server {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_inventory-server"))
+            .args(["--listen", "127.0.0.1:0", "--threads", "2"])
+            .args(["--max-body-bytes", &MAX_BODY_BYTES.to_string()])
+            .args(["--max-batch", &MAX_BATCH.to_string()])
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to start inventory-server");
+        let mut stderr = BufReader::new(child.stderr.take().unwrap());
+        let mut line = String::new();
+        stderr.read_line(&mut line).unwrap();
+        let address = line
+            .trim()
+            .strip_prefix("inventory-server: listening on http://")
+            .unwrap_or_else(|| panic!("unexpected first line {:?}", line))
+            .to_string();
+        // Keep reading the request log so the server never blocks on it.
+        thread::spawn(move || std::io::copy(&mut stderr, &mut std::io::sink()));
+        Self { child, address }
+    }
+
+    /// Status and JSON body of one request.
+    fn request(&self, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            self.address,
+            body.len(),
+            body
+        );
+        // The server may answer (e.g. 413) and close before reading it all.
+        let _ = stream.write_all(request.as_bytes());
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn input(id: &str) -> Value {
+    json!({
+        "inventory": { "id": id, "stockQty": 100, "innerQtyOnMas": 1, "volume": 0.01 },
+        "inventoryList": [],
+        "asinOutboundList": [],
+        "goods": null,
+        "goodsReceipt": { "importedAt": "2026-10-01T08:00:00+07:00" },
+        "supplier": null,
+        "customer": null,
+        "inventoryHistoryList": [{
+            "id": "a",
+            "createdAt": "2026-10-01T08:00:00+07:00",
+            "stockQty": 100,
+            "oldStatus": 7,
+            "newStatus": 1,
+            "quantity": 100
+        }],
+        "receiptOrders": [],
+        "inventoryId": id,
+        "inventoryIdsMap": { id: 1 },
+        "toDate": "2026-10-03"
+    })
+}
+
+#[test]
+fn serves_health_version_and_process() {
+    let server = Server::start();
+
+    assert_eq!(
+        server.request("GET", "/health", ""),
+        (200, json!({ "status": "ok" }))
+    );
+    let (status, version) = server.request("GET", "/version", "");
+    assert_eq!(status, 200);
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+
+    let (status, single) = server.request("POST", "/process", &input("a").to_string());
+    assert_eq!(status, 200);
+    assert!(single["entries"].as_object().is_some_and(|e| !e.is_empty()));
+
+    let batch = json!([input("a"), input("b")]).to_string();
+    let (status, results) = server.request("POST", "/process", &batch);
+    assert_eq!(status, 200);
+    assert_eq!(results.as_array().map(Vec::len), Some(2));
+    assert_eq!(results[0], single);
+
+    let (status, error) = server.request("POST", "/process", r#"{"inventoryId": 1}"#);
+    assert_eq!(status, 400);
+    assert!(error["error"].is_string());
+}
+
+#[test]
+fn rejects_large_requests_and_unknown_routes() {
+    let server = Server::start();
+
+    let padded = format!("{}{}", input("a"), " ".repeat(MAX_BODY_BYTES));
+    let (status, error) = server.request("POST", "/process", &padded);
+    assert_eq!(status, 413);
+    assert!(error["error"].as_str().unwrap().contains("bytes"));
+
+    let batch = json!([input("a"), input("b"), input("c")]).to_string();
+    assert!(batch.len() <= MAX_BODY_BYTES);
+    let (status, error) = server.request("POST", "/process", &batch);
+    assert_eq!(status, 413);
+    assert!(error["error"].as_str().unwrap().contains("inputs"));
+
+    assert_eq!(server.request("GET", "/process", "").0, 405);
+    assert_eq!(server.request("POST", "/health", "").0, 405);
+    assert_eq!(server.request("GET", "/nothing", "").0, 404);
+}